@@ -0,0 +1,50 @@
+//! Checksums used by protocol v5 segments, compatible with the ones used by Cassandra.
+
+const CRC24_INIT: u32 = 0x875060;
+const CRC24_POLY: u32 = 0x1974F0B;
+
+/// Initial bytes fed to CRC32 before the actual data - Cassandra does it to avoid zero checksums
+/// for empty and zero-filled payloads.
+const CRC32_INITIAL_BYTES: [u8; 4] = [0xfa, 0x2d, 0x55, 0xca];
+
+/// Computes CRC24 of given bytes. Used to protect segment headers.
+pub fn crc24(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(CRC24_INIT, |mut crc, byte| {
+        crc ^= (*byte as u32) << 16;
+
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+
+        crc
+    }) & 0xffffff
+}
+
+/// Computes CRC32 of given bytes. Used to protect segment payloads.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&CRC32_INITIAL_BYTES);
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc24() {
+        assert_eq!(crc24(&[]), CRC24_INIT);
+        assert_eq!(crc24(&[0x1e, 0x00, 0x02]), 0x949a4b);
+        assert_ne!(crc24(&[0x1e, 0x00, 0x02]), crc24(&[0x1e, 0x00, 0x00]));
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(&[]), 0x44777ed3);
+        assert_ne!(crc32(&[1, 2, 3]), crc32(&[1, 2, 4]));
+    }
+}
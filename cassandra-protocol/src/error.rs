@@ -8,6 +8,7 @@ use uuid::Error as UuidError;
 
 use crate::compression::CompressionError;
 use crate::frame::frame_error::ErrorBody;
+use crate::frame::Version;
 
 pub type Result<T> = result::Result<T, Error>;
 
@@ -42,6 +43,9 @@ pub enum Error {
     /// Timed out waiting for an operation to complete.
     #[error("Timeout: {0}")]
     Timeout(String),
+    /// Protocol version is not supported by the server.
+    #[error("Unsupported protocol version: {0}")]
+    UnsupportedProtocolVersion(Version),
 }
 
 pub fn column_is_empty_err<T: Display>(column_name: T) -> Error {
@@ -1,7 +1,10 @@
 //! `frame` module contains general Frame functionality.
 use bitflags::bitflags;
+use bytes::Bytes;
 use derivative::Derivative;
 use derive_more::{Constructor, Display};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::Cursor;
 use uuid::Uuid;
@@ -10,7 +13,10 @@ use crate::compression::{Compression, CompressionError};
 use crate::frame::frame_request::RequestBody;
 use crate::frame::frame_response::ResponseBody;
 use crate::types::data_serialization_types::decode_timeuuid;
-use crate::types::{from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes, UUID_LEN};
+use crate::types::{
+    from_cursor_bytes_map, from_cursor_string_list, serialize_bytes_map, try_i16_from_bytes,
+    try_i32_from_bytes, UUID_LEN,
+};
 
 pub use crate::frame::traits::*;
 
@@ -40,6 +46,7 @@ pub mod frame_response;
 pub mod frame_result;
 pub mod frame_startup;
 pub mod frame_supported;
+pub mod segment;
 pub mod traits;
 
 use crate::error;
@@ -48,7 +55,7 @@ pub const EVENT_STREAM_ID: i16 = -1;
 
 pub type StreamId = i16;
 
-#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Constructor)]
+#[derive(Debug, Clone, Eq, PartialEq, Constructor)]
 pub struct ParsedFrame {
     /// How many bytes from the buffer have been read.
     pub frame_len: usize,
@@ -56,7 +63,7 @@ pub struct ParsedFrame {
     pub frame: Frame,
 }
 
-#[derive(Derivative, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Derivative, Clone, PartialEq, Eq)]
 #[derivative(Debug)]
 pub struct Frame {
     pub version: Version,
@@ -65,9 +72,11 @@ pub struct Frame {
     pub opcode: Opcode,
     pub stream_id: StreamId,
     #[derivative(Debug = "ignore")]
-    pub body: Vec<u8>,
+    pub body: Bytes,
     pub tracing_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    /// Custom payload sent to or received from a server. Encoded only if not empty.
+    pub custom_payload: HashMap<String, Vec<u8>>,
 }
 
 impl Frame {
@@ -79,9 +88,10 @@ impl Frame {
         flags: Flags,
         opcode: Opcode,
         stream_id: StreamId,
-        body: Vec<u8>,
+        body: Bytes,
         tracing_id: Option<Uuid>,
         warnings: Vec<String>,
+        custom_payload: HashMap<String, Vec<u8>>,
     ) -> Self {
         Frame {
             version,
@@ -92,17 +102,20 @@ impl Frame {
             body,
             tracing_id,
             warnings,
+            custom_payload,
         }
     }
 
     #[inline]
     pub fn request_body(&self) -> error::Result<RequestBody> {
-        RequestBody::try_from(self.body.as_slice(), self.opcode)
+        RequestBody::try_from(&self.body, self.opcode, self.version)
     }
 
+    /// Parses the response body. Row values in the result share the frame body buffer, instead of
+    /// copying it.
     #[inline]
     pub fn response_body(&self) -> error::Result<ResponseBody> {
-        ResponseBody::try_from(self.body.as_slice(), self.opcode, self.version)
+        ResponseBody::try_from(&self.body, self.opcode, self.version)
     }
 
     #[inline]
@@ -115,6 +128,11 @@ impl Frame {
         &self.warnings
     }
 
+    #[inline]
+    pub fn custom_payload(&self) -> &HashMap<String, Vec<u8>> {
+        &self.custom_payload
+    }
+
     /// Parses the raw bytes of a cassandra frame returning a [`Frame`] struct.
     /// The typical use case is reading from a buffer that may contain 0 or more frames and where the last frame may be incomplete.
     /// The possible return values are:
@@ -152,9 +170,7 @@ impl Frame {
         }
         .map_err(ParseFrameError::DecompressionError)?;
 
-        let body_len = full_body.len();
-
-        // Use cursor to get tracing id, warnings and actual body
+        // Use cursor to get tracing id, warnings, custom payload and actual body
         let mut body_cursor = Cursor::new(full_body.as_slice());
 
         let tracing_id = if flags.contains(Flags::TRACING) {
@@ -172,10 +188,15 @@ impl Frame {
             vec![]
         };
 
-        let mut body = Vec::with_capacity(body_len - body_cursor.position() as usize);
+        let custom_payload = if flags.contains(Flags::CUSTOM_PAYLOAD) {
+            from_cursor_bytes_map(&mut body_cursor)
+                .map_err(ParseFrameError::InvalidCustomPayload)?
+        } else {
+            HashMap::new()
+        };
 
-        std::io::Read::read_to_end(&mut body_cursor, &mut body)
-            .expect("Read cannot fail because cursor is backed by slice");
+        let body_start = body_cursor.position() as usize;
+        let body = Bytes::from(full_body).slice(body_start..);
 
         Ok(ParsedFrame::new(
             frame_len,
@@ -188,13 +209,28 @@ impl Frame {
                 body,
                 tracing_id,
                 warnings,
+                custom_payload,
             },
         ))
     }
 
+    /// Encodes the frame using given compression. Note: protocol v5 frames are never compressed
+    /// on their own - compression is applied to whole segments instead (see
+    /// [`segment`](crate::frame::segment)).
     pub fn encode_with(&self, compressor: Compression) -> error::Result<Vec<u8>> {
+        let is_compressed = compressor.is_compressed() && self.version < Version::V5;
+
         let combined_version_byte = u8::from(self.version) | u8::from(self.direction);
-        let flag_byte = self.flags.bits();
+        let flags = if self.custom_payload.is_empty() {
+            self.flags - Flags::CUSTOM_PAYLOAD
+        } else {
+            self.flags | Flags::CUSTOM_PAYLOAD
+        };
+        let flag_byte = if is_compressed {
+            (flags | Flags::COMPRESSION).bits()
+        } else {
+            flags.bits()
+        };
         let opcode_byte = u8::from(self.opcode);
 
         let mut v = Vec::with_capacity(9);
@@ -204,16 +240,26 @@ impl Frame {
         v.extend_from_slice(&self.stream_id.to_be_bytes());
         v.push(opcode_byte);
 
-        if compressor.is_compressed() {
-            let mut encoded_body = compressor.encode(&self.body)?;
+        let body = if self.custom_payload.is_empty() {
+            Cow::Borrowed(self.body.as_ref())
+        } else {
+            let mut body = Vec::with_capacity(self.body.len());
+            serialize_bytes_map(&mut Cursor::new(&mut body), &self.custom_payload);
+            body.extend_from_slice(&self.body);
+
+            Cow::Owned(body)
+        };
+
+        if is_compressed {
+            let mut encoded_body = compressor.encode(&body)?;
 
             let body_len = encoded_body.len() as i32;
             v.extend_from_slice(&body_len.to_be_bytes());
             v.append(&mut encoded_body);
         } else {
-            let body_len = self.body.len() as i32;
+            let body_len = body.len() as i32;
             v.extend_from_slice(&body_len.to_be_bytes());
-            v.extend_from_slice(&self.body);
+            v.extend_from_slice(&body);
         }
 
         Ok(v)
@@ -230,14 +276,14 @@ pub enum ParseFrameError {
     DecompressionError(CompressionError),
     InvalidUuid(uuid::Error),
     InvalidWarnings(error::Error),
+    InvalidCustomPayload(error::Error),
 }
 
 #[derive(Debug, PartialEq, Copy, Clone, Ord, PartialOrd, Eq, Hash, Display)]
 pub enum Version {
     V3,
     V4,
-    // enable v5 feature when it's actually implemented
-    //V5,
+    V5,
 }
 
 impl From<Version> for u8 {
@@ -245,7 +291,7 @@ impl From<Version> for u8 {
         match value {
             Version::V3 => 3,
             Version::V4 => 4,
-            //Version::V5 => 5,
+            Version::V5 => 5,
         }
     }
 }
@@ -257,7 +303,7 @@ impl TryFrom<u8> for Version {
         match version & 0x7F {
             3 => Ok(Version::V3),
             4 => Ok(Version::V4),
-            //5 => Ok(Version::V5),
+            5 => Ok(Version::V5),
             v => Err(error::Error::General(format!(
                 "Unknown cassandra version: {}",
                 v
@@ -406,7 +452,7 @@ mod tests {
     fn test_frame_version_as_byte() {
         assert_eq!(u8::from(Version::V3), 0x03);
         assert_eq!(u8::from(Version::V4), 0x04);
-        //assert_eq!(u8::from(Version::V5), 0x05);
+        assert_eq!(u8::from(Version::V5), 0x05);
 
         assert_eq!(u8::from(Direction::Request), 0x00);
         assert_eq!(u8::from(Direction::Response), 0x80);
@@ -418,8 +464,8 @@ mod tests {
         assert_eq!(Version::try_from(0x83).unwrap(), Version::V3);
         assert_eq!(Version::try_from(0x04).unwrap(), Version::V4);
         assert_eq!(Version::try_from(0x84).unwrap(), Version::V4);
-        //assert_eq!(Version::try_from(0x05).unwrap(), Version::V5);
-        //assert_eq!(Version::try_from(0x85).unwrap(), Version::V5);
+        assert_eq!(Version::try_from(0x05).unwrap(), Version::V5);
+        assert_eq!(Version::try_from(0x85).unwrap(), Version::V5);
 
         assert_eq!(Direction::from(0x03), Direction::Request);
         assert_eq!(Direction::from(0x04), Direction::Request);
@@ -495,7 +541,7 @@ mod tests {
 
     fn test_encode_decode_roundtrip_request(raw_frame: &[u8], frame: Frame, body: RequestBody) {
         // test encode
-        let encoded_body = body.serialize_to_vec();
+        let encoded_body = body.serialize_to_vec(frame.version);
         assert_eq!(
             &frame.body, &encoded_body,
             "encoded body did not match frames body"
@@ -520,7 +566,7 @@ mod tests {
     /// Use this when the body binary representation is nondeterministic but the body typed representation is deterministic
     fn test_encode_decode_roundtrip_nondeterministic_request(mut frame: Frame, body: RequestBody) {
         // test encode
-        frame.body = body.serialize_to_vec();
+        frame.body = body.serialize_to_vec(frame.version).into();
 
         // test decode
         let decoded_body = frame.request_body().unwrap();
@@ -536,9 +582,10 @@ mod tests {
             flags: Flags::empty(),
             opcode: Opcode::Ready,
             stream_id: 0,
-            body: vec![],
+            body: Bytes::from_static(&[]),
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = ResponseBody::Ready;
         test_encode_decode_roundtrip_response(&raw_frame, frame, body);
@@ -555,9 +602,10 @@ mod tests {
             flags: Flags::empty(),
             opcode: Opcode::Query,
             stream_id: 0,
-            body: vec![0, 0, 0, 4, 98, 108, 97, 104, 0, 0, 64],
+            body: Bytes::from_static(&[0, 0, 0, 4, 98, 108, 97, 104, 0, 0, 64]),
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "blah".into(),
@@ -569,6 +617,8 @@ mod tests {
                 paging_state: None,
                 serial_consistency: None,
                 timestamp: None,
+                keyspace: None,
+                now_in_seconds: None,
             },
         });
         test_encode_decode_roundtrip_request(&raw_frame, frame, body);
@@ -586,12 +636,13 @@ mod tests {
             flags: Flags::empty(),
             opcode: Opcode::Query,
             stream_id: 0,
-            body: vec![
+            body: Bytes::from_static(&[
                 0, 0, 0, 10, 115, 111, 109, 101, 32, 113, 117, 101, 114, 121, 0, 8, 1, 0, 2, 0, 0,
                 0, 3, 1, 2, 3, 255, 255, 255, 255,
-            ],
+            ]),
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "some query".into(),
@@ -606,6 +657,8 @@ mod tests {
                 paging_state: None,
                 serial_consistency: None,
                 timestamp: None,
+                keyspace: None,
+                now_in_seconds: None,
             },
         });
         test_encode_decode_roundtrip_request(&raw_frame, frame, body);
@@ -619,9 +672,10 @@ mod tests {
             flags: Flags::empty(),
             opcode: Opcode::Query,
             stream_id: 0,
-            body: vec![],
+            body: Bytes::from_static(&[]),
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "another query".into(),
@@ -641,6 +695,8 @@ mod tests {
                 paging_state: Some(CBytes::new(vec![0, 1, 2, 3])),
                 serial_consistency: Some(Consistency::One),
                 timestamp: Some(2000),
+                keyspace: None,
+                now_in_seconds: None,
             },
         });
         test_encode_decode_roundtrip_nondeterministic_request(frame, body);
@@ -681,7 +737,7 @@ mod tests {
             flags: Flags::empty(),
             opcode: Opcode::Result,
             stream_id: 0,
-            body: vec![
+            body: Bytes::from_static(&[
                 0, 0, 0, 4, // prepared statement result
                 0, 16, 195, 165, 42, 38, 120, 170, 232, 144, 214, 187, 158, 200, 160, 226, 27,
                 73, // id
@@ -701,14 +757,16 @@ mod tests {
                 0, 13, // ColSpec.col_type = VarChar
                 0, 0, 0, 4, // row metadata flags
                 0, 0, 0, 0, // columns count
-            ],
+            ]),
             tracing_id: None,
             warnings: vec![],
+            custom_payload: Default::default(),
         };
         let body = ResponseBody::Result(ResResultBody::Prepared(BodyResResultPrepared {
             id: CBytesShort::new(vec![
                 195, 165, 42, 38, 120, 170, 232, 144, 214, 187, 158, 200, 160, 226, 27, 73,
             ]),
+            result_metadata_id: None,
             metadata: PreparedMetadata {
                 pk_indexes: vec![0],
                 global_table_spec: Some(TableSpec {
@@ -746,6 +804,7 @@ mod tests {
                 flags: RowsMetadataFlags::NO_METADATA,
                 columns_count: 0,
                 paging_state: None,
+                new_metadata_id: None,
                 global_table_spec: None,
                 col_specs: vec![],
             },
@@ -753,4 +812,32 @@ mod tests {
 
         test_encode_decode_roundtrip_response(&raw_frame, frame, body);
     }
+
+    #[test]
+    fn test_custom_payload() {
+        let frame = Frame {
+            version: Version::V4,
+            direction: Direction::Response,
+            flags: Flags::empty(),
+            opcode: Opcode::Ready,
+            stream_id: 0,
+            body: Bytes::from_static(&[]),
+            tracing_id: None,
+            warnings: vec![],
+            custom_payload: vec![("foo".to_string(), vec![1, 2])].into_iter().collect(),
+        };
+
+        let encoded_frame = frame.encode_with(Compression::None).unwrap();
+        assert_eq!(
+            encoded_frame,
+            &[132, 4, 0, 0, 2, 0, 0, 0, 13, 0, 1, 0, 3, 102, 111, 111, 0, 0, 0, 2, 1, 2]
+        );
+
+        let decoded_frame = Frame::from_buffer(&encoded_frame, Compression::None)
+            .unwrap()
+            .frame;
+        assert!(decoded_frame.flags.contains(Flags::CUSTOM_PAYLOAD));
+        assert_eq!(decoded_frame.custom_payload, frame.custom_payload);
+        assert!(decoded_frame.body.is_empty());
+    }
 }
@@ -35,9 +35,10 @@ impl Frame {
             Flags::empty(),
             opcode,
             0,
-            body.serialize_to_vec(),
+            body.serialize_to_vec().into(),
             None,
             vec![],
+            Default::default(),
         )
     }
 }
@@ -61,7 +62,7 @@ mod tests {
 
         assert_eq!(frame.version, Version::V4);
         assert_eq!(frame.opcode, Opcode::AuthResponse);
-        assert_eq!(frame.body, &[0, 0, 0, 3, 1, 2, 3]);
+        assert_eq!(frame.body, &[0, 0, 0, 3, 1, 2, 3][..]);
         assert_eq!(frame.tracing_id, None);
         assert!(frame.warnings.is_empty());
     }
@@ -18,10 +18,14 @@ pub struct BodyReqBatch {
     pub consistency: Consistency,
     pub serial_consistency: Option<Consistency>,
     pub timestamp: Option<CLong>,
+    /// Keyspace in which the batch should be executed. Requires protocol V5+.
+    pub keyspace: Option<String>,
+    /// Current time override used by the server, in seconds. Requires protocol V5+.
+    pub now_in_seconds: Option<CInt>,
 }
 
-impl Serialize for BodyReqBatch {
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
+impl BodyReqBatch {
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         let batch_type = u8::from(self.batch_type);
         batch_type.serialize(cursor);
 
@@ -42,7 +46,15 @@ impl Serialize for BodyReqBatch {
         if self.timestamp.is_some() {
             flags.insert(QueryFlags::WITH_DEFAULT_TIMESTAMP)
         }
-        flags.bits().serialize(cursor);
+        if version >= Version::V5 {
+            if self.keyspace.is_some() {
+                flags.insert(QueryFlags::WITH_KEYSPACE)
+            }
+            if self.now_in_seconds.is_some() {
+                flags.insert(QueryFlags::WITH_NOW_IN_SECONDS)
+            }
+        }
+        flags.serialize(cursor, version);
 
         if let Some(serial_consistency) = self.serial_consistency {
             let serial_consistency: CIntShort = serial_consistency.into();
@@ -52,11 +64,28 @@ impl Serialize for BodyReqBatch {
         if let Some(timestamp) = self.timestamp {
             timestamp.serialize(cursor);
         }
+
+        if flags.contains(QueryFlags::WITH_KEYSPACE) {
+            if let Some(keyspace) = &self.keyspace {
+                serialize_str(cursor, keyspace);
+            }
+        }
+
+        if flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            if let Some(now_in_seconds) = self.now_in_seconds {
+                now_in_seconds.serialize(cursor);
+            }
+        }
     }
-}
 
-impl FromCursor for BodyReqBatch {
-    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<Self> {
+    #[inline]
+    pub fn serialize_to_vec(&self, version: Version) -> Vec<u8> {
+        let mut buf = vec![];
+        self.serialize(&mut Cursor::new(&mut buf), version);
+        buf
+    }
+
+    pub fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<Self> {
         let mut batch_type = [0];
         cursor.read_exact(&mut batch_type)?;
 
@@ -70,10 +99,7 @@ impl FromCursor for BodyReqBatch {
 
         let consistency = CIntShort::from_cursor(cursor).and_then(TryInto::try_into)?;
 
-        let mut query_flags = [0];
-        cursor.read_exact(&mut query_flags)?;
-
-        let query_flags = QueryFlags::from_bits_truncate(query_flags[0]);
+        let query_flags = QueryFlags::from_cursor(cursor, version)?;
 
         let serial_consistency = if query_flags.contains(QueryFlags::WITH_SERIAL_CONSISTENCY) {
             Some(CIntShort::from_cursor(cursor).and_then(TryInto::try_into)?)
@@ -87,12 +113,26 @@ impl FromCursor for BodyReqBatch {
             None
         };
 
+        let keyspace = if query_flags.contains(QueryFlags::WITH_KEYSPACE) {
+            Some(from_cursor_str(cursor)?.to_string())
+        } else {
+            None
+        };
+
+        let now_in_seconds = if query_flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            Some(CInt::from_cursor(cursor)?)
+        } else {
+            None
+        };
+
         Ok(BodyReqBatch::new(
             batch_type,
             queries,
             consistency,
             serial_consistency,
             timestamp,
+            keyspace,
+            now_in_seconds,
         ))
     }
 }
@@ -200,7 +240,14 @@ impl FromCursor for BatchQuery {
 }
 
 impl Frame {
+    #[inline]
     pub fn new_req_batch(query: BodyReqBatch, flags: Flags, version: Version) -> Frame {
+        Frame::new_req_batch_borrowed(&query, flags, version)
+    }
+
+    /// Creates a batch request without taking ownership of the batch, e.g. when it needs to be
+    /// encoded multiple times.
+    pub fn new_req_batch_borrowed(query: &BodyReqBatch, flags: Flags, version: Version) -> Frame {
         let direction = Direction::Request;
         let opcode = Opcode::Batch;
 
@@ -210,9 +257,10 @@ impl Frame {
             flags,
             opcode,
             0,
-            query.serialize_to_vec(),
+            query.serialize_to_vec(version).into(),
             None,
             vec![],
+            Default::default(),
         )
     }
 }
@@ -223,7 +271,7 @@ mod tests {
 
     use crate::consistency::Consistency;
     use crate::frame::frame_batch::{BatchQuery, BatchQuerySubj, BatchType, BodyReqBatch};
-    use crate::frame::FromCursor;
+    use crate::frame::{FromCursor, Version};
     use crate::query::QueryValues;
     use crate::types::prelude::Value;
 
@@ -242,11 +290,36 @@ mod tests {
         let data = [0, 0, 0, 0, 0, 0x10 | 0x20, 0, 1, 1, 2, 3, 4, 5, 6, 7, 8];
         let mut cursor = Cursor::new(data.as_slice());
 
-        let body = BodyReqBatch::from_cursor(&mut cursor).unwrap();
+        let body = BodyReqBatch::from_cursor(&mut cursor, Version::V4).unwrap();
         assert_eq!(body.batch_type, BatchType::Logged);
         assert!(body.queries.is_empty());
         assert_eq!(body.consistency, Consistency::Any);
         assert_eq!(body.serial_consistency, Some(Consistency::One));
         assert_eq!(body.timestamp, Some(0x0102030405060708));
     }
+
+    #[test]
+    fn should_roundtrip_v5_body() {
+        let body = BodyReqBatch::new(
+            BatchType::Unlogged,
+            vec![],
+            Consistency::One,
+            None,
+            None,
+            Some("ks".into()),
+            Some(10),
+        );
+
+        let data = body.serialize_to_vec(Version::V5);
+        assert_eq!(
+            data,
+            vec![1, 0, 0, 0, 1, 0, 0, 0x01, 0x80, 0, 2, 107, 115, 0, 0, 0, 10]
+        );
+
+        let mut cursor = Cursor::new(data.as_slice());
+        assert_eq!(
+            BodyReqBatch::from_cursor(&mut cursor, Version::V5).unwrap(),
+            body
+        );
+    }
 }
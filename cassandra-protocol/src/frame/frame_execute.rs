@@ -6,24 +6,54 @@ use crate::query::QueryParams;
 use crate::types::*;
 
 /// The structure that represents a body of a frame of type `execute`.
-#[derive(Debug, Constructor, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq)]
 pub struct BodyReqExecute<'a> {
     id: &'a CBytesShort,
+    result_metadata_id: Option<&'a CBytesShort>,
     query_parameters: &'a QueryParams,
+    timestamp: Option<CLong>,
 }
 
-impl<'a> Serialize for BodyReqExecute<'a> {
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
+impl<'a> BodyReqExecute<'a> {
+    pub fn new(
+        id: &'a CBytesShort,
+        result_metadata_id: Option<&'a CBytesShort>,
+        query_parameters: &'a QueryParams,
+    ) -> Self {
+        BodyReqExecute {
+            id,
+            result_metadata_id,
+            query_parameters,
+            timestamp: query_parameters.timestamp,
+        }
+    }
+
+    /// Sets default timestamp, overriding the one from query parameters.
+    #[inline]
+    pub fn with_timestamp(mut self, timestamp: Option<CLong>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         self.id.serialize(cursor);
-        self.query_parameters.serialize(cursor);
+
+        if version >= Version::V5 {
+            // missing id forces the server to always send full metadata with the result
+            match self.result_metadata_id {
+                Some(result_metadata_id) => result_metadata_id.serialize(cursor),
+                None => CBytesShort::new(vec![]).serialize(cursor),
+            }
+        }
+
+        self.query_parameters
+            .serialize_with_timestamp(cursor, version, self.timestamp);
     }
 
     #[inline]
-    fn serialize_to_vec(&self) -> Vec<u8> {
+    pub fn serialize_to_vec(&self, version: Version) -> Vec<u8> {
         let mut buf = Vec::with_capacity(self.id.serialized_len());
-
-        // ignore error, since it can only happen when going over 2^64 bytes size
-        let _ = self.serialize(&mut Cursor::new(&mut buf));
+        self.serialize(&mut Cursor::new(&mut buf), version);
         buf
     }
 }
@@ -32,35 +62,72 @@ impl<'a> Serialize for BodyReqExecute<'a> {
 #[derive(Debug, Constructor, Clone, Eq, PartialEq, Default)]
 pub struct BodyReqExecuteOwned {
     id: CBytesShort,
+    result_metadata_id: Option<CBytesShort>,
     query_parameters: QueryParams,
 }
 
-impl FromCursor for BodyReqExecuteOwned {
-    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<Self> {
+impl BodyReqExecuteOwned {
+    pub fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<Self> {
         let id = CBytesShort::from_cursor(cursor)?;
-        let query_parameters = QueryParams::from_cursor(cursor)?;
 
-        Ok(BodyReqExecuteOwned::new(id, query_parameters))
+        let result_metadata_id = if version >= Version::V5 {
+            Some(CBytesShort::from_cursor(cursor)?)
+        } else {
+            None
+        };
+
+        let query_parameters = QueryParams::from_cursor(cursor, version)?;
+
+        Ok(BodyReqExecuteOwned::new(
+            id,
+            result_metadata_id,
+            query_parameters,
+        ))
     }
-}
 
-impl Serialize for BodyReqExecuteOwned {
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
-        BodyReqExecute::new(&self.id, &self.query_parameters).serialize(cursor)
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
+        BodyReqExecute::new(
+            &self.id,
+            self.result_metadata_id.as_ref(),
+            &self.query_parameters,
+        )
+        .serialize(cursor, version)
     }
 }
 
 impl Frame {
     pub fn new_req_execute(
         id: &CBytesShort,
+        result_metadata_id: Option<&CBytesShort>,
+        query_parameters: &QueryParams,
+        flags: Flags,
+        version: Version,
+    ) -> Frame {
+        Self::new_req_execute_with_timestamp(
+            id,
+            result_metadata_id,
+            query_parameters,
+            query_parameters.timestamp,
+            flags,
+            version,
+        )
+    }
+
+    /// Creates an execute request with given default timestamp, overriding the one from query
+    /// parameters.
+    pub fn new_req_execute_with_timestamp(
+        id: &CBytesShort,
+        result_metadata_id: Option<&CBytesShort>,
         query_parameters: &QueryParams,
+        timestamp: Option<CLong>,
         flags: Flags,
         version: Version,
     ) -> Frame {
         let direction = Direction::Request;
         let opcode = Opcode::Execute;
 
-        let body = BodyReqExecute::new(id, query_parameters);
+        let body =
+            BodyReqExecute::new(id, result_metadata_id, query_parameters).with_timestamp(timestamp);
 
         Frame::new(
             version,
@@ -68,9 +135,10 @@ impl Frame {
             flags,
             opcode,
             0,
-            body.serialize_to_vec(),
+            body.serialize_to_vec(version).into(),
             None,
             vec![],
+            Default::default(),
         )
     }
 }
@@ -80,8 +148,8 @@ mod tests {
     use std::io::Cursor;
 
     use crate::consistency::Consistency;
-    use crate::frame::frame_execute::BodyReqExecuteOwned;
-    use crate::frame::FromCursor;
+    use crate::frame::frame_execute::{BodyReqExecute, BodyReqExecuteOwned};
+    use crate::frame::Version;
     use crate::types::CBytesShort;
 
     #[test]
@@ -89,8 +157,41 @@ mod tests {
         let data = [0, 1, 2, 0, 0, 0];
         let mut cursor = Cursor::new(data.as_slice());
 
-        let body = BodyReqExecuteOwned::from_cursor(&mut cursor).unwrap();
+        let body = BodyReqExecuteOwned::from_cursor(&mut cursor, Version::V4).unwrap();
         assert_eq!(body.id, CBytesShort::new(vec![2]));
         assert_eq!(body.query_parameters.consistency, Consistency::Any);
     }
+
+    #[test]
+    fn should_roundtrip_v5_body() {
+        let body = BodyReqExecuteOwned::new(
+            CBytesShort::new(vec![2]),
+            Some(CBytesShort::new(vec![3])),
+            Default::default(),
+        );
+
+        let mut data = vec![];
+        body.serialize(&mut Cursor::new(&mut data), Version::V5);
+        assert_eq!(data, vec![0, 1, 2, 0, 1, 3, 0, 1, 0, 0, 0, 0]);
+
+        let mut cursor = Cursor::new(data.as_slice());
+        assert_eq!(
+            BodyReqExecuteOwned::from_cursor(&mut cursor, Version::V5).unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn should_serialize_timestamp_override() {
+        let id = CBytesShort::new(vec![2]);
+        let query_parameters = Default::default();
+
+        let data = BodyReqExecute::new(&id, None, &query_parameters)
+            .with_timestamp(Some(5))
+            .serialize_to_vec(Version::V4);
+
+        let mut cursor = Cursor::new(data.as_slice());
+        let body = BodyReqExecuteOwned::from_cursor(&mut cursor, Version::V4).unwrap();
+        assert_eq!(body.query_parameters.timestamp, Some(5));
+    }
 }
@@ -1,50 +1,80 @@
+use bitflags::bitflags;
 use std::io::Cursor;
 
 use crate::frame::*;
 use crate::types::*;
 
+bitflags! {
+    pub struct PrepareFlags: u32 {
+        /// If set indicates that the body contains keyspace name (protocol V5+).
+        const WITH_KEYSPACE = 0x01;
+    }
+}
+
 /// Struct that represents a body of a frame of type `prepare`
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Default)]
 pub struct BodyReqPrepare {
     query: String,
+    keyspace: Option<String>,
 }
 
 impl BodyReqPrepare {
-    /// Creates new body of a frame of type `prepare` that prepares query `query`.
+    /// Creates new body of a frame of type `prepare` that prepares query `query`, optionally in
+    /// given keyspace. Keyspace is only sent with protocol V5+.
     #[inline]
-    pub fn new(query: String) -> BodyReqPrepare {
-        BodyReqPrepare { query }
+    pub fn new(query: String, keyspace: Option<String>) -> BodyReqPrepare {
+        BodyReqPrepare { query, keyspace }
     }
-}
 
-impl Serialize for BodyReqPrepare {
     #[inline]
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         serialize_str_long(cursor, &self.query);
+
+        if version >= Version::V5 {
+            if let Some(keyspace) = &self.keyspace {
+                PrepareFlags::WITH_KEYSPACE.bits().serialize(cursor);
+                serialize_str(cursor, keyspace);
+            } else {
+                PrepareFlags::empty().bits().serialize(cursor);
+            }
+        }
     }
 
     #[inline]
-    fn serialize_to_vec(&self) -> Vec<u8> {
+    pub fn serialize_to_vec(&self, version: Version) -> Vec<u8> {
         let mut buf = Vec::with_capacity(INT_LEN + self.query.len());
-
-        // ignore error, since it can only happen when going over 2^64 bytes size
-        let _ = self.serialize(&mut Cursor::new(&mut buf));
+        self.serialize(&mut Cursor::new(&mut buf), version);
         buf
     }
-}
 
-impl FromCursor for BodyReqPrepare {
-    #[inline]
-    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<Self> {
-        from_cursor_str_long(cursor).map(|query| BodyReqPrepare::new(query.into()))
+    pub fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<Self> {
+        let query = from_cursor_str_long(cursor)?.to_string();
+
+        let keyspace = if version >= Version::V5 {
+            let flags = PrepareFlags::from_bits_truncate(CInt::from_cursor(cursor)? as u32);
+            if flags.contains(PrepareFlags::WITH_KEYSPACE) {
+                Some(from_cursor_str(cursor)?.to_string())
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        Ok(BodyReqPrepare::new(query, keyspace))
     }
 }
 
 impl Frame {
-    pub fn new_req_prepare(query: String, flags: Flags, version: Version) -> Frame {
+    pub fn new_req_prepare(
+        query: String,
+        keyspace: Option<String>,
+        flags: Flags,
+        version: Version,
+    ) -> Frame {
         let direction = Direction::Request;
         let opcode = Opcode::Prepare;
-        let body = BodyReqPrepare::new(query);
+        let body = BodyReqPrepare::new(query, keyspace);
 
         Frame::new(
             version,
@@ -52,9 +82,10 @@ impl Frame {
             flags,
             opcode,
             0,
-            body.serialize_to_vec(),
+            body.serialize_to_vec(version).into(),
             None,
             vec![],
+            Default::default(),
         )
     }
 }
@@ -64,14 +95,31 @@ mod tests {
     use std::io::Cursor;
 
     use crate::frame::frame_prepare::BodyReqPrepare;
-    use crate::frame::FromCursor;
+    use crate::frame::Version;
 
     #[test]
     fn should_deserialize_body() {
         let data = [0, 0, 0, 3, 102, 111, 111, 0];
         let mut cursor = Cursor::new(data.as_slice());
 
-        let body = BodyReqPrepare::from_cursor(&mut cursor).unwrap();
+        let body = BodyReqPrepare::from_cursor(&mut cursor, Version::V4).unwrap();
         assert_eq!(body.query, "foo");
     }
+
+    #[test]
+    fn should_roundtrip_body_with_keyspace() {
+        let body = BodyReqPrepare::new("foo".into(), Some("ks".into()));
+
+        let data = body.serialize_to_vec(Version::V5);
+        assert_eq!(
+            data,
+            vec![0, 0, 0, 3, 102, 111, 111, 0, 0, 0, 1, 0, 2, 107, 115]
+        );
+
+        let mut cursor = Cursor::new(data.as_slice());
+        assert_eq!(
+            BodyReqPrepare::from_cursor(&mut cursor, Version::V5).unwrap(),
+            body
+        );
+    }
 }
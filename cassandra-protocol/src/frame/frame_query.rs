@@ -1,5 +1,4 @@
 use crate::consistency::Consistency;
-use crate::frame::traits::FromCursor;
 use crate::frame::*;
 use crate::query::{Query, QueryParams, QueryValues};
 use crate::types::*;
@@ -25,6 +24,8 @@ impl BodyReqQuery {
         paging_state: Option<CBytes>,
         serial_consistency: Option<Consistency>,
         timestamp: Option<i64>,
+        keyspace: Option<String>,
+        now_in_seconds: Option<CInt>,
     ) -> BodyReqQuery {
         BodyReqQuery {
             query,
@@ -36,40 +37,53 @@ impl BodyReqQuery {
                 paging_state,
                 serial_consistency,
                 timestamp,
+                keyspace,
+                now_in_seconds,
             },
         }
     }
-}
 
-impl FromCursor for BodyReqQuery {
-    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyReqQuery> {
+    pub fn from_cursor(
+        cursor: &mut Cursor<&[u8]>,
+        version: Version,
+    ) -> error::Result<BodyReqQuery> {
         let query = from_cursor_str_long(cursor)?.to_string();
-        let query_params = QueryParams::from_cursor(cursor)?;
+        let query_params = QueryParams::from_cursor(cursor, version)?;
 
         Ok(BodyReqQuery {
             query,
             query_params,
         })
     }
-}
 
-impl Serialize for BodyReqQuery {
     #[inline]
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
-        serialize_str_long(cursor, &self.query);
-        self.query_params.serialize(cursor);
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
+        serialize_query(cursor, &self.query, &self.query_params, version);
     }
 
     #[inline]
-    fn serialize_to_vec(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(INT_LEN + self.query.len());
-
-        // ignore error, since it can only happen when going over 2^64 bytes size
-        let _ = self.serialize(&mut Cursor::new(&mut buf));
-        buf
+    pub fn serialize_to_vec(&self, version: Version) -> Vec<u8> {
+        serialize_query_to_vec(&self.query, &self.query_params, version)
     }
 }
 
+#[inline]
+fn serialize_query(
+    cursor: &mut Cursor<&mut Vec<u8>>,
+    query: &str,
+    query_params: &QueryParams,
+    version: Version,
+) {
+    serialize_str_long(cursor, query);
+    query_params.serialize(cursor, version);
+}
+
+fn serialize_query_to_vec(query: &str, query_params: &QueryParams, version: Version) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(INT_LEN + query.len());
+    serialize_query(&mut Cursor::new(&mut buf), query, query_params, version);
+    buf
+}
+
 impl Frame {
     #[allow(clippy::too_many_arguments)]
     pub fn new_req_query(
@@ -81,6 +95,8 @@ impl Frame {
         paging_state: Option<CBytes>,
         serial_consistency: Option<Consistency>,
         timestamp: Option<i64>,
+        keyspace: Option<String>,
+        now_in_seconds: Option<CInt>,
         flags: Flags,
         version: Version,
     ) -> Frame {
@@ -95,6 +111,8 @@ impl Frame {
             paging_state,
             serial_consistency,
             timestamp,
+            keyspace,
+            now_in_seconds,
         );
 
         Frame::new(
@@ -103,9 +121,10 @@ impl Frame {
             flags,
             opcode,
             0,
-            body.serialize_to_vec(),
+            body.serialize_to_vec(version).into(),
             None,
             vec![],
+            Default::default(),
         )
     }
 
@@ -120,8 +139,67 @@ impl Frame {
             query.params.paging_state,
             query.params.serial_consistency,
             query.params.timestamp,
+            query.params.keyspace,
+            query.params.now_in_seconds,
             flags,
             version,
         )
     }
+
+    /// Creates a query request without taking ownership of the query, e.g. when it needs to be
+    /// encoded multiple times.
+    pub fn new_query_borrowed(
+        query: &str,
+        query_params: &QueryParams,
+        flags: Flags,
+        version: Version,
+    ) -> Frame {
+        Frame::new(
+            version,
+            Direction::Request,
+            flags,
+            Opcode::Query,
+            0,
+            serialize_query_to_vec(query, query_params, version).into(),
+            None,
+            vec![],
+            Default::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consistency::Consistency;
+    use crate::frame::frame_query::BodyReqQuery;
+    use crate::frame::{Flags, Frame, Version};
+    use crate::query::{Query, QueryParams};
+
+    #[test]
+    fn should_create_borrowed_query() {
+        let query_params = QueryParams {
+            consistency: Consistency::Quorum,
+            timestamp: Some(5),
+            ..Default::default()
+        };
+
+        let frame =
+            Frame::new_query_borrowed("SELECT 1", &query_params, Flags::empty(), Version::V5);
+        assert_eq!(
+            frame,
+            Frame::new_query(
+                Query {
+                    query: "SELECT 1".into(),
+                    params: query_params.clone(),
+                },
+                Flags::empty(),
+                Version::V5
+            )
+        );
+
+        let mut cursor = std::io::Cursor::new(frame.body.as_ref());
+        let body = BodyReqQuery::from_cursor(&mut cursor, Version::V5).unwrap();
+        assert_eq!(body.query, "SELECT 1");
+        assert_eq!(body.query_params, query_params);
+    }
 }
@@ -43,9 +43,10 @@ impl Frame {
             Flags::empty(),
             opcode,
             0,
-            register_body.serialize_to_vec(),
+            register_body.serialize_to_vec().into(),
             None,
             vec![],
+            Default::default(),
         )
     }
 }
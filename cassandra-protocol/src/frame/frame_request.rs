@@ -9,7 +9,7 @@ use crate::frame::frame_prepare::BodyReqPrepare;
 use crate::frame::frame_query::BodyReqQuery;
 use crate::frame::frame_register::BodyReqRegister;
 use crate::frame::frame_startup::BodyReqStartup;
-use crate::frame::{FromCursor, Opcode, Serialize};
+use crate::frame::{FromCursor, Opcode, Serialize, Version};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[allow(clippy::large_enum_variant)]
@@ -24,23 +24,32 @@ pub enum RequestBody {
     AuthResponse(BodyReqAuthResponse),
 }
 
-impl Serialize for RequestBody {
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
+impl RequestBody {
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
         match self {
-            RequestBody::Query(body) => body.serialize(cursor),
+            RequestBody::Query(body) => body.serialize(cursor, version),
             RequestBody::Startup(body) => body.serialize(cursor),
             RequestBody::Options(body) => body.serialize(cursor),
-            RequestBody::Prepare(body) => body.serialize(cursor),
-            RequestBody::Execute(body) => body.serialize(cursor),
+            RequestBody::Prepare(body) => body.serialize(cursor, version),
+            RequestBody::Execute(body) => body.serialize(cursor, version),
             RequestBody::Register(body) => body.serialize(cursor),
-            RequestBody::Batch(body) => body.serialize(cursor),
+            RequestBody::Batch(body) => body.serialize(cursor, version),
             RequestBody::AuthResponse(body) => body.serialize(cursor),
         }
     }
-}
 
-impl RequestBody {
-    pub fn try_from(bytes: &[u8], response_type: Opcode) -> error::Result<RequestBody> {
+    #[inline]
+    pub fn serialize_to_vec(&self, version: Version) -> Vec<u8> {
+        let mut buf = vec![];
+        self.serialize(&mut Cursor::new(&mut buf), version);
+        buf
+    }
+
+    pub fn try_from(
+        bytes: &[u8],
+        response_type: Opcode,
+        version: Version,
+    ) -> error::Result<RequestBody> {
         let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
         match response_type {
             Opcode::Startup => Ok(RequestBody::Startup(BodyReqStartup::from_cursor(
@@ -49,17 +58,25 @@ impl RequestBody {
             Opcode::Options => Ok(RequestBody::Options(BodyReqOptions::from_cursor(
                 &mut cursor,
             )?)),
-            Opcode::Query => Ok(RequestBody::Query(BodyReqQuery::from_cursor(&mut cursor)?)),
+            Opcode::Query => Ok(RequestBody::Query(BodyReqQuery::from_cursor(
+                &mut cursor,
+                version,
+            )?)),
             Opcode::Prepare => Ok(RequestBody::Prepare(BodyReqPrepare::from_cursor(
                 &mut cursor,
+                version,
             )?)),
             Opcode::Execute => Ok(RequestBody::Execute(BodyReqExecuteOwned::from_cursor(
                 &mut cursor,
+                version,
             )?)),
             Opcode::Register => Ok(RequestBody::Register(BodyReqRegister::from_cursor(
                 &mut cursor,
             )?)),
-            Opcode::Batch => Ok(RequestBody::Batch(BodyReqBatch::from_cursor(&mut cursor)?)),
+            Opcode::Batch => Ok(RequestBody::Batch(BodyReqBatch::from_cursor(
+                &mut cursor,
+                version,
+            )?)),
             Opcode::AuthResponse => Ok(RequestBody::AuthResponse(
                 BodyReqAuthResponse::from_cursor(&mut cursor)?,
             )),
@@ -1,3 +1,4 @@
+use bytes::Bytes;
 use std::io::Cursor;
 
 use crate::error;
@@ -11,7 +12,7 @@ use crate::frame::frame_result::{
 };
 use crate::frame::frame_supported::*;
 use crate::frame::{FromCursor, Opcode, Version};
-use crate::types::rows::Row;
+use crate::types::rows::{Row, RowIterator};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ResponseBody {
@@ -59,8 +60,9 @@ impl Serialize for ResponseBody {
 }
 
 impl ResponseBody {
+    /// Parses the response body of given type. Row values share the given buffer.
     pub fn try_from(
-        bytes: &[u8],
+        bytes: &Bytes,
         response_type: Opcode,
         version: Version,
     ) -> error::Result<ResponseBody> {
@@ -74,8 +76,9 @@ impl ResponseBody {
             Opcode::Supported => Ok(ResponseBody::Supported(BodyResSupported::from_cursor(
                 &mut cursor,
             )?)),
-            Opcode::Result => Ok(ResponseBody::Result(ResResultBody::from_cursor(
+            Opcode::Result => Ok(ResponseBody::Result(ResResultBody::from_shared_cursor(
                 &mut cursor,
+                bytes,
                 version,
             )?)),
             Opcode::Event => Ok(ResponseBody::Event(BodyResEvent::from_cursor(&mut cursor)?)),
@@ -96,6 +99,14 @@ impl ResponseBody {
         }
     }
 
+    /// Returns a lazy iterator over rows, if the body contains them.
+    pub fn into_rows_iter(self) -> Option<RowIterator> {
+        match self {
+            ResponseBody::Result(res) => res.into_rows_iter(),
+            _ => None,
+        }
+    }
+
     pub fn as_rows_metadata(&self) -> Option<RowsMetadata> {
         match *self {
             ResponseBody::Result(ref res) => res.as_rows_metadata(),
@@ -137,6 +148,15 @@ impl ResponseBody {
         }
     }
 
+    /// Unwraps body and returns BodyResSupported.
+    /// If frame body is not of type `Supported` this method returns `None`.
+    pub fn into_supported(self) -> Option<BodyResSupported> {
+        match self {
+            ResponseBody::Supported(supported) => Some(supported),
+            _ => None,
+        }
+    }
+
     pub fn authenticator(&self) -> Option<&str> {
         match *self {
             ResponseBody::Authenticate(ref auth) => Some(auth.data.as_str()),
@@ -1,4 +1,5 @@
 use bitflags::bitflags;
+use bytes::Bytes;
 use derive_more::{Constructor, Display};
 use std::convert::{TryFrom, TryInto};
 use std::io::{Cursor, Error as IoError, Read};
@@ -7,7 +8,7 @@ use crate::error;
 use crate::error::Error;
 use crate::frame::events::SchemaChange;
 use crate::frame::{FromBytes, FromCursor, Serialize, Version};
-use crate::types::rows::Row;
+use crate::types::rows::{Row, RowIterator};
 use crate::types::*;
 
 /// `ResultKind` is enum which represents types of result.
@@ -124,12 +125,16 @@ impl Serialize for ResResultBody {
 impl ResResultBody {
     fn parse_body_from_cursor(
         cursor: &mut Cursor<&[u8]>,
+        buffer: Option<&Bytes>,
         result_kind: ResultKind,
         version: Version,
     ) -> error::Result<ResResultBody> {
         Ok(match result_kind {
             ResultKind::Void => ResResultBody::Void,
-            ResultKind::Rows => ResResultBody::Rows(BodyResResultRows::from_cursor(cursor)?),
+            ResultKind::Rows => ResResultBody::Rows(match buffer {
+                Some(buffer) => BodyResResultRows::from_shared_cursor(cursor, buffer)?,
+                None => BodyResResultRows::from_cursor(cursor)?,
+            }),
             ResultKind::SetKeyspace => {
                 ResResultBody::SetKeyspace(BodyResResultSetKeyspace::from_cursor(cursor)?)
             }
@@ -150,6 +155,15 @@ impl ResResultBody {
         }
     }
 
+    /// It converts body into a lazy row iterator if body's type is `Row` and returns `None`
+    /// otherwise.
+    pub fn into_rows_iter(self) -> Option<RowIterator> {
+        match self {
+            ResResultBody::Rows(rows_body) => Some(rows_body.into_iter()),
+            _ => None,
+        }
+    }
+
     /// It returns `Some` rows metadata if frame result is of type rows and `None` otherwise
     pub fn as_rows_metadata(&self) -> Option<RowsMetadata> {
         match *self {
@@ -183,7 +197,18 @@ impl ResResultBody {
         version: Version,
     ) -> error::Result<ResResultBody> {
         let result_kind = ResultKind::from_cursor(cursor)?;
-        ResResultBody::parse_body_from_cursor(cursor, result_kind, version)
+        ResResultBody::parse_body_from_cursor(cursor, None, result_kind, version)
+    }
+
+    /// Parses the body from a cursor created from given buffer. Row values will be slices of the
+    /// buffer, rather than copies.
+    pub fn from_shared_cursor(
+        cursor: &mut Cursor<&[u8]>,
+        buffer: &Bytes,
+        version: Version,
+    ) -> error::Result<ResResultBody> {
+        let result_kind = ResultKind::from_cursor(cursor)?;
+        ResResultBody::parse_body_from_cursor(cursor, Some(buffer), result_kind, version)
     }
 }
 
@@ -209,14 +234,16 @@ impl FromCursor for BodyResResultSetKeyspace {
 
 /// Structure that represents result of type
 /// [rows](https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L533).
+/// Rows are decoded lazily, when iterating over the body.
 #[derive(Debug, PartialEq, Ord, PartialOrd, Eq, Clone, Hash)]
 pub struct BodyResResultRows {
     /// Rows metadata
     pub metadata: RowsMetadata,
     /// Number of rows.
     pub rows_count: CInt,
-    /// From spec: it is composed of `rows_count` of rows.
-    pub rows_content: Vec<Vec<CBytes>>,
+    /// From spec: it is composed of `rows_count` of rows. Contains encoded rows, which are
+    /// decoded by iterating over the body.
+    pub rows_content: Bytes,
 }
 
 impl Serialize for BodyResResultRows {
@@ -224,35 +251,41 @@ impl Serialize for BodyResResultRows {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
         self.metadata.serialize(cursor);
         self.rows_count.serialize(cursor);
-        self.rows_content
-            .iter()
-            .flatten()
-            .for_each(|x| x.serialize(cursor));
+        self.rows_content.as_ref().serialize(cursor);
     }
 }
 
 impl BodyResResultRows {
-    fn rows_content(
-        cursor: &mut Cursor<&[u8]>,
+    /// Skips over rows content, checking if all values are present.
+    fn skip_rows_content<'a>(
+        cursor: &mut Cursor<&'a [u8]>,
         rows_count: i32,
         columns_count: i32,
-    ) -> error::Result<Vec<Vec<CBytes>>> {
-        (0..rows_count)
-            .map(|_| {
-                (0..columns_count)
-                    .map(|_| CBytes::from_cursor(cursor))
-                    .collect::<Result<_, _>>()
-            })
-            .collect::<Result<_, _>>()
+    ) -> error::Result<&'a [u8]> {
+        let start = cursor.position() as usize;
+
+        for _ in 0..(rows_count.max(0) as usize * columns_count.max(0) as usize) {
+            let len = CInt::from_cursor(cursor)?;
+            if len > 0 {
+                cursor_next_value_ref(cursor, len as usize)?;
+            }
+        }
+
+        Ok(&cursor.get_ref()[start..cursor.position() as usize])
     }
-}
 
-impl FromCursor for BodyResResultRows {
-    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultRows> {
+    fn from_cursor_with(
+        cursor: &mut Cursor<&[u8]>,
+        buffer: Option<&Bytes>,
+    ) -> error::Result<BodyResResultRows> {
         let metadata = RowsMetadata::from_cursor(cursor)?;
         let rows_count = CInt::from_cursor(cursor)?;
         let rows_content =
-            BodyResResultRows::rows_content(cursor, rows_count, metadata.columns_count)?;
+            BodyResResultRows::skip_rows_content(cursor, rows_count, metadata.columns_count)?;
+        let rows_content = match buffer {
+            Some(buffer) => shared_slice(buffer, rows_content),
+            None => Bytes::copy_from_slice(rows_content),
+        };
 
         Ok(BodyResResultRows {
             metadata,
@@ -260,6 +293,31 @@ impl FromCursor for BodyResResultRows {
             rows_content,
         })
     }
+
+    /// Parses rows from a cursor created from given buffer. Row values will be slices of the
+    /// buffer, rather than copies, which keeps the buffer alive as long as any row references it.
+    pub fn from_shared_cursor(
+        cursor: &mut Cursor<&[u8]>,
+        buffer: &Bytes,
+    ) -> error::Result<BodyResResultRows> {
+        BodyResResultRows::from_cursor_with(cursor, Some(buffer))
+    }
+}
+
+impl FromCursor for BodyResResultRows {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultRows> {
+        BodyResResultRows::from_cursor_with(cursor, None)
+    }
+}
+
+impl IntoIterator for BodyResResultRows {
+    type Item = Row;
+    type IntoIter = RowIterator;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        RowIterator::new(self)
+    }
 }
 
 /// Rows metadata.
@@ -271,6 +329,8 @@ pub struct RowsMetadata {
     pub columns_count: i32,
     /// Paging state.
     pub paging_state: Option<CBytes>,
+    /// New result metadata id, present when metadata changed since preparing (protocol V5+).
+    pub new_metadata_id: Option<CBytesShort>,
     // In fact by specification Vec should have only two elements representing the
     // (unique) keyspace name and table name the columns belong to
     /// `Option` that may contain global table space.
@@ -288,6 +348,10 @@ impl Serialize for RowsMetadata {
             self.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES),
             self.paging_state.is_some()
         );
+        assert_eq!(
+            self.flags.contains(RowsMetadataFlags::METADATA_CHANGED),
+            self.new_metadata_id.is_some()
+        );
 
         match (
             self.flags.contains(RowsMetadataFlags::NO_METADATA),
@@ -314,6 +378,10 @@ impl Serialize for RowsMetadata {
             paging_state.serialize(cursor);
         }
 
+        if let Some(new_metadata_id) = &self.new_metadata_id {
+            new_metadata_id.serialize(cursor);
+        }
+
         if let Some(global_table_spec) = &self.global_table_spec {
             global_table_spec.serialize(cursor);
         }
@@ -333,11 +401,18 @@ impl FromCursor for RowsMetadata {
             None
         };
 
+        let new_metadata_id = if flags.contains(RowsMetadataFlags::METADATA_CHANGED) {
+            Some(CBytesShort::from_cursor(cursor)?)
+        } else {
+            None
+        };
+
         if flags.contains(RowsMetadataFlags::NO_METADATA) {
             return Ok(RowsMetadata {
                 flags,
                 columns_count,
                 paging_state,
+                new_metadata_id,
                 global_table_spec: None,
                 col_specs: vec![],
             });
@@ -352,6 +427,7 @@ impl FromCursor for RowsMetadata {
             flags,
             columns_count,
             paging_state,
+            new_metadata_id,
             global_table_spec,
             col_specs,
         })
@@ -363,6 +439,7 @@ bitflags! {
         const GLOBAL_TABLE_SPACE = 0x0001;
         const HAS_MORE_PAGES = 0x0002;
         const NO_METADATA = 0x0004;
+        const METADATA_CHANGED = 0x0008;
     }
 }
 
@@ -488,6 +565,7 @@ pub enum ColType {
     Time,
     Smallint,
     Tinyint,
+    Duration,
     List,
     Map,
     Set,
@@ -521,6 +599,7 @@ impl TryFrom<i16> for ColType {
             0x0012 => Ok(ColType::Time),
             0x0013 => Ok(ColType::Smallint),
             0x0014 => Ok(ColType::Tinyint),
+            0x0015 => Ok(ColType::Duration),
             0x0020 => Ok(ColType::List),
             0x0021 => Ok(ColType::Map),
             0x0022 => Ok(ColType::Set),
@@ -564,6 +643,7 @@ impl Serialize for ColType {
             ColType::Time => 0x0012,
             ColType::Smallint => 0x0013,
             ColType::Tinyint => 0x0014,
+            ColType::Duration => 0x0015,
             ColType::List => 0x0020,
             ColType::Map => 0x0021,
             ColType::Set => 0x0022,
@@ -608,9 +688,15 @@ impl FromCursor for ColTypeOption {
     fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<ColTypeOption> {
         let id = ColType::from_cursor(cursor)?;
         let value = match id {
-            ColType::Custom => Some(ColTypeOptionValue::CString(
-                from_cursor_str(cursor)?.to_string(),
-            )),
+            ColType::Custom => {
+                let class_name = from_cursor_str(cursor)?;
+                Some(match parse_vector_type(class_name)? {
+                    Some((element_type, dimensions)) => {
+                        ColTypeOptionValue::CVector(Box::new(element_type), dimensions)
+                    }
+                    None => ColTypeOptionValue::CString(class_name.to_string()),
+                })
+            }
             ColType::Set => {
                 let col_type = ColTypeOption::from_cursor(cursor)?;
                 Some(ColTypeOptionValue::CSet(Box::new(col_type)))
@@ -646,6 +732,8 @@ pub enum ColTypeOptionValue {
     UdtType(CUdt),
     TupleType(CTuple),
     CMap(Box<ColTypeOption>, Box<ColTypeOption>),
+    /// Vector of given element type and number of dimensions. Sent by the server as a custom type.
+    CVector(Box<ColTypeOption>, usize),
 }
 
 impl Serialize for ColTypeOptionValue {
@@ -662,10 +750,108 @@ impl Serialize for ColTypeOptionValue {
                 v1.serialize(cursor);
                 v2.serialize(cursor);
             }
+            Self::CVector(element_type, dimensions) => {
+                serialize_str(cursor, &vector_class_name(element_type, *dimensions))
+            }
         }
     }
 }
 
+const MARSHAL_PACKAGE: &str = "org.apache.cassandra.db.marshal.";
+const VECTOR_TYPE: &str = "VectorType";
+
+// class names of types which can be vector elements
+const SIMPLE_TYPE_CLASSES: [(ColType, &str); 20] = [
+    (ColType::Ascii, "AsciiType"),
+    (ColType::Bigint, "LongType"),
+    (ColType::Blob, "BytesType"),
+    (ColType::Boolean, "BooleanType"),
+    (ColType::Counter, "CounterColumnType"),
+    (ColType::Decimal, "DecimalType"),
+    (ColType::Double, "DoubleType"),
+    (ColType::Float, "FloatType"),
+    (ColType::Int, "Int32Type"),
+    (ColType::Timestamp, "TimestampType"),
+    (ColType::Uuid, "UUIDType"),
+    (ColType::Varchar, "UTF8Type"),
+    (ColType::Varint, "IntegerType"),
+    (ColType::Timeuuid, "TimeUUIDType"),
+    (ColType::Inet, "InetAddressType"),
+    (ColType::Date, "SimpleDateType"),
+    (ColType::Time, "TimeType"),
+    (ColType::Smallint, "ShortType"),
+    (ColType::Tinyint, "ByteType"),
+    (ColType::Duration, "DurationType"),
+];
+
+/// Parses custom type class name in the form of `VectorType(<element type>, <dimensions>)` into
+/// vector element type and dimensions. Returns `None` for other classes.
+fn parse_vector_type(class_name: &str) -> error::Result<Option<(ColTypeOption, usize)>> {
+    let parameters = match class_name
+        .trim()
+        .trim_start_matches(MARSHAL_PACKAGE)
+        .strip_prefix(VECTOR_TYPE)
+        .and_then(|parameters| parameters.trim().strip_prefix('('))
+        .and_then(|parameters| parameters.strip_suffix(')'))
+    {
+        Some(parameters) => parameters,
+        None => return Ok(None),
+    };
+
+    let invalid_type = || Error::General(format!("Invalid vector type: {}", class_name));
+
+    let (element_class, dimensions) = parameters.rsplit_once(',').ok_or_else(invalid_type)?;
+    let dimensions = dimensions.trim().parse().map_err(|_| invalid_type())?;
+
+    let element_class = element_class.trim();
+    let element_type = match parse_vector_type(element_class)? {
+        Some((element_type, dimensions)) => ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CVector(
+                Box::new(element_type),
+                dimensions,
+            )),
+        },
+        None => {
+            let short_class = element_class.trim_start_matches(MARSHAL_PACKAGE);
+            match SIMPLE_TYPE_CLASSES
+                .iter()
+                .find(|(_, class)| *class == short_class)
+            {
+                Some((id, _)) => ColTypeOption {
+                    id: *id,
+                    value: None,
+                },
+                None => ColTypeOption {
+                    id: ColType::Custom,
+                    value: Some(ColTypeOptionValue::CString(element_class.to_string())),
+                },
+            }
+        }
+    };
+
+    Ok(Some((element_type, dimensions)))
+}
+
+fn vector_class_name(element_type: &ColTypeOption, dimensions: usize) -> String {
+    let element_class = match &element_type.value {
+        Some(ColTypeOptionValue::CString(class_name)) => class_name.clone(),
+        Some(ColTypeOptionValue::CVector(element_type, dimensions)) => {
+            vector_class_name(element_type, *dimensions)
+        }
+        _ => SIMPLE_TYPE_CLASSES
+            .iter()
+            .find(|(id, _)| *id == element_type.id)
+            .map(|(_, class)| format!("{}{}", MARSHAL_PACKAGE, class))
+            .unwrap_or_default(),
+    };
+
+    format!(
+        "{}{}({}, {})",
+        MARSHAL_PACKAGE, VECTOR_TYPE, element_class, dimensions
+    )
+}
+
 /// User defined type.
 #[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
 pub struct CUdt {
@@ -751,6 +937,8 @@ impl FromCursor for CTuple {
 pub struct BodyResResultPrepared {
     /// id of prepared request
     pub id: CBytesShort,
+    /// id of result metadata (protocol V5+)
+    pub result_metadata_id: Option<CBytesShort>,
     /// metadata
     pub metadata: PreparedMetadata,
     /// It is defined exactly the same as <metadata> in the Rows
@@ -762,6 +950,11 @@ impl Serialize for BodyResResultPrepared {
     #[inline]
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
         self.id.serialize(cursor);
+
+        if let Some(result_metadata_id) = &self.result_metadata_id {
+            result_metadata_id.serialize(cursor);
+        }
+
         self.metadata.serialize(cursor);
         self.result_metadata.serialize(cursor);
     }
@@ -773,11 +966,19 @@ impl BodyResResultPrepared {
         version: Version,
     ) -> error::Result<BodyResResultPrepared> {
         let id = CBytesShort::from_cursor(cursor)?;
+
+        let result_metadata_id = if version >= Version::V5 {
+            Some(CBytesShort::from_cursor(cursor)?)
+        } else {
+            None
+        };
+
         let metadata = PreparedMetadata::from_cursor(cursor, version)?;
         let result_metadata = RowsMetadata::from_cursor(cursor)?;
 
         Ok(BodyResResultPrepared {
             id,
+            result_metadata_id,
             metadata,
             result_metadata,
         })
@@ -886,6 +1087,13 @@ fn test_encode_decode(bytes: &[u8], expected: ResResultBody) {
         assert_eq!(expected, result);
     }
 
+    {
+        let buffer = Bytes::copy_from_slice(bytes);
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&buffer);
+        let result = ResResultBody::from_shared_cursor(&mut cursor, &buffer, Version::V4).unwrap();
+        assert_eq!(expected, result);
+    }
+
     {
         let mut buffer = Vec::new();
         let mut cursor = Cursor::new(&mut buffer);
@@ -980,6 +1188,69 @@ mod ctuple {
     }
 }
 
+#[cfg(test)]
+mod cvector {
+    use super::*;
+
+    #[test]
+    fn cvector() {
+        let class_name =
+            "org.apache.cassandra.db.marshal.VectorType(org.apache.cassandra.db.marshal.FloatType, 3)";
+        let mut bytes = vec![0, 0, 0, class_name.len() as u8];
+        bytes.extend_from_slice(class_name.as_bytes());
+
+        let expected = ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CVector(
+                Box::new(ColTypeOption {
+                    id: ColType::Float,
+                    value: None,
+                }),
+                3,
+            )),
+        };
+
+        {
+            let mut cursor: Cursor<&[u8]> = Cursor::new(&bytes);
+            let vector = ColTypeOption::from_cursor(&mut cursor).unwrap();
+            assert_eq!(vector, expected);
+        }
+
+        {
+            let mut buffer = Vec::new();
+            let mut cursor = Cursor::new(&mut buffer);
+            expected.serialize(&mut cursor);
+            assert_eq!(buffer, bytes);
+        }
+    }
+
+    #[test]
+    fn nested_cvector() {
+        let (element_type, dimensions) = parse_vector_type("VectorType(VectorType(FloatType,2),4)")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(dimensions, 4);
+        assert_eq!(
+            element_type.value,
+            Some(ColTypeOptionValue::CVector(
+                Box::new(ColTypeOption {
+                    id: ColType::Float,
+                    value: None,
+                }),
+                2,
+            ))
+        );
+
+        assert!(parse_vector_type("VectorType(FloatType)").is_err());
+        assert!(
+            parse_vector_type("org.apache.cassandra.db.marshal.DateRangeType")
+                .unwrap()
+                .is_none()
+        );
+    }
+}
+
 #[cfg(test)]
 mod col_spec {
     use super::*;
@@ -1181,6 +1452,7 @@ mod rows_metadata {
 
             columns_count: 2,
             paging_state: None,
+            new_metadata_id: None,
             global_table_spec: None,
             col_specs: vec![
                 ColSpec {
@@ -1221,11 +1493,43 @@ mod rows_metadata {
             assert_eq!(buffer, bytes);
         }
     }
+
+    #[test]
+    fn rows_metadata_changed() {
+        let bytes = &[
+            0, 0, 0, 0x0c, // rows metadata flag
+            0, 0, 0, 1, // columns count
+            0, 2, 1, 2, // new metadata id
+        ];
+
+        let expected = RowsMetadata {
+            flags: RowsMetadataFlags::NO_METADATA | RowsMetadataFlags::METADATA_CHANGED,
+            columns_count: 1,
+            paging_state: None,
+            new_metadata_id: Some(CBytesShort::new(vec![1, 2])),
+            global_table_spec: None,
+            col_specs: vec![],
+        };
+
+        {
+            let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+            let metadata = RowsMetadata::from_cursor(&mut cursor).unwrap();
+            assert_eq!(metadata, expected);
+        }
+
+        {
+            let mut buffer = Vec::new();
+            let mut cursor = Cursor::new(&mut buffer);
+            expected.serialize(&mut cursor);
+            assert_eq!(buffer, bytes);
+        }
+    }
 }
 
 #[cfg(test)]
 mod rows {
     use super::*;
+    use std::sync::Arc;
 
     #[test]
     fn test_rows() {
@@ -1253,6 +1557,7 @@ mod rows {
                 flags: RowsMetadataFlags::empty(),
                 columns_count: 2,
                 paging_state: None,
+                new_metadata_id: None,
                 global_table_spec: None,
                 col_specs: vec![
                     ColSpec {
@@ -1280,7 +1585,7 @@ mod rows {
                 ],
             },
             rows_count: 0,
-            rows_content: vec![],
+            rows_content: Bytes::new(),
         });
 
         test_encode_decode(bytes, expected);
@@ -1300,15 +1605,84 @@ mod rows {
                 flags: RowsMetadataFlags::NO_METADATA,
                 columns_count: 3,
                 paging_state: None,
+                new_metadata_id: None,
                 global_table_spec: None,
                 col_specs: vec![],
             },
             rows_count: 0,
-            rows_content: vec![],
+            rows_content: Bytes::new(),
         });
 
         test_encode_decode(bytes, expected);
     }
+
+    #[test]
+    fn test_rows_iter() {
+        let bytes = [
+            0, 0, 0, 2, // rows flag
+            0, 0, 0, 1, // rows metadata flag
+            0, 0, 0, 2, // columns count
+            0, 2, 107, 115, // ks
+            0, 1, 116, // t
+            0, 3, 102, 111, 111, // foo
+            0, 9, // col type id
+            0, 3, 98, 97, 114, // bar
+            0, 9, // col type id
+            0, 0, 0, 2, // rows count
+            0, 0, 0, 4, 0, 0, 0, 1, // foo 1
+            255, 255, 255, 255, // bar 1
+            0, 0, 0, 4, 0, 0, 0, 2, // foo 2
+            0, 0, 0, 4, 0, 0, 0, 3, // bar 2
+        ];
+        let buffer = Bytes::copy_from_slice(&bytes);
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&buffer);
+        let rows = ResResultBody::from_shared_cursor(&mut cursor, &buffer, Version::V4)
+            .unwrap()
+            .into_rows_iter()
+            .unwrap();
+
+        assert_eq!(rows.column_index().index_of("bar"), Some(1));
+        assert_eq!(rows.column_index().index_of("baz"), None);
+
+        let rows: Vec<Row> = rows.collect();
+        assert_eq!(rows.len(), 2);
+
+        let foo: Option<i32> = rows[0].get_by_name("foo").unwrap();
+        let bar: Option<i32> = rows[0].get_by_name("bar").unwrap();
+        assert_eq!(foo, Some(1));
+        assert_eq!(bar, None);
+
+        let foo: Option<i32> = rows[1].get_by_name("foo").unwrap();
+        let bar: Option<i32> = rows[1].get_by_index(1).unwrap();
+        assert_eq!(foo, Some(2));
+        assert_eq!(bar, Some(3));
+        assert!(Arc::ptr_eq(rows[0].column_index(), rows[1].column_index()));
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&bytes[..bytes.len() - 1]);
+        assert!(ResResultBody::from_cursor(&mut cursor, Version::V4).is_err());
+    }
+
+    #[test]
+    fn test_rows_share_buffer() {
+        let buffer = Bytes::from_static(&[
+            0, 0, 0, 2, // rows flag
+            0, 0, 0, 4, // rows metadataflag
+            0, 0, 0, 2, // columns count
+            0, 0, 0, 1, // rows count
+            0, 0, 0, 2, 1, 2, // value 1
+            255, 255, 255, 255, // value 2
+        ]);
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&buffer);
+        let rows = match ResResultBody::from_shared_cursor(&mut cursor, &buffer, Version::V4) {
+            Ok(ResResultBody::Rows(rows)) => rows,
+            _ => panic!("expected rows"),
+        };
+
+        assert_eq!(rows.rows_content.as_ptr(), buffer[16..].as_ptr());
+        assert_eq!(rows.into_iter().count(), 1);
+    }
 }
 
 #[cfg(test)]
@@ -1443,6 +1817,7 @@ mod prepared {
 
         let expected = ResResultBody::Prepared(BodyResResultPrepared {
             id: CBytesShort::new(to_short(1)),
+            result_metadata_id: None,
             metadata: PreparedMetadata {
                 pk_indexes: vec![0],
                 global_table_spec: None,
@@ -1475,6 +1850,7 @@ mod prepared {
                 flags: RowsMetadataFlags::empty(),
                 columns_count: 2,
                 paging_state: None,
+                new_metadata_id: None,
                 global_table_spec: None,
                 col_specs: vec![
                     ColSpec {
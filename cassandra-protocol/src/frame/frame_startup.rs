@@ -67,9 +67,10 @@ impl Frame {
             Flags::empty(),
             opcode,
             0,
-            body.serialize_to_vec(),
+            body.serialize_to_vec().into(),
             None,
             vec![],
+            Default::default(),
         )
     }
 }
@@ -1,17 +1,66 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::io::{Cursor, Read};
 
+use crate::compression::Compression;
 use crate::error;
-use crate::frame::FromCursor;
+use crate::frame::{FromCursor, Version};
 use crate::types::{from_cursor_str, from_cursor_string_list, serialize_str, SHORT_LEN};
 
 use super::Serialize;
 
+/// Supported CQL version option key.
+pub const CQL_VERSION: &str = "CQL_VERSION";
+/// Supported compression algorithms option key.
+pub const COMPRESSION: &str = "COMPRESSION";
+/// Supported protocol versions option key.
+pub const PROTOCOL_VERSIONS: &str = "PROTOCOL_VERSIONS";
+/// Server product type option key.
+pub const PRODUCT_TYPE: &str = "PRODUCT_TYPE";
+
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct BodyResSupported {
     pub data: HashMap<String, Vec<String>>,
 }
 
+impl BodyResSupported {
+    /// Returns the first CQL version supported by the server, if present.
+    pub fn cql_version(&self) -> Option<&str> {
+        self.first_value(CQL_VERSION)
+    }
+
+    /// Returns server product type, if present.
+    pub fn product_type(&self) -> Option<&str> {
+        self.first_value(PRODUCT_TYPE)
+    }
+
+    /// Returns compression algorithms supported by both the server and the driver.
+    pub fn compression(&self) -> Vec<Compression> {
+        self.values(COMPRESSION)
+            .map(|compression| Compression::from(compression.to_lowercase().as_str()))
+            .filter(|compression| compression.is_compressed())
+            .collect()
+    }
+
+    /// Returns non-beta protocol versions supported by both the server and the driver. Versions
+    /// are reported in the form of `4/v4` or `5/v5-beta`.
+    pub fn protocol_versions(&self) -> Vec<Version> {
+        self.values(PROTOCOL_VERSIONS)
+            .filter(|version| !version.ends_with("-beta"))
+            .filter_map(|version| version.split('/').next()?.parse::<u8>().ok())
+            .filter_map(|version| Version::try_from(version).ok())
+            .collect()
+    }
+
+    fn values(&self, key: &str) -> impl Iterator<Item = &String> {
+        self.data.get(key).into_iter().flatten()
+    }
+
+    fn first_value(&self, key: &str) -> Option<&str> {
+        self.values(key).next().map(String::as_str)
+    }
+}
+
 impl Serialize for BodyResSupported {
     fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
         (self.data.len() as i16).serialize(cursor);
@@ -71,4 +120,35 @@ mod tests {
             assert_eq!(buffer, bytes);
         }
     }
+
+    #[test]
+    fn body_res_supported_options() {
+        let mut data: HashMap<String, Vec<String>> = HashMap::new();
+        data.insert(CQL_VERSION.into(), vec!["3.4.5".into()]);
+        data.insert(
+            COMPRESSION.into(),
+            vec!["snappy".into(), "lz4".into(), "zstd".into()],
+        );
+        data.insert(
+            PROTOCOL_VERSIONS.into(),
+            vec![
+                "3/v3".into(),
+                "4/v4".into(),
+                "5/v5".into(),
+                "6/v6-beta".into(),
+            ],
+        );
+        let supported = BodyResSupported { data };
+
+        assert_eq!(supported.cql_version(), Some("3.4.5"));
+        assert_eq!(supported.product_type(), None);
+        assert_eq!(
+            supported.compression(),
+            vec![Compression::Snappy, Compression::Lz4]
+        );
+        assert_eq!(
+            supported.protocol_versions(),
+            vec![Version::V3, Version::V4, Version::V5]
+        );
+    }
 }
@@ -0,0 +1,429 @@
+//! Protocol v5 outer framing layer. After the STARTUP/READY (or AUTH_SUCCESS) exchange, frames
+//! are no longer sent directly over the wire - instead they are wrapped in checksummed segments.
+//! A segment is either self-contained (contains one or more complete frames) or carries a part of
+//! a single large frame, which needs to be reassembled from consecutive segments. Compression, if
+//! negotiated, is applied per segment and only LZ4 is supported.
+use derive_more::Constructor;
+use std::convert::TryInto;
+
+use crate::compression::{Compression, CompressionError};
+use crate::crc::{crc24, crc32};
+use crate::error;
+use crate::frame::{Frame, ParseFrameError};
+
+/// Maximum length of a single segment payload.
+pub const MAX_PAYLOAD_LEN: usize = 128 * 1024 - 1;
+
+const UNCOMPRESSED_HEADER_LEN: usize = 3;
+const COMPRESSED_HEADER_LEN: usize = 5;
+const HEADER_CRC_LEN: usize = 3;
+const PAYLOAD_CRC_LEN: usize = 4;
+
+const PAYLOAD_LEN_BITS: u32 = 17;
+const PAYLOAD_LEN_MASK: u64 = (1 << PAYLOAD_LEN_BITS) - 1;
+
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Constructor)]
+pub struct ParsedSegment {
+    /// How many bytes from the buffer have been read.
+    pub segment_len: usize,
+    /// The parsed segment.
+    pub segment: Segment,
+}
+
+#[derive(Debug)]
+pub enum ParseSegmentError {
+    /// There are not enough bytes to parse a single segment, [`Segment::from_buffer`] should be
+    /// recalled when it is possible that there are more bytes.
+    NotEnoughBytes,
+    /// Segment header checksum mismatch - the connection should be closed, since the stream
+    /// cannot be trusted anymore.
+    InvalidHeaderCrc,
+    /// Segment payload checksum mismatch.
+    InvalidPayloadCrc,
+    /// Only LZ4 compression is supported by protocol v5.
+    UnsupportedCompression(Compression),
+    DecompressionError(CompressionError),
+}
+
+/// A single protocol v5 segment.
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Constructor)]
+pub struct Segment {
+    /// Uncompressed payload.
+    pub payload: Vec<u8>,
+    /// Does the payload contain only complete frames.
+    pub is_self_contained: bool,
+}
+
+struct SegmentHeader {
+    payload_len: usize,
+    uncompressed_len: usize,
+    is_self_contained: bool,
+}
+
+impl Segment {
+    /// Returns the length of segment header, including its checksum, for given compression.
+    #[inline]
+    pub fn header_len(compression: Compression) -> usize {
+        if compression.is_compressed() {
+            COMPRESSED_HEADER_LEN + HEADER_CRC_LEN
+        } else {
+            UNCOMPRESSED_HEADER_LEN + HEADER_CRC_LEN
+        }
+    }
+
+    /// Returns the full length of a segment, based on its header. Useful when reading segments
+    /// from a stream, where the header is read first. The buffer needs to contain at least
+    /// [`Segment::header_len`] bytes.
+    pub fn len_from_header(
+        data: &[u8],
+        compression: Compression,
+    ) -> Result<usize, ParseSegmentError> {
+        let header = Self::parse_header(data, compression)?;
+        Ok(Self::header_len(compression) + header.payload_len + PAYLOAD_CRC_LEN)
+    }
+
+    /// Parses the raw bytes of a segment returning a [`Segment`] struct. Semantics are the same
+    /// as in [`Frame::from_buffer`].
+    pub fn from_buffer(
+        data: &[u8],
+        compression: Compression,
+    ) -> Result<ParsedSegment, ParseSegmentError> {
+        let header = Self::parse_header(data, compression)?;
+
+        let payload_start = Self::header_len(compression);
+        let payload_end = payload_start + header.payload_len;
+        let segment_len = payload_end + PAYLOAD_CRC_LEN;
+        if data.len() < segment_len {
+            return Err(ParseSegmentError::NotEnoughBytes);
+        }
+
+        let payload = &data[payload_start..payload_end];
+        let payload_crc = u32::from_le_bytes(data[payload_end..segment_len].try_into().unwrap());
+        if payload_crc != crc32(payload) {
+            return Err(ParseSegmentError::InvalidPayloadCrc);
+        }
+
+        // uncompressed length of 0 means the sender decided not to compress the payload
+        let payload = if header.uncompressed_len > 0 {
+            lz4_flex::block::decompress(payload, header.uncompressed_len).map_err(|error| {
+                ParseSegmentError::DecompressionError(CompressionError::Lz4(std::io::Error::other(
+                    error,
+                )))
+            })?
+        } else {
+            payload.to_vec()
+        };
+
+        Ok(ParsedSegment::new(
+            segment_len,
+            Segment::new(payload, header.is_self_contained),
+        ))
+    }
+
+    /// Encodes the segment using given compression. Payload length must not exceed
+    /// [`MAX_PAYLOAD_LEN`].
+    pub fn encode_with(&self, compression: Compression) -> error::Result<Vec<u8>> {
+        if self.payload.len() > MAX_PAYLOAD_LEN {
+            return Err(format!(
+                "Segment payload too large: {} (max: {})",
+                self.payload.len(),
+                MAX_PAYLOAD_LEN
+            )
+            .into());
+        }
+
+        let self_contained_bit = if self.is_self_contained { 1 } else { 0 };
+
+        match compression {
+            Compression::None => {
+                let header = self.payload.len() as u64 | (self_contained_bit << PAYLOAD_LEN_BITS);
+                Ok(Self::encode_segment(
+                    &header.to_le_bytes()[..UNCOMPRESSED_HEADER_LEN],
+                    &self.payload,
+                ))
+            }
+            Compression::Lz4 => {
+                let compressed = lz4_flex::block::compress(&self.payload);
+
+                // compression might actually increase the size, so send uncompressed data in such
+                // case (signaled by uncompressed length set to 0)
+                let (payload, uncompressed_len) = if compressed.len() < self.payload.len() {
+                    (compressed.as_slice(), self.payload.len() as u64)
+                } else {
+                    (self.payload.as_slice(), 0)
+                };
+
+                let header = payload.len() as u64
+                    | (uncompressed_len << PAYLOAD_LEN_BITS)
+                    | (self_contained_bit << (2 * PAYLOAD_LEN_BITS));
+                Ok(Self::encode_segment(
+                    &header.to_le_bytes()[..COMPRESSED_HEADER_LEN],
+                    payload,
+                ))
+            }
+            Compression::Snappy => {
+                Err("Snappy compression is not supported by protocol v5!".into())
+            }
+        }
+    }
+
+    /// Splits an encoded frame into segments, which should be sent in order. Frames which fit in
+    /// a single segment result in a self-contained segment.
+    pub fn split_frame(data: &[u8]) -> Vec<Segment> {
+        if data.len() <= MAX_PAYLOAD_LEN {
+            return vec![Segment::new(data.to_vec(), true)];
+        }
+
+        data.chunks(MAX_PAYLOAD_LEN)
+            .map(|chunk| Segment::new(chunk.to_vec(), false))
+            .collect()
+    }
+
+    fn encode_segment(header: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut result =
+            Vec::with_capacity(header.len() + HEADER_CRC_LEN + payload.len() + PAYLOAD_CRC_LEN);
+
+        result.extend_from_slice(header);
+        result.extend_from_slice(&crc24(header).to_le_bytes()[..HEADER_CRC_LEN]);
+        result.extend_from_slice(payload);
+        result.extend_from_slice(&crc32(payload).to_le_bytes());
+
+        result
+    }
+
+    fn parse_header(
+        data: &[u8],
+        compression: Compression,
+    ) -> Result<SegmentHeader, ParseSegmentError> {
+        let raw_header_len = match compression {
+            Compression::None => UNCOMPRESSED_HEADER_LEN,
+            Compression::Lz4 => COMPRESSED_HEADER_LEN,
+            Compression::Snappy => {
+                return Err(ParseSegmentError::UnsupportedCompression(compression))
+            }
+        };
+
+        if data.len() < raw_header_len + HEADER_CRC_LEN {
+            return Err(ParseSegmentError::NotEnoughBytes);
+        }
+
+        let raw_header = &data[..raw_header_len];
+
+        let mut crc_bytes = [0; 4];
+        crc_bytes[..HEADER_CRC_LEN]
+            .copy_from_slice(&data[raw_header_len..raw_header_len + HEADER_CRC_LEN]);
+
+        if u32::from_le_bytes(crc_bytes) != crc24(raw_header) {
+            return Err(ParseSegmentError::InvalidHeaderCrc);
+        }
+
+        let mut header_bytes = [0; 8];
+        header_bytes[..raw_header_len].copy_from_slice(raw_header);
+
+        let header = u64::from_le_bytes(header_bytes);
+        let payload_len = (header & PAYLOAD_LEN_MASK) as usize;
+
+        Ok(if compression.is_compressed() {
+            SegmentHeader {
+                payload_len,
+                uncompressed_len: ((header >> PAYLOAD_LEN_BITS) & PAYLOAD_LEN_MASK) as usize,
+                is_self_contained: (header >> (2 * PAYLOAD_LEN_BITS)) & 1 == 1,
+            }
+        } else {
+            SegmentHeader {
+                payload_len,
+                uncompressed_len: 0,
+                is_self_contained: (header >> PAYLOAD_LEN_BITS) & 1 == 1,
+            }
+        })
+    }
+}
+
+/// Extracts frames from consecutive segments, taking care of reassembling frames which span
+/// multiple segments.
+#[derive(Debug, Default, Clone)]
+pub struct SegmentFrameDecoder {
+    partial_frame: Vec<u8>,
+}
+
+impl SegmentFrameDecoder {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Consumes given segment and returns all frames which are complete.
+    pub fn decode(&mut self, segment: Segment) -> error::Result<Vec<Frame>> {
+        if segment.is_self_contained {
+            if !self.partial_frame.is_empty() {
+                return Err(
+                    "Received a self-contained segment while waiting for the rest of a frame!"
+                        .into(),
+                );
+            }
+
+            let mut frames = vec![];
+            let mut data = segment.payload.as_slice();
+
+            while !data.is_empty() {
+                let parsed = Frame::from_buffer(data, Compression::None).map_err(frame_error)?;
+
+                frames.push(parsed.frame);
+                data = &data[parsed.frame_len..];
+            }
+
+            return Ok(frames);
+        }
+
+        self.partial_frame.extend_from_slice(&segment.payload);
+
+        match Frame::from_buffer(&self.partial_frame, Compression::None) {
+            Ok(parsed) => {
+                if parsed.frame_len != self.partial_frame.len() {
+                    return Err("Segments contain more data than a single frame!".into());
+                }
+
+                self.partial_frame.clear();
+                Ok(vec![parsed.frame])
+            }
+            Err(ParseFrameError::NotEnoughBytes) => Ok(vec![]),
+            Err(error) => Err(frame_error(error)),
+        }
+    }
+}
+
+fn frame_error(error: ParseFrameError) -> error::Error {
+    error::Error::General(format!("Error parsing frame from segment: {:?}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Direction, Flags, Opcode, Version};
+
+    fn test_frame(body: Vec<u8>) -> Frame {
+        Frame::new(
+            Version::V5,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            5,
+            body.into(),
+            None,
+            vec![],
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn test_uncompressed_roundtrip() {
+        let segment = Segment::new(vec![1, 2, 3, 4], true);
+        let encoded = segment.encode_with(Compression::None).unwrap();
+
+        assert_eq!(encoded.len(), 6 + 4 + 4);
+        assert_eq!(
+            Segment::len_from_header(&encoded, Compression::None).unwrap(),
+            encoded.len()
+        );
+
+        let parsed = Segment::from_buffer(&encoded, Compression::None).unwrap();
+        assert_eq!(parsed.segment_len, encoded.len());
+        assert_eq!(parsed.segment, segment);
+    }
+
+    #[test]
+    fn test_compressed_roundtrip() {
+        let segment = Segment::new(vec![7; 1024], false);
+        let encoded = segment.encode_with(Compression::Lz4).unwrap();
+
+        assert!(encoded.len() < 1024);
+
+        let parsed = Segment::from_buffer(&encoded, Compression::Lz4).unwrap();
+        assert_eq!(parsed.segment, segment);
+    }
+
+    #[test]
+    fn test_incompressible_roundtrip() {
+        let segment = Segment::new(vec![1, 2, 3], true);
+        let encoded = segment.encode_with(Compression::Lz4).unwrap();
+
+        // uncompressed length set to 0
+        assert_eq!(encoded.len(), 8 + 3 + 4);
+
+        let parsed = Segment::from_buffer(&encoded, Compression::Lz4).unwrap();
+        assert_eq!(parsed.segment, segment);
+    }
+
+    #[test]
+    fn test_not_enough_bytes() {
+        let encoded = Segment::new(vec![1, 2, 3], true)
+            .encode_with(Compression::None)
+            .unwrap();
+
+        assert!(matches!(
+            Segment::from_buffer(&encoded[..4], Compression::None),
+            Err(ParseSegmentError::NotEnoughBytes)
+        ));
+        assert!(matches!(
+            Segment::from_buffer(&encoded[..encoded.len() - 1], Compression::None),
+            Err(ParseSegmentError::NotEnoughBytes)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_crc() {
+        let mut encoded = Segment::new(vec![1, 2, 3], true)
+            .encode_with(Compression::None)
+            .unwrap();
+
+        encoded[7] = 0;
+        assert!(matches!(
+            Segment::from_buffer(&encoded, Compression::None),
+            Err(ParseSegmentError::InvalidPayloadCrc)
+        ));
+
+        encoded[0] = 0;
+        assert!(matches!(
+            Segment::from_buffer(&encoded, Compression::None),
+            Err(ParseSegmentError::InvalidHeaderCrc)
+        ));
+    }
+
+    #[test]
+    fn test_payload_too_large() {
+        assert!(Segment::new(vec![0; MAX_PAYLOAD_LEN + 1], true)
+            .encode_with(Compression::None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_decode_self_contained() {
+        let first = test_frame(vec![1, 2, 3]);
+        let second = test_frame(vec![4]);
+
+        let mut payload = first.encode_with(Compression::None).unwrap();
+        payload.append(&mut second.encode_with(Compression::None).unwrap());
+
+        let mut decoder = SegmentFrameDecoder::new();
+        let frames = decoder.decode(Segment::new(payload, true)).unwrap();
+
+        assert_eq!(frames, vec![first, second]);
+    }
+
+    #[test]
+    fn test_decode_multi_segment() {
+        let frame = test_frame(vec![3; MAX_PAYLOAD_LEN * 2]);
+        let segments = Segment::split_frame(&frame.encode_with(Compression::None).unwrap());
+
+        assert_eq!(segments.len(), 3);
+        assert!(segments.iter().all(|segment| !segment.is_self_contained));
+
+        let mut decoder = SegmentFrameDecoder::new();
+        let mut frames = vec![];
+        for segment in segments {
+            frames.append(&mut decoder.decode(segment).unwrap());
+        }
+
+        assert_eq!(frames, vec![frame]);
+    }
+}
@@ -11,6 +11,7 @@ pub mod types;
 pub mod authenticators;
 pub mod compression;
 pub mod consistency;
+pub mod crc;
 pub mod error;
 pub mod events;
 pub mod token;
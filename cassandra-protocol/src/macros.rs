@@ -801,6 +801,38 @@ macro_rules! as_rust_type {
             ))),
         }
     };
+    ($data_type_option:ident, $data_value:ident, Duration) => {
+        match $data_type_option.id {
+            ColType::Duration => match $data_value.as_slice() {
+                Some(ref bytes) => decode_duration(bytes).map(Some).map_err(Into::into),
+                None => Ok(None),
+            },
+            _ => Err(crate::error::Error::General(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into Duration (valid types: Duration).",
+                $data_type_option.id
+            ))),
+        }
+    };
+    ($data_type_option:ident, $data_value:ident, Vec<f32>) => {
+        match &$data_type_option.value {
+            Some(crate::frame::frame_result::ColTypeOptionValue::CVector(element_type, dimensions))
+                if element_type.id == ColType::Float =>
+            {
+                match $data_value.as_slice() {
+                    Some(ref bytes) => decode_float_vector(bytes, *dimensions)
+                        .map(Some)
+                        .map_err(Into::into),
+                    None => Ok(None),
+                }
+            }
+            _ => Err(crate::error::Error::General(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into Vec<f32> (valid types: vector<float, n>).",
+                $data_type_option
+            ))),
+        }
+    };
     ($data_type_option:ident, $data_value:ident, NaiveDateTime) => {
         match $data_type_option.id {
             ColType::Timestamp => match $data_value.as_slice() {
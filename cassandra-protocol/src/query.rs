@@ -7,7 +7,7 @@ pub mod query_values;
 pub mod utils;
 
 pub use crate::query::batch_query_builder::{BatchQueryBuilder, QueryBatch};
-pub use crate::query::prepared_query::PreparedQuery;
+pub use crate::query::prepared_query::{PreparedQuery, PreparedResultMetadata};
 pub use crate::query::query_flags::QueryFlags;
 pub use crate::query::query_params::QueryParams;
 pub use crate::query::query_params_builder::QueryParamsBuilder;
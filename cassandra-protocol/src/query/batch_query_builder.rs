@@ -12,6 +12,8 @@ pub struct BatchQueryBuilder {
     consistency: Consistency,
     serial_consistency: Option<Consistency>,
     timestamp: Option<i64>,
+    keyspace: Option<String>,
+    now_in_seconds: Option<i32>,
 }
 
 impl Default for BatchQueryBuilder {
@@ -22,6 +24,8 @@ impl Default for BatchQueryBuilder {
             consistency: Consistency::One,
             serial_consistency: None,
             timestamp: None,
+            keyspace: None,
+            now_in_seconds: None,
         }
     }
 }
@@ -48,7 +52,7 @@ impl BatchQueryBuilder {
     /// Add a query (prepared one)
     pub fn add_query_prepared(mut self, query: &PreparedQuery, values: QueryValues) -> Self {
         self.queries.push(BatchQuery {
-            subject: BatchQuerySubj::PreparedId(query.id.clone()),
+            subject: BatchQuerySubj::PreparedId(query.id().as_ref().clone()),
             values,
         });
         self
@@ -74,6 +78,18 @@ impl BatchQueryBuilder {
         self
     }
 
+    /// Sets new keyspace (protocol V5+).
+    pub fn with_keyspace(mut self, keyspace: String) -> Self {
+        self.keyspace = Some(keyspace);
+        self
+    }
+
+    /// Sets new "now" in seconds (protocol V5+).
+    pub fn with_now_in_seconds(mut self, now_in_seconds: i32) -> Self {
+        self.now_in_seconds = Some(now_in_seconds);
+        self
+    }
+
     pub fn build(self) -> CResult<BodyReqBatch> {
         let with_names_for_values = self.queries.iter().all(|q| q.values.has_names());
 
@@ -94,6 +110,8 @@ impl BatchQueryBuilder {
             consistency: self.consistency,
             serial_consistency: self.serial_consistency,
             timestamp: self.timestamp,
+            keyspace: self.keyspace,
+            now_in_seconds: self.now_in_seconds,
         })
     }
 }
@@ -1,9 +1,97 @@
+use arc_swap::ArcSwap;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::frame::frame_result::RowsMetadata;
 use crate::types::CBytesShort;
 
-#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+/// Result metadata of a prepared statement. Can change when the underlying table is altered.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreparedResultMetadata {
+    /// Id of the metadata, sent along with executed statements (protocol V5+).
+    pub id: Option<CBytesShort>,
+    pub metadata: RowsMetadata,
+}
+
+#[derive(Debug)]
 pub struct PreparedQuery {
-    pub id: CBytesShort,
+    /// Current statement id - gets updated when re-preparing the statement results in a new one,
+    /// e.g. after a schema change.
+    pub id: ArcSwap<CBytesShort>,
     pub query: String,
     pub keyspace: Option<String>,
     pub pk_indexes: Vec<i16>,
+    /// Current result metadata - gets updated when the server reports a change.
+    pub result_metadata: ArcSwap<PreparedResultMetadata>,
+}
+
+impl PreparedQuery {
+    /// Returns current statement id.
+    #[inline]
+    pub fn id(&self) -> Arc<CBytesShort> {
+        self.id.load_full()
+    }
+
+    /// Replaces statement id with a new one, received when re-preparing the statement.
+    #[inline]
+    pub fn update_id(&self, id: CBytesShort) {
+        self.id.store(Arc::new(id));
+    }
+
+    /// Returns current result metadata id, if known.
+    #[inline]
+    pub fn result_metadata_id(&self) -> Option<CBytesShort> {
+        self.result_metadata.load().id.clone()
+    }
+
+    /// Replaces result metadata with new one, received from the server.
+    #[inline]
+    pub fn update_result_metadata(&self, result_metadata: PreparedResultMetadata) {
+        self.result_metadata.store(Arc::new(result_metadata));
+    }
+
+    // the id is not a part of the key, since it can change
+    #[inline]
+    fn key(&self) -> (&String, &Option<String>, &Vec<i16>) {
+        (&self.query, &self.keyspace, &self.pk_indexes)
+    }
+}
+
+impl Clone for PreparedQuery {
+    fn clone(&self) -> Self {
+        PreparedQuery {
+            id: ArcSwap::new(self.id.load_full()),
+            query: self.query.clone(),
+            keyspace: self.keyspace.clone(),
+            pk_indexes: self.pk_indexes.clone(),
+            result_metadata: ArcSwap::new(self.result_metadata.load_full()),
+        }
+    }
+}
+
+impl PartialEq for PreparedQuery {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for PreparedQuery {}
+
+impl PartialOrd for PreparedQuery {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PreparedQuery {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for PreparedQuery {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
 }
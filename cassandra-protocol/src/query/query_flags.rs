@@ -1,7 +1,11 @@
 use bitflags::bitflags;
+use std::io::{Cursor, Read};
+
+use crate::error;
+use crate::frame::{Serialize, Version};
 
 bitflags! {
-    pub struct QueryFlags: u8 {
+    pub struct QueryFlags: u32 {
         /// If set indicates that Query Params contains value.
         const VALUE = 0x01;
         /// If set indicates that Query Params does not contain metadata.
@@ -16,6 +20,35 @@ bitflags! {
         const WITH_DEFAULT_TIMESTAMP = 0x20;
         /// If set indicates that Query Params values are named ones.
         const WITH_NAMES_FOR_VALUES = 0x40;
+        /// If set indicates that Query Params contains keyspace name (protocol V5+).
+        const WITH_KEYSPACE = 0x80;
+        /// If set indicates that Query Params contains "now" in seconds (protocol V5+).
+        const WITH_NOW_IN_SECONDS = 0x100;
+    }
+}
+
+impl QueryFlags {
+    /// Serializes flags using the representation appropriate for given protocol version - a
+    /// single byte before V5 and an int since then.
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
+        if version >= Version::V5 {
+            self.bits().serialize(cursor);
+        } else {
+            (self.bits() as u8).serialize(cursor);
+        }
+    }
+
+    /// Deserializes flags using the representation appropriate for given protocol version.
+    pub fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> error::Result<Self> {
+        if version >= Version::V5 {
+            let mut buff = [0; 4];
+            cursor.read_exact(&mut buff)?;
+            Ok(QueryFlags::from_bits_truncate(u32::from_be_bytes(buff)))
+        } else {
+            let mut buff = [0];
+            cursor.read_exact(&mut buff)?;
+            Ok(QueryFlags::from_bits_truncate(buff[0] as u32))
+        }
     }
 }
 
@@ -25,3 +58,26 @@ impl Default for QueryFlags {
         QueryFlags::empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_serialize_flags_per_version() {
+        let flags = QueryFlags::VALUE | QueryFlags::PAGE_SIZE;
+
+        let mut buf = vec![];
+        flags.serialize(&mut Cursor::new(&mut buf), Version::V4);
+        assert_eq!(buf, vec![0x05]);
+
+        let flags = QueryFlags::VALUE | QueryFlags::WITH_KEYSPACE;
+
+        let mut buf = vec![];
+        flags.serialize(&mut Cursor::new(&mut buf), Version::V5);
+        assert_eq!(buf, vec![0, 0, 0, 0x81]);
+
+        let flags = QueryFlags::from_cursor(&mut Cursor::new(&[0, 0, 1, 0][..]), Version::V5);
+        assert_eq!(flags.unwrap(), QueryFlags::WITH_NOW_IN_SECONDS);
+    }
+}
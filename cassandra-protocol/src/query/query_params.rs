@@ -3,10 +3,10 @@ use std::io::{Cursor, Read};
 
 use crate::consistency::Consistency;
 use crate::frame::traits::FromCursor;
-use crate::frame::Serialize;
+use crate::frame::{Serialize, Version};
 use crate::query::query_flags::QueryFlags;
 use crate::query::query_values::QueryValues;
-use crate::types::{from_cursor_str, value::Value, CIntShort};
+use crate::types::{from_cursor_str, serialize_str, value::Value, CInt, CIntShort};
 use crate::types::{CBytes, CLong};
 use crate::Error;
 
@@ -27,10 +27,15 @@ pub struct QueryParams {
     pub serial_consistency: Option<Consistency>,
     /// Timestamp.
     pub timestamp: Option<CLong>,
+    /// Keyspace in which the query should be executed, superseding the one the connection is
+    /// bound to. Requires protocol V5+.
+    pub keyspace: Option<String>,
+    /// Current time override used by the server, in seconds. Requires protocol V5+.
+    pub now_in_seconds: Option<CInt>,
 }
 
 impl QueryParams {
-    fn flags(&self) -> QueryFlags {
+    fn flags(&self, version: Version, timestamp: Option<CLong>) -> QueryFlags {
         let mut flags = QueryFlags::empty();
 
         if self.values.is_some() {
@@ -53,21 +58,43 @@ impl QueryParams {
             flags.insert(QueryFlags::WITH_SERIAL_CONSISTENCY);
         }
 
-        if self.timestamp.is_some() {
+        if timestamp.is_some() {
             flags.insert(QueryFlags::WITH_DEFAULT_TIMESTAMP);
         }
 
+        if version >= Version::V5 {
+            if self.keyspace.is_some() {
+                flags.insert(QueryFlags::WITH_KEYSPACE);
+            }
+
+            if self.now_in_seconds.is_some() {
+                flags.insert(QueryFlags::WITH_NOW_IN_SECONDS);
+            }
+        }
+
         flags
     }
-}
 
-impl Serialize for QueryParams {
-    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
+    /// Serializes parameters for given protocol version. Options not supported by the version are
+    /// skipped.
+    #[inline]
+    pub fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>, version: Version) {
+        self.serialize_with_timestamp(cursor, version, self.timestamp);
+    }
+
+    /// Serializes parameters for given protocol version, using given default timestamp instead of
+    /// [`QueryParams::timestamp`]. Allows setting a timestamp without copying the parameters.
+    pub fn serialize_with_timestamp(
+        &self,
+        cursor: &mut Cursor<&mut Vec<u8>>,
+        version: Version,
+        timestamp: Option<CLong>,
+    ) {
         let consistency: CIntShort = self.consistency.into();
         consistency.serialize(cursor);
 
-        let flag_bits = self.flags().bits();
-        flag_bits.serialize(cursor);
+        let flags = self.flags(version, timestamp);
+        flags.serialize(cursor, version);
 
         if let Some(values) = &self.values {
             let len = values.len() as CIntShort;
@@ -88,20 +115,27 @@ impl Serialize for QueryParams {
             serial_consistency.serialize(cursor);
         }
 
-        if let Some(timestamp) = self.timestamp {
+        if let Some(timestamp) = timestamp {
             timestamp.serialize(cursor);
         }
+
+        if flags.contains(QueryFlags::WITH_KEYSPACE) {
+            if let Some(keyspace) = &self.keyspace {
+                serialize_str(cursor, keyspace);
+            }
+        }
+
+        if flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            if let Some(now_in_seconds) = self.now_in_seconds {
+                now_in_seconds.serialize(cursor);
+            }
+        }
     }
-}
 
-impl FromCursor for QueryParams {
-    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> Result<QueryParams, Error> {
+    /// Deserializes parameters encoded with given protocol version.
+    pub fn from_cursor(cursor: &mut Cursor<&[u8]>, version: Version) -> Result<QueryParams, Error> {
         let consistency = Consistency::from_cursor(cursor)?;
-        let flags = {
-            let mut buff = [0];
-            cursor.read_exact(&mut buff)?;
-            QueryFlags::from_bits_truncate(buff[0])
-        };
+        let flags = QueryFlags::from_cursor(cursor, version)?;
 
         let values = if flags.contains(QueryFlags::VALUE) {
             let number_of_values = {
@@ -161,6 +195,18 @@ impl FromCursor for QueryParams {
             None
         };
 
+        let keyspace = if flags.contains(QueryFlags::WITH_KEYSPACE) {
+            Some(from_cursor_str(cursor)?.to_string())
+        } else {
+            None
+        };
+
+        let now_in_seconds = if flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            Some(CInt::from_cursor(cursor)?)
+        } else {
+            None
+        };
+
         let with_names = flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES);
 
         Ok(QueryParams {
@@ -171,6 +217,47 @@ impl FromCursor for QueryParams {
             paging_state,
             serial_consistency,
             timestamp,
+            keyspace,
+            now_in_seconds,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_roundtrip_v5_options() {
+        let params = QueryParams {
+            consistency: Consistency::One,
+            keyspace: Some("ks".into()),
+            now_in_seconds: Some(10),
+            ..Default::default()
+        };
+
+        let mut buf = vec![];
+        params.serialize(&mut Cursor::new(&mut buf), Version::V5);
+        assert_eq!(
+            buf,
+            vec![0, 1, 0, 0, 0x01, 0x80, 0, 2, 107, 115, 0, 0, 0, 10]
+        );
+
+        let decoded = QueryParams::from_cursor(&mut Cursor::new(buf.as_slice()), Version::V5);
+        assert_eq!(decoded.unwrap(), params);
+    }
+
+    #[test]
+    fn should_skip_v5_options_in_v4() {
+        let params = QueryParams {
+            consistency: Consistency::One,
+            keyspace: Some("ks".into()),
+            now_in_seconds: Some(10),
+            ..Default::default()
+        };
+
+        let mut buf = vec![];
+        params.serialize(&mut Cursor::new(&mut buf), Version::V4);
+        assert_eq!(buf, vec![0, 1, 0]);
+    }
+}
@@ -12,6 +12,8 @@ pub struct QueryParamsBuilder {
     paging_state: Option<CBytes>,
     serial_consistency: Option<Consistency>,
     timestamp: Option<i64>,
+    keyspace: Option<String>,
+    now_in_seconds: Option<i32>,
 }
 
 impl QueryParamsBuilder {
@@ -81,6 +83,18 @@ impl QueryParamsBuilder {
         self
     }
 
+    /// Sets new keyspace (protocol V5+).
+    pub fn with_keyspace(mut self, keyspace: String) -> Self {
+        self.keyspace = Some(keyspace);
+        self
+    }
+
+    /// Sets new "now" in seconds (protocol V5+).
+    pub fn with_now_in_seconds(mut self, now_in_seconds: i32) -> Self {
+        self.now_in_seconds = Some(now_in_seconds);
+        self
+    }
+
     /// Finalizes query building process and returns query itself
     pub fn build(self) -> QueryParams {
         QueryParams {
@@ -91,6 +105,8 @@ impl QueryParamsBuilder {
             paging_state: self.paging_state,
             serial_consistency: self.serial_consistency,
             timestamp: self.timestamp,
+            keyspace: self.keyspace,
+            now_in_seconds: self.now_in_seconds,
         }
     }
 }
@@ -1,4 +1,6 @@
+use bytes::Bytes;
 use derive_more::Constructor;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io::{self, Write};
 use std::io::{Cursor, Read};
@@ -22,8 +24,10 @@ const NULL_SHORT_LEN: CIntShort = -1;
 #[macro_use]
 pub mod blob;
 pub mod cassandra_type;
+pub mod custom_type;
 pub mod data_serialization_types;
 pub mod decimal;
+pub mod duration;
 pub mod from_cdrs;
 pub mod list;
 pub mod map;
@@ -31,18 +35,22 @@ pub mod rows;
 pub mod tuple;
 pub mod udt;
 pub mod value;
+pub mod vector;
+pub mod vint;
 
 pub mod prelude {
     pub use crate::error::{Error, Result};
     pub use crate::frame::{TryFromRow, TryFromUdt};
     pub use crate::types::blob::Blob;
     pub use crate::types::decimal::Decimal;
+    pub use crate::types::duration::Duration;
     pub use crate::types::list::List;
     pub use crate::types::map::Map;
     pub use crate::types::rows::Row;
     pub use crate::types::tuple::Tuple;
     pub use crate::types::udt::Udt;
     pub use crate::types::value::{Bytes, Value};
+    pub use crate::types::vector::FloatVector;
     pub use crate::types::AsRustType;
 }
 
@@ -270,15 +278,56 @@ pub fn from_cursor_string_list(cursor: &mut Cursor<&[u8]>) -> CDRSResult<Vec<Str
     Ok(list)
 }
 
+/// Serializes a `[bytes map]`, e.g. a custom payload.
+pub fn serialize_bytes_map(cursor: &mut Cursor<&mut Vec<u8>>, map: &HashMap<String, Vec<u8>>) {
+    let len = map.len() as CIntShort;
+    len.serialize(cursor);
+
+    for (key, value) in map {
+        serialize_str(cursor, key);
+
+        let len = value.len() as CInt;
+        len.serialize(cursor);
+        let _ = cursor.write(value);
+    }
+}
+
+/// Reads a `[bytes map]`, e.g. a custom payload. Null values are read as empty ones, so both are
+/// indistinguishable in the resulting map.
+pub fn from_cursor_bytes_map(cursor: &mut Cursor<&[u8]>) -> CDRSResult<HashMap<String, Vec<u8>>> {
+    let mut buff = [0; SHORT_LEN];
+    cursor.read_exact(&mut buff)?;
+
+    let len = CIntShort::from_be_bytes(buff);
+    let mut map = HashMap::with_capacity(len.max(0) as usize);
+    for _ in 0..len {
+        let key = from_cursor_str(cursor)?.to_string();
+        let value = CBytes::from_cursor(cursor)?
+            .into_bytes()
+            .unwrap_or_default();
+        map.insert(key, value);
+    }
+
+    Ok(map)
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Ord, PartialOrd)]
 /// The structure that represents Cassandra byte type.
 pub struct CBytes {
-    bytes: Option<Vec<u8>>,
+    bytes: Option<Bytes>,
 }
 
 impl CBytes {
     #[inline]
     pub fn new(bytes: Vec<u8>) -> CBytes {
+        CBytes {
+            bytes: Some(bytes.into()),
+        }
+    }
+
+    /// Creates Cassandra bytes from a shared buffer, without copying
+    #[inline]
+    pub fn from_shared(bytes: Bytes) -> CBytes {
         CBytes { bytes: Some(bytes) }
     }
 
@@ -288,9 +337,16 @@ impl CBytes {
         CBytes { bytes: None }
     }
 
-    /// Converts `CBytes` into a plain array of bytes
+    /// Converts `CBytes` into a plain array of bytes. Note: this copies the data, if it is shared
+    /// with other values, e.g. the frame body it was parsed from.
     #[inline]
     pub fn into_bytes(self) -> Option<Vec<u8>> {
+        self.bytes.map(Vec::from)
+    }
+
+    /// Converts `CBytes` into a shared buffer, without copying
+    #[inline]
+    pub fn into_shared(self) -> Option<Bytes> {
         self.bytes
     }
 
@@ -310,7 +366,23 @@ impl CBytes {
     #[inline]
     #[deprecated(note = "Use into_bytes().")]
     pub fn into_plain(self) -> Option<Vec<u8>> {
-        self.bytes
+        self.into_bytes()
+    }
+
+    /// Reads bytes from a cursor backed by given buffer, returning a slice of the buffer instead
+    /// of a copy. Values are copied, if the cursor is not backed by `buffer`.
+    pub(crate) fn from_shared_cursor(
+        cursor: &mut Cursor<&[u8]>,
+        buffer: &Bytes,
+    ) -> CDRSResult<CBytes> {
+        let len = CInt::from_cursor(cursor)?;
+        // null or not set value
+        if len < 0 {
+            return Ok(CBytes { bytes: None });
+        }
+
+        cursor_next_value_ref(cursor, len as usize)
+            .map(|bytes| CBytes::from_shared(shared_slice(buffer, bytes)))
     }
 }
 
@@ -334,7 +406,7 @@ impl Serialize for CBytes {
             Some(bytes) => {
                 let len = bytes.len() as CInt;
                 len.serialize(cursor);
-                bytes.serialize(cursor);
+                bytes.as_ref().serialize(cursor);
             }
             None => NULL_INT_LEN.serialize(cursor),
         }
@@ -486,21 +558,29 @@ pub fn cursor_next_value(cursor: &mut Cursor<&[u8]>, len: usize) -> CDRSResult<V
     Ok(buff)
 }
 
+/// Returns given bytes as a slice of the buffer, if they are a part of it, or a copy otherwise.
+pub(crate) fn shared_slice(buffer: &Bytes, bytes: &[u8]) -> Bytes {
+    let buffer_range = buffer.as_ptr_range();
+    let bytes_range = bytes.as_ptr_range();
+
+    if buffer_range.start <= bytes_range.start && bytes_range.end <= buffer_range.end {
+        buffer.slice_ref(bytes)
+    } else {
+        Bytes::copy_from_slice(bytes)
+    }
+}
+
 pub fn cursor_next_value_ref<'a>(
     cursor: &mut Cursor<&'a [u8]>,
     len: usize,
 ) -> CDRSResult<&'a [u8]> {
     let start = cursor.position() as usize;
-    let result = &cursor.get_ref()[start..start + len];
+    let result = cursor.get_ref().get(start..start + len).ok_or_else(|| {
+        CdrsError::General("cursor_next_value_ref could not retrieve a full slice".into())
+    })?;
     cursor.set_position(cursor.position() + len as u64);
 
-    if result.len() != len {
-        Err(CdrsError::General(
-            "cursor_next_value_ref could not retrieve a full slice".into(),
-        ))
-    } else {
-        Ok(result)
-    }
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -567,6 +647,29 @@ mod tests {
         assert_eq!(list, vec!("foo".to_string(), "fop".to_string()));
     }
 
+    #[test]
+    fn test_bytes_map() {
+        let map: HashMap<String, Vec<u8>> = vec![("foo".to_string(), vec![1, 2, 3])]
+            .into_iter()
+            .collect();
+
+        let mut buf = vec![];
+        serialize_bytes_map(&mut Cursor::new(&mut buf), &map);
+        assert_eq!(buf, &[0, 1, 0, 3, 102, 111, 111, 0, 0, 0, 3, 1, 2, 3]);
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&buf);
+        assert_eq!(from_cursor_bytes_map(&mut cursor).unwrap(), map);
+
+        // null value
+        let buf = [0, 1, 0, 3, 102, 111, 111, 255, 255, 255, 255];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&buf);
+        assert_eq!(
+            from_cursor_bytes_map(&mut cursor).unwrap(),
+            vec![("foo".to_string(), vec![])].into_iter().collect()
+        );
+        assert_eq!(cursor.position(), buf.len() as u64);
+    }
+
     // CBytes
     #[test]
     fn test_cbytes_new() {
@@ -588,6 +691,29 @@ mod tests {
         assert_eq!(cbytes.into_bytes().unwrap(), vec![1, 2, 3]);
     }
 
+    #[test]
+    fn test_cbytes_from_shared_cursor() {
+        let buffer = Bytes::from_static(&[0, 0, 0, 3, 1, 2, 3, 0xff, 0xff, 0xff, 0xff, 0, 0]);
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&buffer);
+
+        let shared = CBytes::from_shared_cursor(&mut cursor, &buffer)
+            .unwrap()
+            .into_shared()
+            .unwrap();
+        assert_eq!(shared, &[1, 2, 3][..]);
+        assert_eq!(shared.as_ptr(), buffer[4..].as_ptr());
+
+        let null = CBytes::from_shared_cursor(&mut cursor, &buffer).unwrap();
+        assert!(null.as_slice().is_none());
+
+        assert!(CBytes::from_shared_cursor(&mut cursor, &buffer).is_err());
+
+        let other = [0, 0, 0, 1, 5];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&other);
+        let copied = CBytes::from_shared_cursor(&mut cursor, &buffer).unwrap();
+        assert_eq!(copied.as_slice(), Some(&[5][..]));
+    }
+
     #[test]
     fn test_cbytes_serialize() {
         let bytes_vec = vec![1, 2, 3];
@@ -2,7 +2,7 @@ use num::BigInt;
 use std::collections::HashMap;
 use std::net::IpAddr;
 
-use super::prelude::{Blob, Decimal};
+use super::prelude::{Blob, Decimal, Duration};
 use crate::error::Result as CDRSResult;
 use crate::frame::frame_result::{ColType, ColTypeOption};
 use crate::types::CBytes;
@@ -28,11 +28,19 @@ pub enum CassandraType {
     Time(i64),
     Smallint(i16),
     Tinyint(i8),
+    Duration(Duration),
     List(Vec<CassandraType>),
     Map(Vec<(CassandraType, CassandraType)>),
     Set(Vec<CassandraType>),
     Udt(HashMap<String, CassandraType>),
     Tuple(Vec<CassandraType>),
+    Vector(Vec<CassandraType>),
+    /// Value of a custom type without a registered codec - see
+    /// [`register_custom_type_codec`](crate::types::custom_type::register_custom_type_codec).
+    Custom {
+        class: String,
+        bytes: Vec<u8>,
+    },
     Null,
 }
 
@@ -45,7 +53,7 @@ pub fn wrapper_fn(
         ColType::Ascii => &wrappers::ascii,
         ColType::Int => &wrappers::int,
         ColType::List => &wrappers::list,
-        ColType::Custom => todo!(),
+        ColType::Custom => &wrappers::custom,
         ColType::Bigint => &wrappers::bigint,
         ColType::Boolean => &wrappers::bool,
         ColType::Counter => &wrappers::counter,
@@ -62,6 +70,7 @@ pub fn wrapper_fn(
         ColType::Time => &wrappers::time,
         ColType::Smallint => &wrappers::smallint,
         ColType::Tinyint => &wrappers::tinyint,
+        ColType::Duration => &wrappers::duration,
         ColType::Map => &wrappers::map,
         ColType::Set => &wrappers::set,
         ColType::Udt => &wrappers::udt,
@@ -71,9 +80,10 @@ pub fn wrapper_fn(
 }
 
 pub mod wrappers {
-    use super::CassandraType;
+    use super::{wrapper_fn, CassandraType};
     use crate::error::Result as CDRSResult;
     use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
+    use crate::types::custom_type::custom_type_codec;
     use crate::types::data_serialization_types::*;
     use crate::types::list::List;
     use crate::types::AsCassandraType;
@@ -134,6 +144,39 @@ pub mod wrappers {
         Ok(CassandraType::Null)
     }
 
+    pub fn custom(bytes: &CBytes, col_type: &ColTypeOption) -> CDRSResult<CassandraType> {
+        match &col_type.value {
+            Some(ColTypeOptionValue::CVector(element_type, dimensions)) => {
+                if let Some(actual_bytes) = bytes.as_slice() {
+                    let wrapper = wrapper_fn(&element_type.id);
+                    let decoded_vector = decode_vector(actual_bytes, element_type, *dimensions)?;
+
+                    return decoded_vector
+                        .iter()
+                        .map(|element| wrapper(element, element_type))
+                        .collect::<CDRSResult<_>>()
+                        .map(CassandraType::Vector);
+                }
+
+                Ok(CassandraType::Null)
+            }
+            Some(ColTypeOptionValue::CString(class)) => match bytes.as_slice() {
+                Some(actual_bytes) => match custom_type_codec(class) {
+                    Some(codec) => codec.decode(actual_bytes, col_type),
+                    None => Ok(CassandraType::Custom {
+                        class: class.clone(),
+                        bytes: actual_bytes.to_vec(),
+                    }),
+                },
+                None => Ok(CassandraType::Null),
+            },
+            _ => Err(crate::error::Error::General(format!(
+                "Invalid custom type: {:?}",
+                col_type.value
+            ))),
+        }
+    }
+
     pub fn null(_: &CBytes, _col_type: &ColTypeOption) -> CDRSResult<CassandraType> {
         Ok(CassandraType::Null)
     }
@@ -308,6 +351,15 @@ pub mod wrappers {
         })
     }
 
+    pub fn duration(bytes: &CBytes, col_type: &ColTypeOption) -> CDRSResult<CassandraType> {
+        let t = as_rust_type!(col_type, bytes, Duration)?;
+
+        Ok(match t {
+            Some(t) => CassandraType::Duration(t),
+            None => CassandraType::Null,
+        })
+    }
+
     pub fn bool(bytes: &CBytes, col_type: &ColTypeOption) -> CDRSResult<CassandraType> {
         let t = as_rust_type!(col_type, bytes, bool)?;
 
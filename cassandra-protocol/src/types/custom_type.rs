@@ -0,0 +1,115 @@
+//! Registry of codecs for custom types, e.g. DSE `DateRangeType`. Values of custom types without a
+//! registered codec are represented as [`CassandraType::Custom`] with raw bytes.
+use arc_swap::ArcSwap;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::Result;
+use crate::frame::frame_result::ColTypeOption;
+use crate::types::cassandra_type::CassandraType;
+
+/// Decodes values of a custom type.
+pub trait CustomTypeCodec: Send + Sync {
+    /// Decodes non-null value of given custom column type.
+    fn decode(&self, bytes: &[u8], col_type: &ColTypeOption) -> Result<CassandraType>;
+}
+
+impl<F> CustomTypeCodec for F
+where
+    F: Fn(&[u8], &ColTypeOption) -> Result<CassandraType> + Send + Sync,
+{
+    #[inline]
+    fn decode(&self, bytes: &[u8], col_type: &ColTypeOption) -> Result<CassandraType> {
+        self(bytes, col_type)
+    }
+}
+
+type CodecMap = HashMap<String, Arc<dyn CustomTypeCodec>>;
+
+lazy_static! {
+    static ref CUSTOM_TYPE_CODECS: ArcSwap<CodecMap> = ArcSwap::from_pointee(CodecMap::new());
+}
+
+/// Registers a codec for given custom type class name, as sent by the server, e.g.
+/// `org.apache.cassandra.db.marshal.DateRangeType`. Replaces any previously registered codec for
+/// the same class.
+pub fn register_custom_type_codec(class_name: impl Into<String>, codec: Arc<dyn CustomTypeCodec>) {
+    let class_name = class_name.into();
+    CUSTOM_TYPE_CODECS.rcu(|codecs| {
+        let mut codecs = CodecMap::clone(codecs);
+        codecs.insert(class_name.clone(), codec.clone());
+        codecs
+    });
+}
+
+/// Removes a codec registered for given custom type class name.
+pub fn unregister_custom_type_codec(class_name: &str) {
+    CUSTOM_TYPE_CODECS.rcu(|codecs| {
+        let mut codecs = CodecMap::clone(codecs);
+        codecs.remove(class_name);
+        codecs
+    });
+}
+
+/// Returns a codec registered for given custom type class name.
+pub fn custom_type_codec(class_name: &str) -> Option<Arc<dyn CustomTypeCodec>> {
+    CUSTOM_TYPE_CODECS.load().get(class_name).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::{ColType, ColTypeOptionValue};
+    use crate::types::cassandra_type::wrapper_fn;
+    use crate::types::CBytes;
+
+    fn custom_col_type(class: &str) -> ColTypeOption {
+        ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CString(class.into())),
+        }
+    }
+
+    #[test]
+    fn should_fall_back_to_raw_bytes() {
+        let col_type = custom_col_type("com.example.UnknownType");
+        let wrapper = wrapper_fn(&col_type.id);
+
+        assert_eq!(
+            wrapper(&CBytes::new(vec![1, 2]), &col_type).unwrap(),
+            CassandraType::Custom {
+                class: "com.example.UnknownType".into(),
+                bytes: vec![1, 2]
+            }
+        );
+        assert_eq!(
+            wrapper(&CBytes::new_empty(), &col_type).unwrap(),
+            CassandraType::Null
+        );
+    }
+
+    #[test]
+    fn should_use_registered_codec() {
+        let class = "com.example.RegisteredType";
+        let col_type = custom_col_type(class);
+        let wrapper = wrapper_fn(&col_type.id);
+
+        register_custom_type_codec(
+            class,
+            Arc::new(|bytes: &[u8], _: &ColTypeOption| Ok(CassandraType::Int(bytes.len() as i32))),
+        );
+
+        assert_eq!(
+            wrapper(&CBytes::new(vec![1, 2, 3]), &col_type).unwrap(),
+            CassandraType::Int(3)
+        );
+
+        unregister_custom_type_codec(class);
+
+        assert!(matches!(
+            wrapper(&CBytes::new(vec![1, 2, 3]), &col_type).unwrap(),
+            CassandraType::Custom { .. }
+        ));
+    }
+}
@@ -1,12 +1,16 @@
 use arrayref::array_ref;
 use num::BigInt;
+use std::convert::TryFrom;
 use std::io;
 use std::net;
 use std::string::FromUtf8Error;
 
 use super::blob::Blob;
 use super::decimal::Decimal;
+use super::duration::Duration;
+use super::vint::{decode_unsigned_vint, decode_vint};
 use crate::error;
+use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use crate::frame::FromCursor;
 use crate::types::{
     try_f32_from_bytes, try_f64_from_bytes, try_i16_from_bytes, try_i32_from_bytes,
@@ -86,6 +90,21 @@ pub fn decode_decimal(bytes: &[u8]) -> Result<Decimal, io::Error> {
     Ok(Decimal::new(unscaled, scale))
 }
 
+// Decodes Cassandra `duration` data (bytes)
+pub fn decode_duration(bytes: &[u8]) -> Result<Duration, io::Error> {
+    let mut cursor = io::Cursor::new(bytes);
+    let months = decode_vint(&mut cursor)?;
+    let days = decode_vint(&mut cursor)?;
+    let nanoseconds = decode_vint(&mut cursor)?;
+
+    let months =
+        i32::try_from(months).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let days =
+        i32::try_from(days).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    Ok(Duration::new(months, days, nanoseconds))
+}
+
 // Decodes Cassandra `double` data (bytes)
 #[inline]
 pub fn decode_double(bytes: &[u8]) -> Result<f64, io::Error> {
@@ -206,6 +225,81 @@ pub fn decode_varint(bytes: &[u8]) -> Result<BigInt, io::Error> {
     Ok(BigInt::from_signed_bytes_be(bytes))
 }
 
+// Returns the size of vector elements of given type, if the size is fixed
+pub fn vector_element_size(element_type: &ColTypeOption) -> Option<usize> {
+    match element_type.id {
+        ColType::Boolean | ColType::Tinyint => Some(1),
+        ColType::Smallint => Some(2),
+        ColType::Int | ColType::Float | ColType::Date => Some(INT_LEN),
+        ColType::Bigint
+        | ColType::Counter
+        | ColType::Double
+        | ColType::Timestamp
+        | ColType::Time => Some(8),
+        ColType::Uuid | ColType::Timeuuid => Some(16),
+        ColType::Custom => match &element_type.value {
+            Some(ColTypeOptionValue::CVector(element_type, dimensions)) => {
+                vector_element_size(element_type).map(|size| size * dimensions)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Decodes Cassandra `vector` data (bytes) into separate elements. Fixed size elements are stored
+// back-to-back, while variable size ones are prefixed with vint length.
+pub fn decode_vector(
+    bytes: &[u8],
+    element_type: &ColTypeOption,
+    dimensions: usize,
+) -> Result<Vec<CBytes>, io::Error> {
+    if let Some(size) = vector_element_size(element_type) {
+        if bytes.len() != size * dimensions {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Expected {} bytes of vector data, got {}",
+                    size * dimensions,
+                    bytes.len()
+                ),
+            ));
+        }
+
+        return Ok(bytes
+            .chunks(size)
+            .map(|element| CBytes::new(element.to_vec()))
+            .collect());
+    }
+
+    let mut cursor = io::Cursor::new(bytes);
+    let mut vector = Vec::with_capacity(dimensions);
+    for _ in 0..dimensions {
+        let len = decode_unsigned_vint(&mut cursor)? as usize;
+        let mut element = vec![0; len];
+        io::Read::read_exact(&mut cursor, &mut element)?;
+        vector.push(CBytes::new(element));
+    }
+
+    Ok(vector)
+}
+
+// Decodes Cassandra `vector<float, n>` data (bytes)
+pub fn decode_float_vector(bytes: &[u8], dimensions: usize) -> Result<Vec<f32>, io::Error> {
+    if bytes.len() != INT_LEN * dimensions {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Expected {} bytes of float vector data, got {}",
+                INT_LEN * dimensions,
+                bytes.len()
+            ),
+        ));
+    }
+
+    bytes.chunks(INT_LEN).map(try_f32_from_bytes).collect()
+}
+
 // Decodes Cassandra `Udt` data (bytes)
 pub fn decode_udt(bytes: &[u8], l: usize) -> Result<Vec<CBytes>, io::Error> {
     let mut cursor = io::Cursor::new(bytes);
@@ -393,6 +487,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn decode_duration_test() {
+        assert_eq!(decode_duration(&[2, 4, 6]).unwrap(), Duration::new(1, 2, 3));
+        assert_eq!(
+            decode_duration(&[1, 3, 0x87, 0xcf]).unwrap(),
+            Duration::new(-1, -2, -1000)
+        );
+        assert!(decode_duration(&[2, 4]).is_err());
+    }
+
+    #[test]
+    fn decode_vector_test() {
+        let float_type = ColTypeOption {
+            id: ColType::Float,
+            value: None,
+        };
+        let bytes = [0x3f, 0x80, 0, 0, 0xc0, 0x20, 0, 0];
+
+        assert_eq!(
+            decode_vector(&bytes, &float_type, 2).unwrap(),
+            vec![
+                CBytes::new(vec![0x3f, 0x80, 0, 0]),
+                CBytes::new(vec![0xc0, 0x20, 0, 0])
+            ]
+        );
+        assert!(decode_vector(&bytes, &float_type, 3).is_err());
+        assert_eq!(decode_float_vector(&bytes, 2).unwrap(), vec![1.0, -2.5]);
+
+        let text_type = ColTypeOption {
+            id: ColType::Varchar,
+            value: None,
+        };
+        assert_eq!(
+            decode_vector(&[1, 97, 2, 98, 99], &text_type, 2).unwrap(),
+            vec![CBytes::new(b"a".to_vec()), CBytes::new(b"bc".to_vec())]
+        );
+    }
+
     #[test]
     fn decode_text_test() {
         assert_eq!(decode_text(b"abcba").unwrap(), "abcba");
@@ -774,6 +906,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn as_rust_float_vector_test() {
+        let type_vector = ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CVector(
+                Box::new(ColTypeOption {
+                    id: ColType::Float,
+                    value: None,
+                }),
+                2,
+            )),
+        };
+        let data = CBytes::new(vec![0x3f, 0x80, 0, 0, 0xc0, 0x20, 0, 0]);
+
+        assert_eq!(
+            as_rust_type!(type_vector, data, Vec<f32>).unwrap().unwrap(),
+            vec![1.0, -2.5]
+        );
+
+        let wrong_type = ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CString("FloatType".into())),
+        };
+        assert!(as_rust_type!(wrong_type, data, Vec<f32>).is_err());
+    }
+
     #[test]
     fn as_rust_inet_test() {
         let type_inet = ColTypeOption {
@@ -0,0 +1,41 @@
+use derive_more::Constructor;
+use std::io::Cursor;
+
+use crate::frame::Serialize;
+use crate::types::vint::encode_vint;
+
+/// Cassandra Duration type. Months, days and nanoseconds are stored separately, since the number
+/// of days in a month varies, and a day can have 23 or 25 hours when daylight saving is involved.
+/// All components need to have the same sign.
+#[derive(Debug, Clone, Copy, PartialEq, Constructor, Ord, PartialOrd, Eq, Hash, Default)]
+pub struct Duration {
+    pub months: i32,
+    pub days: i32,
+    pub nanoseconds: i64,
+}
+
+impl Serialize for Duration {
+    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
+        let mut buffer = Vec::with_capacity(3);
+        encode_vint(self.months as i64, &mut buffer);
+        encode_vint(self.days as i64, &mut buffer);
+        encode_vint(self.nanoseconds, &mut buffer);
+
+        buffer.serialize(cursor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_test() {
+        assert_eq!(Duration::new(0, 0, 0).serialize_to_vec(), vec![0, 0, 0]);
+        assert_eq!(Duration::new(1, 2, 3).serialize_to_vec(), vec![2, 4, 6]);
+        assert_eq!(
+            Duration::new(-1, -2, -1000).serialize_to_vec(),
+            vec![1, 3, 0x87, 0xcf]
+        );
+    }
+}
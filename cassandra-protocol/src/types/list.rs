@@ -8,6 +8,7 @@ use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
 use crate::types::udt::Udt;
@@ -49,6 +50,8 @@ list_as_rust!(Map);
 list_as_rust!(Udt);
 list_as_rust!(Tuple);
 list_as_rust!(Decimal);
+list_as_rust!(Duration);
+list_as_rust!(Vec<f32>);
 list_as_rust!(BigInt);
 
 list_as_cassandra_type!();
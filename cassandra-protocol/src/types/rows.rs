@@ -1,7 +1,10 @@
+use std::collections::HashMap;
+use std::io::Cursor;
 use std::net::IpAddr;
 use std::num::{NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8};
 use std::sync::Arc;
 
+use bytes::Bytes;
 use chrono::prelude::*;
 use time::PrimitiveDateTime;
 use uuid::Uuid;
@@ -13,6 +16,7 @@ use crate::frame::frame_result::{
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
@@ -20,30 +24,132 @@ use crate::types::udt::Udt;
 use crate::types::{ByIndex, ByName, CBytes, IntoRustByIndex, IntoRustByName};
 use num::BigInt;
 
+/// Resolves column names into indexes. Can be created once for a result and reused for all rows,
+/// instead of looking up column specs for every value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ColumnIndex {
+    indexes: HashMap<String, usize>,
+}
+
+impl ColumnIndex {
+    pub fn new(metadata: &RowsMetadata) -> Self {
+        let mut indexes = HashMap::with_capacity(metadata.col_specs.len());
+        for (index, spec) in metadata.col_specs.iter().enumerate() {
+            // first column wins in case of duplicate names
+            indexes.entry(spec.name.clone()).or_insert(index);
+        }
+
+        ColumnIndex { indexes }
+    }
+
+    /// Returns the index of a column with given name.
+    #[inline]
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.indexes.get(name).copied()
+    }
+}
+
+/// Iterator decoding rows one at a time from a rows result. Values of returned rows are slices
+/// of the result buffer.
+#[derive(Clone, Debug)]
+pub struct RowIterator {
+    metadata: Arc<RowsMetadata>,
+    column_index: Arc<ColumnIndex>,
+    content: Bytes,
+    position: u64,
+    remaining: usize,
+}
+
+impl RowIterator {
+    pub fn new(body: BodyResResultRows) -> Self {
+        RowIterator {
+            column_index: Arc::new(ColumnIndex::new(&body.metadata)),
+            metadata: Arc::new(body.metadata),
+            content: body.rows_content,
+            position: 0,
+            remaining: body.rows_count.max(0) as usize,
+        }
+    }
+
+    /// Metadata of returned rows.
+    #[inline]
+    pub fn metadata(&self) -> &Arc<RowsMetadata> {
+        &self.metadata
+    }
+
+    /// Column index shared by returned rows.
+    #[inline]
+    pub fn column_index(&self) -> &Arc<ColumnIndex> {
+        &self.column_index
+    }
+
+    fn decode_row(&mut self) -> Result<Row> {
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&self.content);
+        cursor.set_position(self.position);
+
+        let row_content = (0..self.metadata.columns_count)
+            .map(|_| CBytes::from_shared_cursor(&mut cursor, &self.content))
+            .collect::<Result<_>>()?;
+
+        self.position = cursor.position();
+
+        Ok(Row {
+            metadata: self.metadata.clone(),
+            column_index: self.column_index.clone(),
+            row_content,
+        })
+    }
+}
+
+impl Iterator for RowIterator {
+    type Item = Row;
+
+    /// Decodes the next row. Rows content is validated when parsing the body, so iteration only
+    /// stops early if the body has been constructed with malformed content.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        match self.decode_row() {
+            Ok(row) => {
+                self.remaining -= 1;
+                Some(row)
+            }
+            Err(_) => {
+                self.remaining = 0;
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Row {
     metadata: Arc<RowsMetadata>,
+    column_index: Arc<ColumnIndex>,
     row_content: Vec<CBytes>,
 }
 
 impl Row {
     pub fn from_frame_body(body: BodyResResultRows) -> Vec<Row> {
-        let metadata = Arc::new(body.metadata);
-        body.rows_content
-            .into_iter()
-            .map(|row| Row {
-                metadata: metadata.clone(),
-                row_content: row,
-            })
-            .collect()
+        body.into_iter().collect()
+    }
+
+    /// Column index shared by all rows from the same result.
+    #[inline]
+    pub fn column_index(&self) -> &Arc<ColumnIndex> {
+        &self.column_index
     }
 
     /// Checks if a column is present in the row.
     pub fn contains_column(&self, name: &str) -> bool {
-        self.metadata
-            .col_specs
-            .iter()
-            .any(|spec| spec.name.as_str() == name)
+        self.column_index.index_of(name).is_some()
     }
 
     /// Checks for NULL for a given column. Returns false if given column does not exist.
@@ -56,30 +162,23 @@ impl Row {
 
     /// Checks for NULL for a given column. Returns false if given column does not exist.
     pub fn is_empty_by_name(&self, name: &str) -> bool {
-        self.metadata
-            .col_specs
-            .iter()
-            .position(|spec| spec.name.as_str() == name)
+        self.column_index
+            .index_of(name)
             .map(|index| self.is_empty(index))
             .unwrap_or(false)
     }
 
     fn col_spec_by_name(&self, name: &str) -> Option<(&ColSpec, &CBytes)> {
-        self.metadata
-            .col_specs
-            .iter()
-            .position(|spec| spec.name.as_str() == name)
-            .map(|i| {
-                let col_spec = &self.metadata.col_specs[i];
-                let data = &self.row_content[i];
-                (col_spec, data)
-            })
+        self.column_index
+            .index_of(name)
+            .and_then(|i| self.col_spec_by_index(i))
     }
 
     fn col_spec_by_index(&self, index: usize) -> Option<(&ColSpec, &CBytes)> {
-        let specs = self.metadata.col_specs.iter();
-        let values = self.row_content.iter();
-        specs.zip(values).nth(index)
+        Some((
+            self.metadata.col_specs.get(index)?,
+            self.row_content.get(index)?,
+        ))
     }
 }
 
@@ -102,6 +201,8 @@ into_rust_by_name!(Row, Udt);
 into_rust_by_name!(Row, Tuple);
 into_rust_by_name!(Row, PrimitiveDateTime);
 into_rust_by_name!(Row, Decimal);
+into_rust_by_name!(Row, Duration);
+into_rust_by_name!(Row, Vec<f32>);
 into_rust_by_name!(Row, NonZeroI8);
 into_rust_by_name!(Row, NonZeroI16);
 into_rust_by_name!(Row, NonZeroI32);
@@ -129,6 +230,8 @@ into_rust_by_index!(Row, Udt);
 into_rust_by_index!(Row, Tuple);
 into_rust_by_index!(Row, PrimitiveDateTime);
 into_rust_by_index!(Row, Decimal);
+into_rust_by_index!(Row, Duration);
+into_rust_by_index!(Row, Vec<f32>);
 into_rust_by_index!(Row, NonZeroI8);
 into_rust_by_index!(Row, NonZeroI16);
 into_rust_by_index!(Row, NonZeroI32);
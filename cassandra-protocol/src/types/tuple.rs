@@ -9,6 +9,7 @@ use crate::frame::frame_result::{CTuple, ColType, ColTypeOption, ColTypeOptionVa
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::udt::Udt;
@@ -78,6 +79,8 @@ into_rust_by_index!(Tuple, Udt);
 into_rust_by_index!(Tuple, Tuple);
 into_rust_by_index!(Tuple, PrimitiveDateTime);
 into_rust_by_index!(Tuple, Decimal);
+into_rust_by_index!(Tuple, Duration);
+into_rust_by_index!(Tuple, Vec<f32>);
 into_rust_by_index!(Tuple, NaiveDateTime);
 into_rust_by_index!(Tuple, DateTime<Utc>);
 into_rust_by_index!(Tuple, BigInt);
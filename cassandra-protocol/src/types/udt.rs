@@ -11,6 +11,7 @@ use crate::frame::frame_result::{CUdt, ColType, ColTypeOption, ColTypeOptionValu
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
@@ -53,6 +54,8 @@ into_rust_by_name!(Udt, Udt);
 into_rust_by_name!(Udt, Tuple);
 into_rust_by_name!(Udt, PrimitiveDateTime);
 into_rust_by_name!(Udt, Decimal);
+into_rust_by_name!(Udt, Duration);
+into_rust_by_name!(Udt, Vec<f32>);
 into_rust_by_name!(Udt, NonZeroI8);
 into_rust_by_name!(Udt, NonZeroI16);
 into_rust_by_name!(Udt, NonZeroI32);
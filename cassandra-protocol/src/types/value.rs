@@ -13,6 +13,8 @@ use uuid::Uuid;
 
 use super::blob::Blob;
 use super::decimal::Decimal;
+use super::duration::Duration;
+use super::vector::FloatVector;
 use super::*;
 use crate::Error;
 
@@ -259,6 +261,20 @@ impl From<Decimal> for Bytes {
     }
 }
 
+impl From<Duration> for Bytes {
+    #[inline]
+    fn from(value: Duration) -> Self {
+        Bytes(value.serialize_to_vec())
+    }
+}
+
+impl From<FloatVector> for Bytes {
+    #[inline]
+    fn from(value: FloatVector) -> Self {
+        Bytes(value.serialize_to_vec())
+    }
+}
+
 impl From<NaiveDateTime> for Bytes {
     #[inline]
     fn from(value: NaiveDateTime) -> Self {
@@ -0,0 +1,47 @@
+use derive_more::Constructor;
+use std::io::Cursor;
+
+use crate::frame::Serialize;
+
+/// Vector of floats, as stored in `vector<float, n>` columns. Unlike `Vec<f32>`, which gets
+/// serialized as a list, elements are serialized back-to-back, as expected by vector columns and
+/// `ANN OF` queries.
+#[derive(Debug, Clone, PartialEq, Constructor, Default)]
+pub struct FloatVector {
+    pub data: Vec<f32>,
+}
+
+impl Serialize for FloatVector {
+    fn serialize(&self, cursor: &mut Cursor<&mut Vec<u8>>) {
+        for value in &self.data {
+            value.to_bits().serialize(cursor);
+        }
+    }
+}
+
+impl From<Vec<f32>> for FloatVector {
+    #[inline]
+    fn from(data: Vec<f32>) -> Self {
+        FloatVector { data }
+    }
+}
+
+impl From<FloatVector> for Vec<f32> {
+    #[inline]
+    fn from(vector: FloatVector) -> Self {
+        vector.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_test() {
+        assert_eq!(
+            FloatVector::new(vec![1.0, -2.5]).serialize_to_vec(),
+            vec![0x3f, 0x80, 0, 0, 0xc0, 0x20, 0, 0]
+        );
+    }
+}
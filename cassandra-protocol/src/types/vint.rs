@@ -0,0 +1,104 @@
+//! Variable length integer encoding, compatible with the one used by Cassandra: the number of
+//! leading set bits in the first byte denotes the number of additional bytes, while signed values
+//! are zig-zag encoded.
+use std::io::{self, Cursor, Read};
+
+/// Encodes given unsigned value as vint and appends it to the buffer.
+pub fn encode_unsigned_vint(value: u64, buffer: &mut Vec<u8>) {
+    let size = unsigned_vint_size(value);
+    if size == 1 {
+        buffer.push(value as u8);
+        return;
+    }
+
+    let bytes = value.to_be_bytes();
+    if size > bytes.len() {
+        // the first byte only marks extra bytes
+        buffer.push(0xff);
+        buffer.extend_from_slice(&bytes);
+        return;
+    }
+
+    let start = buffer.len();
+    buffer.extend_from_slice(&bytes[bytes.len() - size..]);
+
+    // extra bytes are marked by leading ones in the first byte
+    let extra_bytes = size - 1;
+    buffer[start] |= !(0xffu8 >> extra_bytes);
+}
+
+/// Encodes given signed value as zig-zag vint and appends it to the buffer.
+#[inline]
+pub fn encode_vint(value: i64, buffer: &mut Vec<u8>) {
+    encode_unsigned_vint(((value << 1) ^ (value >> 63)) as u64, buffer);
+}
+
+/// Decodes an unsigned vint from the cursor.
+pub fn decode_unsigned_vint(cursor: &mut Cursor<&[u8]>) -> io::Result<u64> {
+    let mut first_byte = [0];
+    cursor.read_exact(&mut first_byte)?;
+
+    let first_byte = first_byte[0];
+    let extra_bytes = first_byte.leading_ones() as usize;
+    if extra_bytes == 0 {
+        return Ok(first_byte as u64);
+    }
+
+    let mut bytes = [0; 8];
+    cursor.read_exact(&mut bytes[..extra_bytes])?;
+
+    let first_value = first_byte as u64 & (0xff >> extra_bytes);
+    Ok(bytes[..extra_bytes]
+        .iter()
+        .fold(first_value, |value, byte| (value << 8) | *byte as u64))
+}
+
+/// Decodes a signed zig-zag vint from the cursor.
+#[inline]
+pub fn decode_vint(cursor: &mut Cursor<&[u8]>) -> io::Result<i64> {
+    decode_unsigned_vint(cursor).map(|value| (value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+#[inline]
+fn unsigned_vint_size(value: u64) -> usize {
+    let magnitude = (value | 1).leading_zeros() as usize;
+    (639 - magnitude * 9) >> 6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(value: i64) -> Vec<u8> {
+        let mut buffer = vec![];
+        encode_vint(value, &mut buffer);
+
+        let mut cursor = Cursor::new(buffer.as_slice());
+        assert_eq!(decode_vint(&mut cursor).unwrap(), value);
+        assert_eq!(cursor.position() as usize, buffer.len());
+
+        buffer
+    }
+
+    #[test]
+    fn should_encode_vint() {
+        assert_eq!(roundtrip(0), vec![0]);
+        assert_eq!(roundtrip(1), vec![2]);
+        assert_eq!(roundtrip(-1), vec![1]);
+        assert_eq!(roundtrip(63), vec![126]);
+        assert_eq!(roundtrip(64), vec![0x80, 0x80]);
+        assert_eq!(roundtrip(-65), vec![0x80, 0x81]);
+        assert_eq!(roundtrip(i64::MAX).len(), 9);
+        assert_eq!(roundtrip(i64::MIN).len(), 9);
+
+        for value in [1000, -1000, 1 << 20, -(1 << 40), 86_400_000_000_000] {
+            roundtrip(value);
+        }
+    }
+
+    #[test]
+    fn should_fail_on_missing_bytes() {
+        let mut cursor = Cursor::new(&[0x80u8][..]);
+        assert!(decode_vint(&mut cursor).is_err());
+    }
+}
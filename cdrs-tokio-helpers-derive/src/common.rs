@@ -89,7 +89,8 @@ fn into_rust_with_args(field_type: &Type, arguments: TokenStream) -> TokenStream
     let field_type_ident = get_cdrs_type(field_type);
     match get_ident_string(&field_type_ident).as_str() {
         "Blob" | "String" | "bool" | "i64" | "i32" | "i16" | "i8" | "f64" | "f32" | "Decimal"
-        | "IpAddr" | "Uuid" | "Timespec" | "PrimitiveDateTime" | "NaiveDateTime" | "DateTime" => {
+        | "Duration" | "IpAddr" | "Uuid" | "Timespec" | "PrimitiveDateTime" | "NaiveDateTime"
+        | "DateTime" => {
             quote! {
               #field_type_ident::from_cdrs_r(#arguments)?
             }
@@ -168,6 +169,7 @@ fn get_cdrs_type(ty: &Type) -> Type {
         "f64" => parse_str("f64").unwrap(),
         "f32" => parse_str("f32").unwrap(),
         "Decimal" => parse_str("Decimal").unwrap(),
+        "Duration" => parse_str("Duration").unwrap(),
         "IpAddr" => parse_str("IpAddr").unwrap(),
         "Uuid" => parse_str("Uuid").unwrap(),
         "Timespec" => parse_str("Timespec").unwrap(),
@@ -203,7 +205,7 @@ fn as_rust(ty: &Type, val: TokenStream) -> TokenStream {
     let cdrs_type = get_cdrs_type(ty);
     match get_ident_string(&cdrs_type).as_str() {
         "Blob" | "String" | "bool" | "i64" | "i32" | "i16" | "i8" | "f64" | "f32" | "IpAddr"
-        | "Uuid" | "Timespec" | "Decimal" | "PrimitiveDateTime" => val,
+        | "Uuid" | "Timespec" | "Decimal" | "Duration" | "PrimitiveDateTime" => val,
         "List" => {
             let vec_type = get_ident_params_string(ty);
             let inter_rust_type = get_cdrs_type(&vec_type);
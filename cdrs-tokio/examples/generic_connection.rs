@@ -22,13 +22,14 @@ use cdrs_tokio::{
 use cdrs_tokio::cluster::connection_pool::ConnectionPoolConfig;
 use cdrs_tokio::cluster::session::{
     NodeDistanceEvaluatorWrapper, ReconnectionPolicyWrapper, RetryPolicyWrapper,
-    DEFAULT_TRANSPORT_BUFFER_SIZE,
+    DEFAULT_MAX_IN_FLIGHT_REQUESTS, DEFAULT_TRANSPORT_BUFFER_SIZE,
 };
-use cdrs_tokio::cluster::{ConnectionManager, KeyspaceHolder};
+use cdrs_tokio::cluster::{ConnectionManager, KeyspaceHolder, VersionHolder};
 use cdrs_tokio::compression::Compression;
 use cdrs_tokio::frame::{Frame, Version};
 use cdrs_tokio::future::BoxFuture;
 use cdrs_tokio::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
+use cdrs_tokio::metrics::NoopMetricsSink;
 use cdrs_tokio::retry::{ConstantReconnectionPolicy, ReconnectionPolicy};
 use cdrs_tokio::IntoCdrsValue;
 use futures::FutureExt;
@@ -107,6 +108,7 @@ impl VirtualConnectionManager {
     async fn new(
         config: &VirtualClusterConfig,
         keyspace_holder: Arc<KeyspaceHolder>,
+        version_holder: Arc<VersionHolder>,
     ) -> Result<Self> {
         Ok(VirtualConnectionManager {
             inner: TcpConnectionManager::new(
@@ -116,7 +118,10 @@ impl VirtualConnectionManager {
                 Compression::None,
                 DEFAULT_TRANSPORT_BUFFER_SIZE,
                 true,
-                config.version,
+                DEFAULT_MAX_IN_FLIGHT_REQUESTS,
+                None,
+                Arc::new(NoopMetricsSink),
+                version_holder,
             ),
             mask: config.mask,
             actual: config.actual,
@@ -128,10 +133,11 @@ impl GenericClusterConfig<TransportTcp, VirtualConnectionManager> for VirtualClu
     fn create_manager(
         &self,
         keyspace_holder: Arc<KeyspaceHolder>,
+        version_holder: Arc<VersionHolder>,
     ) -> BoxFuture<Result<VirtualConnectionManager>> {
         // create a connection manager that points at the rewritten address so that's where it connects, but
         // then return a manager with the 'virtual' address for internal purposes.
-        VirtualConnectionManager::new(self, keyspace_holder).boxed()
+        VirtualConnectionManager::new(self, keyspace_holder, version_holder).boxed()
     }
 
     fn event_channel_capacity(&self) -> usize {
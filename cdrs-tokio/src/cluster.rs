@@ -2,11 +2,12 @@ pub(crate) use self::cluster_metadata_manager::ClusterMetadataManager;
 #[cfg(feature = "rust-tls")]
 pub use self::config_rustls::{NodeRustlsConfig, NodeRustlsConfigBuilder};
 pub use self::config_tcp::{NodeTcpConfig, NodeTcpConfigBuilder};
-pub use self::connection_manager::{startup, ConnectionManager};
+pub use self::connection_manager::{negotiate_version, startup, ConnectionManager};
 pub use self::keyspace_holder::KeyspaceHolder;
 pub use self::node_address::NodeAddress;
 pub use self::node_info::NodeInfo;
-pub use self::pager::{ExecPager, PagerState, QueryPager, SessionPager};
+pub use self::pager::{ExecPager, PagerState, QueryPager, RowStream, SessionPager, TypedRowStream};
+pub use self::prepared_statement_cache::DEFAULT_PREPARED_STATEMENT_CACHE_SIZE;
 #[cfg(feature = "rust-tls")]
 pub use self::rustls_connection_manager::RustlsConnectionManager;
 pub use self::session::connect_generic;
@@ -14,12 +15,17 @@ pub(crate) use self::session_context::SessionContext;
 pub use self::tcp_connection_manager::TcpConnectionManager;
 pub use self::token_map::TokenMap;
 pub use self::topology::cluster_metadata::ClusterMetadata;
+pub use self::version_holder::VersionHolder;
 use crate::cluster::connection_pool::ConnectionPoolConfig;
 use crate::future::BoxFuture;
+use crate::metrics::{MetricsSink, NoopMetricsSink};
+use crate::request_interceptor::RequestInterceptor;
+use crate::timestamp_generator::{AtomicMonotonicTimestampGenerator, TimestampGenerator};
 use crate::transport::CdrsTransport;
 use cassandra_protocol::error;
 use cassandra_protocol::frame::Version;
 use std::sync::Arc;
+use std::time::Duration;
 
 mod cluster_metadata_manager;
 #[cfg(feature = "rust-tls")]
@@ -33,8 +39,11 @@ mod metadata_builder;
 mod node_address;
 mod node_info;
 mod pager;
+mod prepared_statement_cache;
+mod request_span;
 #[cfg(feature = "rust-tls")]
 mod rustls_connection_manager;
+pub(crate) mod schema_builder;
 pub mod send_frame;
 pub mod session;
 mod session_context;
@@ -42,19 +51,64 @@ mod tcp_connection_manager;
 pub(crate) mod token_factory;
 mod token_map;
 pub mod topology;
+mod version_holder;
 
 /// Generic connection configuration trait that can be used to create user-supplied
 /// connection objects that can be used with the `session::connect()` function.
 pub trait GenericClusterConfig<T: CdrsTransport, CM: ConnectionManager<T>>: Send + Sync {
-    fn create_manager(&self, keyspace_holder: Arc<KeyspaceHolder>) -> BoxFuture<error::Result<CM>>;
+    /// Creates a connection manager. Managers should negotiate protocol version using given
+    /// holder - see [`negotiate_version`].
+    fn create_manager(
+        &self,
+        keyspace_holder: Arc<KeyspaceHolder>,
+        version_holder: Arc<VersionHolder>,
+    ) -> BoxFuture<error::Result<CM>>;
 
     /// Returns desired event channel capacity. Take a look at
     /// [`Session`](self::session::Session) builders for more info.
     fn event_channel_capacity(&self) -> usize;
 
-    /// Cassandra protocol version to use.
+    /// Highest Cassandra protocol version to use. Lower versions are negotiated if not supported
+    /// by the cluster.
     fn version(&self) -> Version;
 
     /// Connection pool configuration.
     fn connection_pool_config(&self) -> ConnectionPoolConfig;
+
+    /// Maximum number of statements cached by [`Session::query_prepared`](self::session::Session::query_prepared).
+    fn prepared_statement_cache_size(&self) -> usize {
+        DEFAULT_PREPARED_STATEMENT_CACHE_SIZE
+    }
+
+    /// Should statements be prepared on all up nodes at once.
+    fn prepare_on_all_nodes(&self) -> bool {
+        false
+    }
+
+    /// Should full schema metadata be fetched, in addition to keyspaces.
+    fn schema_metadata(&self) -> bool {
+        true
+    }
+
+    /// Default time to wait for a response from a single node.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Timestamp generator for requests without an explicit timestamp.
+    fn timestamp_generator(&self) -> Box<dyn TimestampGenerator + Send + Sync> {
+        Box::new(AtomicMonotonicTimestampGenerator::default())
+    }
+
+    /// Sink for driver metrics. Note: the connection manager created by
+    /// [`create_manager`](GenericClusterConfig::create_manager) is responsible for passing it to
+    /// its transports.
+    fn metrics_sink(&self) -> Arc<dyn MetricsSink + Send + Sync> {
+        Arc::new(NoopMetricsSink)
+    }
+
+    /// Request interceptors, in order of registration.
+    fn request_interceptors(&self) -> Vec<Arc<dyn RequestInterceptor + Send + Sync>> {
+        vec![]
+    }
 }
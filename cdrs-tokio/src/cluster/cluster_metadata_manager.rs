@@ -2,8 +2,8 @@ use arc_swap::ArcSwap;
 use cassandra_protocol::error::{Error, Result};
 use cassandra_protocol::events::{SchemaChange, ServerEvent};
 use cassandra_protocol::frame::events::{
-    SchemaChangeOptions, SchemaChangeType, StatusChange, StatusChangeType, TopologyChange,
-    TopologyChangeType,
+    SchemaChangeOptions, SchemaChangeTarget, SchemaChangeType, StatusChange, StatusChangeType,
+    TopologyChange, TopologyChangeType,
 };
 use cassandra_protocol::frame::frame_error::{AdditionalErrorInfo, ErrorBody};
 use cassandra_protocol::frame::{Frame, Version};
@@ -22,18 +22,24 @@ use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
+use tokio::time::timeout;
 use tracing::*;
 
 use crate::cluster::connection_pool::ConnectionPoolFactory;
 use crate::cluster::metadata_builder::{add_new_node, build_initial_metadata, refresh_metadata};
+use crate::cluster::prepared_statement_cache::{prepare_on_node, PreparedStatementCache};
+use crate::cluster::schema_builder::{add_schema, SchemaRows};
 use crate::cluster::topology::{KeyspaceMetadata, Node, NodeState, ReplicationStrategy};
 use crate::cluster::{ClusterMetadata, ConnectionManager};
-use crate::cluster::{NodeInfo, SessionContext};
+use crate::cluster::{NodeInfo, SessionContext, VersionHolder};
 use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
 use crate::transport::CdrsTransport;
 
+const PREPARE_ON_NODE_TIMEOUT: Duration = Duration::from_secs(10);
+
 fn find_in_peers(
     peers: &[Row],
     broadcast_rpc_address: SocketAddr,
@@ -224,6 +230,14 @@ async fn fetch_control_connection_info<T: CdrsTransport>(
         .ok_or_else(|| format!("Node {} failed to return info about itself!", control_addr).into())
 }
 
+// selects schema elements of all keyspaces, a single keyspace or a single table/view
+#[derive(Clone, Copy)]
+enum SchemaFilter<'a> {
+    All,
+    Keyspace(&'a str),
+    Table(&'a str, &'a str),
+}
+
 fn build_keyspace(row: &Row) -> Result<(String, KeyspaceMetadata)> {
     let keyspace_name = row.get_r_by_name("keyspace_name")?;
 
@@ -305,7 +319,9 @@ pub struct ClusterMetadataManager<T: CdrsTransport + 'static, CM: ConnectionMana
     is_schema_v2: AtomicBool,
     session_context: Arc<SessionContext<T>>,
     node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
-    version: Version,
+    version_holder: Arc<VersionHolder>,
+    prepared_statement_cache: Arc<PreparedStatementCache>,
+    schema_metadata: bool,
 }
 
 impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMetadataManager<T, CM> {
@@ -314,7 +330,9 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         connection_pool_factory: Arc<ConnectionPoolFactory<T, CM>>,
         session_context: Arc<SessionContext<T>>,
         node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
-        version: Version,
+        version_holder: Arc<VersionHolder>,
+        prepared_statement_cache: Arc<PreparedStatementCache>,
+        schema_metadata: bool,
     ) -> Self {
         ClusterMetadataManager {
             metadata: ArcSwap::from_pointee(ClusterMetadata::default()),
@@ -324,7 +342,9 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             is_schema_v2: AtomicBool::new(true),
             session_context,
             node_distance_evaluator,
-            version,
+            version_holder,
+            prepared_statement_cache,
+            schema_metadata,
         }
     }
 
@@ -354,14 +374,35 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
     }
 
     async fn process_schema_event(&self, event: SchemaChange) {
-        if let SchemaChangeOptions::Keyspace(keyspace) = &event.options {
-            match event.change_type {
+        match &event.options {
+            SchemaChangeOptions::Keyspace(keyspace) => match event.change_type {
                 SchemaChangeType::Created | SchemaChangeType::Updated => {
                     self.refresh_keyspace(keyspace).await
                 }
                 SchemaChangeType::Dropped => {
                     self.remove_keyspace(keyspace);
                 }
+            },
+            // only keyspaces are kept without schema metadata
+            _ if !self.schema_metadata => {}
+            SchemaChangeOptions::TableType(keyspace, table)
+                if event.target == SchemaChangeTarget::Table =>
+            {
+                // materialized views are reported as tables
+                match event.change_type {
+                    SchemaChangeType::Created | SchemaChangeType::Updated => {
+                        self.refresh_table(keyspace, table).await
+                    }
+                    SchemaChangeType::Dropped => {
+                        self.remove_table(keyspace, table);
+                    }
+                }
+            }
+            // columns, functions and aggregates can use user types, so a whole keyspace needs to
+            // be refreshed on any change of types, functions or aggregates
+            SchemaChangeOptions::TableType(keyspace, _)
+            | SchemaChangeOptions::FunctionAggregate(keyspace, _, _) => {
+                self.refresh_keyspace(keyspace).await
             }
         }
     }
@@ -406,9 +447,18 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                         debug!(?node, "Setting existing node state to up.");
 
                         // node was down or in an unknown state
-                        let node = node.clone_with_node_state(NodeState::Up);
-                        self.metadata
-                            .store(Arc::new(metadata.clone_with_node(node)));
+                        let node = Arc::new(node.clone_with_node_state(NodeState::Up));
+                        self.prepare_cached_statements(&node).await;
+
+                        // metadata could have changed while preparing
+                        self.metadata.rcu(|metadata| {
+                            match metadata.find_node_by_rpc_address(event.addr.addr) {
+                                Some(current) if current.host_id() == node.host_id() => {
+                                    Arc::new(metadata.clone_with_shared_node(node.clone()))
+                                }
+                                _ => metadata.clone(),
+                            }
+                        });
                     } else {
                         debug!(?node, "Ignoring up node event for already up node.");
                     }
@@ -452,31 +502,190 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         debug!(%keyspace, "Refreshing keyspace.");
 
         let control_transport = self.control_transport()?;
-        send_query_with_values(
-            "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces WHERE keyspace_name = ?",
-            QueryValues::SimpleValues(vec![keyspace.into()]),
-            control_transport.as_ref(),
-            self.version
-        )
-        .await
-        .map(|rows| { rows.and_then(|mut rows| rows.pop()) })
-        .and_then(|row| {
-            match row {
-                Some(row) => {
-                    let (keyspace_name, keyspace) = build_keyspace(&row)?;
-                    let metadata = self.metadata.load().clone();
-                    self.metadata.store(Arc::new(
-                        metadata.clone_with_keyspace(keyspace_name, keyspace),
-                    ));
-                }
-                None => {
-                    warn!(%keyspace, "Keyspace to refresh disappeared.");
-                    self.remove_keyspace(keyspace);
-                }
+
+        // schema elements which cannot be fetched don't prevent refreshing the keyspace itself
+        let (row, schema_rows) = tokio::join!(
+            send_query_with_values(
+                "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces WHERE keyspace_name = ?",
+                QueryValues::SimpleValues(vec![keyspace.into()]),
+                control_transport.as_ref(),
+                self.version_holder.version()
+            ),
+            self.query_schema_rows(control_transport.as_ref(), SchemaFilter::Keyspace(keyspace))
+        );
+
+        match row?.and_then(|mut rows| rows.pop()) {
+            Some(row) => {
+                let (keyspace_name, keyspace) = build_keyspace(&row)?;
+
+                let mut keyspaces = FxHashMap::default();
+                keyspaces.insert(keyspace_name, keyspace);
+                add_schema(&mut keyspaces, &schema_rows);
+
+                let metadata = self.metadata.load().clone();
+                let (keyspace_name, keyspace) = keyspaces.into_iter().next().unwrap();
+                self.metadata.store(Arc::new(
+                    metadata.clone_with_keyspace(keyspace_name, keyspace),
+                ));
+            }
+            None => {
+                warn!(%keyspace, "Keyspace to refresh disappeared.");
+                self.remove_keyspace(keyspace);
             }
+        }
 
-            Ok(())
-        })
+        Ok(())
+    }
+
+    fn remove_table(&self, keyspace_name: &str, table: &str) {
+        let metadata = self.metadata.load().clone();
+        if let Some(keyspace) = metadata.keyspace(keyspace_name) {
+            let mut keyspace = keyspace.clone();
+            keyspace.tables.remove(table);
+            keyspace.views.remove(table);
+
+            self.metadata.store(Arc::new(
+                metadata.clone_with_keyspace(keyspace_name.into(), keyspace),
+            ));
+        }
+    }
+
+    async fn refresh_table(&self, keyspace: &str, table: &str) {
+        if let Err(error) = self.try_refresh_table(keyspace, table).await {
+            error!(?error, %keyspace, %table, "Error refreshing table!");
+        }
+    }
+
+    async fn try_refresh_table(&self, keyspace_name: &str, table: &str) -> Result<()> {
+        if self.metadata.load().keyspace(keyspace_name).is_none() {
+            // we don't know about the keyspace yet, so refresh it as a whole
+            return self.try_refresh_keyspace(keyspace_name).await;
+        }
+
+        debug!(keyspace = %keyspace_name, %table, "Refreshing table.");
+
+        let control_transport = self.control_transport()?;
+        let schema_rows = self
+            .query_schema_rows(
+                control_transport.as_ref(),
+                SchemaFilter::Table(keyspace_name, table),
+            )
+            .await;
+
+        // the keyspace might have changed while querying
+        let metadata = self.metadata.load().clone();
+        if let Some(keyspace) = metadata.keyspace(keyspace_name) {
+            let mut keyspace = keyspace.clone();
+            keyspace.tables.remove(table);
+            keyspace.views.remove(table);
+
+            let mut keyspaces = FxHashMap::default();
+            keyspaces.insert(keyspace_name.to_string(), keyspace);
+            add_schema(&mut keyspaces, &schema_rows);
+
+            let (keyspace_name, keyspace) = keyspaces.into_iter().next().unwrap();
+            self.metadata.store(Arc::new(
+                metadata.clone_with_keyspace(keyspace_name, keyspace),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // schema elements which cannot be fetched, e.g. due to missing permissions or a schema table
+    // not existing in given Cassandra version, are left empty instead of failing the whole refresh
+    async fn query_schema_rows(&self, transport: &T, filter: SchemaFilter<'_>) -> SchemaRows {
+        if !self.schema_metadata {
+            return Default::default();
+        }
+
+        let (tables, columns, indexes, views) = tokio::join!(
+            self.query_schema_table_or_empty(transport, "tables", "table_name", filter),
+            self.query_schema_table_or_empty(transport, "columns", "table_name", filter),
+            self.query_schema_table_or_empty(transport, "indexes", "table_name", filter),
+            self.query_schema_table_or_empty(transport, "views", "view_name", filter),
+        );
+
+        let (types, functions, aggregates) = if let SchemaFilter::Table(_, _) = filter {
+            Default::default()
+        } else {
+            tokio::join!(
+                self.query_schema_table_or_empty(transport, "types", "type_name", filter),
+                self.query_schema_table_or_empty(transport, "functions", "function_name", filter),
+                self.query_schema_table_or_empty(transport, "aggregates", "aggregate_name", filter),
+            )
+        };
+
+        SchemaRows {
+            tables,
+            columns,
+            indexes,
+            views,
+            types,
+            functions,
+            aggregates,
+        }
+    }
+
+    async fn query_schema_table_or_empty(
+        &self,
+        transport: &T,
+        schema_table: &str,
+        name_column: &str,
+        filter: SchemaFilter<'_>,
+    ) -> Vec<Row> {
+        self.query_schema_table(transport, schema_table, name_column, filter)
+            .await
+            .unwrap_or_else(|error| {
+                warn!(%error, %schema_table, "Error fetching schema metadata - ignoring.");
+                vec![]
+            })
+    }
+
+    async fn query_schema_table(
+        &self,
+        transport: &T,
+        schema_table: &str,
+        name_column: &str,
+        filter: SchemaFilter<'_>,
+    ) -> Result<Vec<Row>> {
+        let version = self.version_holder.version();
+        let rows = match filter {
+            SchemaFilter::All => {
+                send_query(
+                    &format!("SELECT * FROM system_schema.{}", schema_table),
+                    transport,
+                    version,
+                )
+                .await?
+            }
+            SchemaFilter::Keyspace(keyspace) => {
+                send_query_with_values(
+                    &format!(
+                        "SELECT * FROM system_schema.{} WHERE keyspace_name = ?",
+                        schema_table
+                    ),
+                    QueryValues::SimpleValues(vec![keyspace.into()]),
+                    transport,
+                    version,
+                )
+                .await?
+            }
+            SchemaFilter::Table(keyspace, name) => {
+                send_query_with_values(
+                    &format!(
+                        "SELECT * FROM system_schema.{} WHERE keyspace_name = ? AND {} = ?",
+                        schema_table, name_column
+                    ),
+                    QueryValues::SimpleValues(vec![keyspace.into(), name.into()]),
+                    transport,
+                    version,
+                )
+                .await?
+            }
+        };
+
+        Ok(rows.unwrap_or_default())
     }
 
     async fn add_new_node(
@@ -490,12 +699,20 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         let new_node_info = self.find_new_node_info(broadcast_rpc_address).await;
         match new_node_info {
             Ok(Some(new_node_info)) => {
-                self.metadata.store(Arc::new(add_new_node(
+                let metadata = add_new_node(
                     new_node_info,
                     metadata.as_ref(),
                     &self.connection_pool_factory,
                     state,
-                )));
+                );
+
+                if let Some(node) = metadata.find_node_by_rpc_address(broadcast_rpc_address) {
+                    self.prepare_cached_statements(&node).await;
+
+                    // metadata could have changed while preparing
+                    self.metadata
+                        .rcu(|metadata| metadata.clone_with_shared_node(node.clone()));
+                }
             }
             Ok(None) => {
                 warn!(%broadcast_rpc_address, "Cannot find new node info. Ignoring new node.");
@@ -506,6 +723,21 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         }
     }
 
+    // statements need to be prepared before the node becomes visible to load balancing, otherwise
+    // first requests would get UNPREPARED errors
+    async fn prepare_cached_statements(&self, node: &Node<T, CM>) {
+        let statements = self.prepared_statement_cache.prepared_statements();
+        let prepare = prepare_on_node(node, &statements, self.version_holder.version());
+
+        // a slow node shouldn't stall processing other events
+        if timeout(PREPARE_ON_NODE_TIMEOUT, prepare).await.is_err() {
+            warn!(
+                broadcast_rpc_address = %node.broadcast_rpc_address(),
+                "Timeout preparing statements on node."
+            );
+        }
+    }
+
     async fn find_new_node_info(
         &self,
         broadcast_rpc_address: SocketAddr,
@@ -520,7 +752,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             let local_info = fetch_control_connection_info(
                 control_transport.as_ref(),
                 &control_addr,
-                self.version,
+                self.version_holder.version(),
             )
             .await?;
 
@@ -530,7 +762,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         send_query(
             &format!("SELECT * FROM {}", self.peer_table_name()),
             control_transport.as_ref(),
-            self.version,
+            self.version_holder.version(),
         )
         .await
         .map(|peers| {
@@ -597,26 +829,34 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
 
     async fn refresh_keyspaces(&self) -> Result<FxHashMap<String, KeyspaceMetadata>> {
         let control_transport = self.control_transport()?;
-        send_query(
-            "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces",
-            control_transport.as_ref(),
-            self.version,
-        )
-        .await
-        .and_then(|rows| {
-            rows.map(|rows| rows.iter().map(build_keyspace).try_collect())
-                .transpose()
-        })
-        .map(|keyspaces| keyspaces.unwrap_or_default())
+        let (rows, schema_rows) = tokio::join!(
+            send_query(
+                "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces",
+                control_transport.as_ref(),
+                self.version_holder.version(),
+            ),
+            self.query_schema_rows(control_transport.as_ref(), SchemaFilter::All)
+        );
+
+        let mut keyspaces: FxHashMap<String, KeyspaceMetadata> = rows?
+            .map(|rows| rows.iter().map(build_keyspace).try_collect())
+            .transpose()?
+            .unwrap_or_default();
+
+        add_schema(&mut keyspaces, &schema_rows);
+        Ok(keyspaces)
     }
 
     async fn refresh_node_infos(&self) -> Result<Vec<NodeInfo>> {
         let control_transport = self.control_transport()?;
         let control_addr = control_transport.address();
 
-        let local =
-            fetch_control_connection_info(control_transport.as_ref(), &control_addr, self.version)
-                .await?;
+        let local = fetch_control_connection_info(
+            control_transport.as_ref(),
+            &control_addr,
+            self.version_holder.version(),
+        )
+        .await?;
 
         if !is_peer_row_valid(&local) {
             return Err("Invalid local row info!".into());
@@ -651,8 +891,12 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
     }
 
     async fn query_peers(&self, transport: &T) -> Result<Option<Vec<Row>>> {
-        let peers_v2_result =
-            send_query("SELECT * FROM system.peers_v2", transport, self.version).await;
+        let peers_v2_result = send_query(
+            "SELECT * FROM system.peers_v2",
+            transport,
+            self.version_holder.version(),
+        )
+        .await;
         match peers_v2_result {
             Ok(result) => Ok(result),
             // peers_v2 does not exist
@@ -661,9 +905,471 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                 ..
             })) => {
                 self.is_schema_v2.store(false, Ordering::Relaxed);
-                send_query("SELECT * FROM system.peers", transport, self.version).await
+                send_query(
+                    "SELECT * FROM system.peers",
+                    transport,
+                    self.version_holder.version(),
+                )
+                .await
             }
             Err(error) => Err(error),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use arc_swap::ArcSwap;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::events::{SchemaChange, ServerEvent};
+    use cassandra_protocol::frame::events::{
+        SchemaChangeOptions, SchemaChangeTarget, SchemaChangeType, StatusChange, StatusChangeType,
+    };
+    use cassandra_protocol::frame::frame_prepare::BodyReqPrepare;
+    use cassandra_protocol::frame::frame_request::RequestBody;
+    use cassandra_protocol::frame::frame_result::{
+        ColType, ResResultBody, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::{Direction, Flags, Frame, Opcode, Serialize, Version};
+    use cassandra_protocol::query::{PreparedQuery, PreparedResultMetadata};
+    use cassandra_protocol::types::{CBytesShort, CInet};
+    use fxhash::FxHashMap;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex, Weak};
+    use tokio::sync::watch;
+    use uuid::Uuid;
+
+    use super::ClusterMetadataManager;
+    use crate::cluster::connection_manager::{mock_connection_manager, MockConnectionManager};
+    use crate::cluster::connection_pool::ConnectionPoolFactory;
+    use crate::cluster::prepared_statement_cache::PreparedStatementCache;
+    use crate::cluster::schema_builder::{test_rows, TestValue};
+    use crate::cluster::topology::{
+        KeyspaceMetadata, Node, NodeDistance, NodeState, ReplicationStrategy,
+    };
+    use crate::cluster::{ClusterMetadata, SessionContext, VersionHolder};
+    use crate::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
+    use crate::metrics::NoopMetricsSink;
+    use crate::transport::{mock_transport, MockCdrsTransport, MockResponder};
+
+    type MockManager =
+        ClusterMetadataManager<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+
+    type MockConnectionPoolFactory =
+        ConnectionPoolFactory<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+
+    const NODE_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+
+    fn response(body: ResResultBody) -> Frame {
+        Frame::new(
+            Version::V5,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec().into(),
+            None,
+            vec![],
+            Default::default(),
+        )
+    }
+
+    // creates a manager with nodes connecting using given connection manager and the control
+    // connection responding with given responder
+    fn create_manager(
+        connection_manager: MockConnectionManager<MockCdrsTransport>,
+        control_responder: Option<MockResponder>,
+        prepared_statement_cache: Arc<PreparedStatementCache>,
+        schema_metadata: bool,
+    ) -> (Arc<MockManager>, Arc<MockConnectionPoolFactory>) {
+        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+            Default::default(),
+            Arc::new(VersionHolder::new_negotiated(Version::V5)),
+            connection_manager,
+            watch::channel(None).1,
+            Arc::new(NoopMetricsSink),
+        ));
+
+        let session_context = Arc::new(SessionContext::default());
+        if let Some(control_responder) = control_responder {
+            session_context
+                .control_connection_transport
+                .store(Some(Arc::new(mock_transport(
+                    NODE_ADDRESS,
+                    control_responder,
+                ))));
+        }
+
+        let cluster_metadata_manager = Arc::new(ClusterMetadataManager::new(
+            vec![],
+            connection_pool_factory.clone(),
+            session_context,
+            Box::<AllLocalNodeDistanceEvaluator>::default(),
+            Arc::new(VersionHolder::new_negotiated(Version::V5)),
+            prepared_statement_cache,
+            schema_metadata,
+        ));
+
+        (cluster_metadata_manager, connection_pool_factory)
+    }
+
+    // responds with keyspace "ks" containing table "t" and fails queries of given schema tables;
+    // all queries are recorded
+    fn schema_responder(
+        failing_tables: &'static [&'static str],
+        queries: Arc<Mutex<Vec<String>>>,
+    ) -> MockResponder {
+        Arc::new(move |frame| {
+            let query = match frame.request_body()? {
+                RequestBody::Query(body) => body.query,
+                _ => panic!("QUERY expected"),
+            };
+            queries.lock().unwrap().push(query.clone());
+
+            let schema_table = query
+                .split_whitespace()
+                .find_map(|word| word.strip_prefix("system_schema."))
+                .unwrap_or_default();
+
+            if failing_tables.contains(&schema_table) {
+                return Err(Error::General(format!("Cannot query {}!", schema_table)));
+            }
+
+            let rows = match schema_table {
+                "keyspaces" => test_rows(
+                    &["keyspace_name", "replication"],
+                    &[vec![
+                        TestValue::Text("ks"),
+                        TestValue::Text(r#"{"class":"SimpleStrategy","replication_factor":"1"}"#),
+                    ]],
+                ),
+                "tables" => test_rows(
+                    &["keyspace_name", "table_name", "id"],
+                    &[vec![
+                        TestValue::Text("ks"),
+                        TestValue::Text("t"),
+                        TestValue::Null(ColType::Uuid),
+                    ]],
+                ),
+                "columns" => test_rows(
+                    &[
+                        "keyspace_name",
+                        "table_name",
+                        "column_name",
+                        "kind",
+                        "position",
+                        "type",
+                        "clustering_order",
+                    ],
+                    &[vec![
+                        TestValue::Text("ks"),
+                        TestValue::Text("t"),
+                        TestValue::Text("k"),
+                        TestValue::Text("partition_key"),
+                        TestValue::Int(0),
+                        TestValue::Text("int"),
+                        TestValue::Text("none"),
+                    ]],
+                ),
+                _ => return Ok(response(ResResultBody::Void)),
+            };
+
+            Ok(response(ResResultBody::Rows(rows)))
+        })
+    }
+
+    fn table_event(change_type: SchemaChangeType, keyspace: &str, table: &str) -> ServerEvent {
+        ServerEvent::SchemaChange(SchemaChange {
+            change_type,
+            target: SchemaChangeTarget::Table,
+            options: SchemaChangeOptions::TableType(keyspace.into(), table.into()),
+        })
+    }
+
+    fn metadata_with_empty_keyspace(
+    ) -> Arc<ClusterMetadata<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>> {
+        let mut keyspaces = FxHashMap::default();
+        keyspaces.insert(
+            "ks".to_string(),
+            KeyspaceMetadata::new(ReplicationStrategy::Other),
+        );
+
+        Arc::new(ClusterMetadata::new(Default::default(), keyspaces))
+    }
+
+    #[tokio::test]
+    async fn should_prepare_statements_before_node_is_up() {
+        let manager: Arc<Mutex<Weak<MockManager>>> = Default::default();
+        let prepared = Arc::new(Mutex::new(vec![]));
+
+        // records node state at the time of preparing and modifies metadata in the meantime
+        let connection_manager = mock_connection_manager(Arc::new({
+            let manager = manager.clone();
+            let prepared = prepared.clone();
+            move |frame| {
+                let body = match frame.request_body()? {
+                    RequestBody::Prepare(body) => body,
+                    _ => panic!("PREPARE expected"),
+                };
+
+                let manager = manager.lock().unwrap().upgrade().unwrap();
+                let metadata = manager.metadata();
+                let state = metadata
+                    .find_node_by_rpc_address(NODE_ADDRESS)
+                    .unwrap()
+                    .state();
+                prepared.lock().unwrap().push((body, state));
+
+                manager
+                    .metadata
+                    .store(Arc::new(metadata.clone_with_keyspace(
+                        "other_ks".into(),
+                        KeyspaceMetadata::new(ReplicationStrategy::Other),
+                    )));
+
+                Ok(response(ResResultBody::Void))
+            }
+        }));
+
+        let prepared_statement_cache = Arc::new(PreparedStatementCache::new(10));
+        prepared_statement_cache.register(
+            Some("ks".into()),
+            Arc::new(PreparedQuery {
+                id: ArcSwap::from_pointee(CBytesShort::new(vec![1])),
+                query: "SELECT * FROM t".into(),
+                keyspace: Some("ks".into()),
+                pk_indexes: vec![],
+                result_metadata: ArcSwap::from_pointee(PreparedResultMetadata {
+                    id: None,
+                    metadata: RowsMetadata {
+                        flags: RowsMetadataFlags::NO_METADATA,
+                        columns_count: 0,
+                        paging_state: None,
+                        new_metadata_id: None,
+                        global_table_spec: None,
+                        col_specs: vec![],
+                    },
+                }),
+            }),
+        );
+
+        let (cluster_metadata_manager, connection_pool_factory) =
+            create_manager(connection_manager, None, prepared_statement_cache, true);
+
+        let host_id = Uuid::new_v4();
+        let mut nodes = FxHashMap::default();
+        nodes.insert(
+            host_id,
+            Arc::new(Node::new_with_state(
+                connection_pool_factory,
+                NODE_ADDRESS,
+                None,
+                Some(host_id),
+                Some(NodeDistance::Local),
+                NodeState::Down,
+                vec![],
+                "r1".into(),
+                "dc1".into(),
+            )),
+        );
+
+        cluster_metadata_manager
+            .metadata
+            .store(Arc::new(ClusterMetadata::new(nodes, Default::default())));
+        *manager.lock().unwrap() = Arc::downgrade(&cluster_metadata_manager);
+
+        cluster_metadata_manager
+            .process_event(ServerEvent::StatusChange(StatusChange {
+                change_type: StatusChangeType::Up,
+                addr: CInet { addr: NODE_ADDRESS },
+            }))
+            .await;
+
+        assert_eq!(
+            *prepared.lock().unwrap(),
+            vec![(
+                BodyReqPrepare::new("SELECT * FROM t".into(), Some("ks".into())),
+                NodeState::Down
+            )]
+        );
+
+        let metadata = cluster_metadata_manager.metadata();
+        assert_eq!(
+            metadata
+                .find_node_by_rpc_address(NODE_ADDRESS)
+                .unwrap()
+                .state(),
+            NodeState::Up
+        );
+
+        // changes made while preparing are kept
+        assert!(metadata.keyspace("other_ks").is_some());
+    }
+
+    #[tokio::test]
+    async fn should_refresh_keyspaces_despite_schema_errors() {
+        let (cluster_metadata_manager, _) = create_manager(
+            MockConnectionManager::new(),
+            Some(schema_responder(
+                &["views", "functions"],
+                Default::default(),
+            )),
+            Arc::new(PreparedStatementCache::new(0)),
+            true,
+        );
+
+        let keyspaces = cluster_metadata_manager.refresh_keyspaces().await.unwrap();
+
+        let keyspace = &keyspaces["ks"];
+        assert_eq!(keyspace.tables["t"].partition_key, vec!["k"]);
+        assert!(keyspace.views.is_empty());
+        assert!(keyspace.functions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_refresh_only_keyspaces_without_schema_metadata() {
+        let queries = Arc::new(Mutex::new(vec![]));
+        let (cluster_metadata_manager, _) = create_manager(
+            MockConnectionManager::new(),
+            Some(schema_responder(&[], queries.clone())),
+            Arc::new(PreparedStatementCache::new(0)),
+            false,
+        );
+
+        let keyspaces = cluster_metadata_manager.refresh_keyspaces().await.unwrap();
+        assert!(keyspaces["ks"].tables.is_empty());
+
+        cluster_metadata_manager
+            .metadata
+            .store(metadata_with_empty_keyspace());
+        cluster_metadata_manager
+            .process_event(table_event(SchemaChangeType::Created, "ks", "t"))
+            .await;
+
+        assert!(cluster_metadata_manager
+            .metadata()
+            .keyspace("ks")
+            .unwrap()
+            .tables
+            .is_empty());
+        assert_eq!(queries.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_process_table_schema_changes() {
+        let (cluster_metadata_manager, _) = create_manager(
+            MockConnectionManager::new(),
+            Some(schema_responder(&[], Default::default())),
+            Arc::new(PreparedStatementCache::new(0)),
+            true,
+        );
+
+        cluster_metadata_manager
+            .metadata
+            .store(metadata_with_empty_keyspace());
+
+        cluster_metadata_manager
+            .process_event(table_event(SchemaChangeType::Created, "ks", "t"))
+            .await;
+
+        let metadata = cluster_metadata_manager.metadata();
+        let keyspace = metadata.keyspace("ks").unwrap();
+        assert_eq!(keyspace.tables["t"].partition_key, vec!["k"]);
+        // only the table gets refreshed
+        assert!(matches!(
+            keyspace.replication_strategy,
+            ReplicationStrategy::Other
+        ));
+
+        cluster_metadata_manager
+            .process_event(table_event(SchemaChangeType::Dropped, "ks", "t"))
+            .await;
+
+        let metadata = cluster_metadata_manager.metadata();
+        assert!(metadata.keyspace("ks").unwrap().tables.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_refresh_unknown_keyspace_on_table_change() {
+        let (cluster_metadata_manager, _) = create_manager(
+            MockConnectionManager::new(),
+            Some(schema_responder(&[], Default::default())),
+            Arc::new(PreparedStatementCache::new(0)),
+            true,
+        );
+
+        cluster_metadata_manager
+            .process_event(table_event(SchemaChangeType::Updated, "ks", "t"))
+            .await;
+
+        let metadata = cluster_metadata_manager.metadata();
+        let keyspace = metadata.keyspace("ks").unwrap();
+        assert!(matches!(
+            keyspace.replication_strategy,
+            ReplicationStrategy::SimpleStrategy {
+                replication_factor: 1
+            }
+        ));
+        assert_eq!(keyspace.tables["t"].partition_key, vec!["k"]);
+    }
+
+    #[tokio::test]
+    async fn should_process_keyspace_schema_changes_despite_schema_errors() {
+        let (cluster_metadata_manager, _) = create_manager(
+            MockConnectionManager::new(),
+            Some(schema_responder(
+                &["functions", "aggregates"],
+                Default::default(),
+            )),
+            Arc::new(PreparedStatementCache::new(0)),
+            true,
+        );
+
+        cluster_metadata_manager
+            .metadata
+            .store(metadata_with_empty_keyspace());
+
+        cluster_metadata_manager
+            .process_event(ServerEvent::SchemaChange(SchemaChange {
+                change_type: SchemaChangeType::Updated,
+                target: SchemaChangeTarget::Keyspace,
+                options: SchemaChangeOptions::Keyspace("ks".into()),
+            }))
+            .await;
+
+        let metadata = cluster_metadata_manager.metadata();
+        let keyspace = metadata.keyspace("ks").unwrap();
+        assert!(matches!(
+            keyspace.replication_strategy,
+            ReplicationStrategy::SimpleStrategy {
+                replication_factor: 1
+            }
+        ));
+        assert_eq!(keyspace.tables["t"].partition_key, vec!["k"]);
+        assert!(keyspace.functions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_process_table_schema_changes_despite_schema_errors() {
+        let (cluster_metadata_manager, _) = create_manager(
+            MockConnectionManager::new(),
+            Some(schema_responder(&["views"], Default::default())),
+            Arc::new(PreparedStatementCache::new(0)),
+            true,
+        );
+
+        cluster_metadata_manager
+            .metadata
+            .store(metadata_with_empty_keyspace());
+
+        cluster_metadata_manager
+            .process_event(table_event(SchemaChangeType::Created, "ks", "t"))
+            .await;
+
+        let metadata = cluster_metadata_manager.metadata();
+        assert_eq!(
+            metadata.keyspace("ks").unwrap().tables["t"].partition_key,
+            vec!["k"]
+        );
+    }
+}
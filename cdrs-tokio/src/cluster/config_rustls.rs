@@ -33,7 +33,7 @@ impl NodeRustlsConfigBuilder {
             dns_name,
             authenticator_provider: Arc::new(NoneAuthenticatorProvider),
             config,
-            version: Version::V4,
+            version: Version::V5,
         }
     }
 
@@ -68,7 +68,8 @@ impl NodeRustlsConfigBuilder {
         self
     }
 
-    /// Set cassandra protocol version
+    /// Sets the highest cassandra protocol version to use. Lower versions are negotiated
+    /// automatically, if the cluster doesn't support the given one.
     pub fn with_version(mut self, version: Version) -> Self {
         self.version = version;
         self
@@ -26,7 +26,7 @@ impl Default for NodeTcpConfigBuilder {
         NodeTcpConfigBuilder {
             addrs: vec![],
             authenticator_provider: Arc::new(NoneAuthenticatorProvider),
-            version: Version::V4,
+            version: Version::V5,
         }
     }
 }
@@ -71,7 +71,8 @@ impl NodeTcpConfigBuilder {
         self
     }
 
-    /// Set cassandra protocol version
+    /// Sets the highest cassandra protocol version to use. Lower versions are negotiated
+    /// automatically, if the cluster doesn't support the given one.
     #[must_use]
     pub fn with_version(mut self, version: Version) -> Self {
         self.version = version;
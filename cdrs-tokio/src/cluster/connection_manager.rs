@@ -1,17 +1,21 @@
+use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
 use tokio::sync::mpsc::Sender;
+use tracing::*;
 
 #[cfg(test)]
 use mockall::*;
 
-use crate::cluster::KeyspaceHolder;
+use crate::cluster::{KeyspaceHolder, VersionHolder};
 use crate::future::BoxFuture;
 use crate::transport::CdrsTransport;
 use cassandra_protocol::authenticators::SaslAuthenticatorProvider;
 use cassandra_protocol::compression::Compression;
 use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::frame_error::AdditionalErrorInfo;
 use cassandra_protocol::frame::frame_response::ResponseBody;
+use cassandra_protocol::frame::frame_supported::BodyResSupported;
 use cassandra_protocol::frame::{Frame, Opcode, Version};
 use cassandra_protocol::query::utils::quote;
 
@@ -42,6 +46,151 @@ mock! {
     }
 }
 
+/// Creates a mock connection manager, which creates connections responding to frames using given
+/// function. Control connections (with an event handler) cannot be established.
+#[cfg(test)]
+pub(crate) fn mock_connection_manager(
+    respond: crate::transport::MockResponder,
+) -> MockConnectionManager<crate::transport::MockCdrsTransport> {
+    use futures::FutureExt;
+
+    let mut connection_manager = MockConnectionManager::new();
+    connection_manager
+        .expect_connection()
+        .returning(move |event_handler, _, addr| {
+            let result = if event_handler.is_some() {
+                Err("Control connection not available!".into())
+            } else {
+                Ok(crate::transport::mock_transport(addr, respond.clone()))
+            };
+
+            futures::future::ready(result).boxed()
+        });
+
+    connection_manager
+}
+
+/// Establishes a connection using given function, negotiating protocol version if it hasn't been
+/// negotiated yet. Starting with the current version in `version_holder`, lower versions are tried
+/// as long as the server reports the used one as unsupported. Note: each attempt should use a new
+/// transport, since servers close connections after reporting protocol errors.
+pub async fn negotiate_version<T, F, Fut>(
+    version_holder: &VersionHolder,
+    compression: Compression,
+    mut connect: F,
+) -> Result<T>
+where
+    F: FnMut(Version) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    if version_holder.is_negotiated() {
+        return connect(version_holder.version()).await;
+    }
+
+    let mut version = version_holder.version();
+    if version >= Version::V5 && compression == Compression::Snappy {
+        // snappy is not supported by V5 framing
+        version = Version::V4;
+    }
+
+    loop {
+        match connect(version).await {
+            Ok(transport) => {
+                let negotiated = version_holder.negotiate(version);
+                if negotiated == version {
+                    return Ok(transport);
+                }
+
+                // some other connection negotiated a different version in the meantime
+                return connect(negotiated).await;
+            }
+            Err(error) if is_unsupported_version_error(&error) => match lower_version(version) {
+                Some(lower) => {
+                    debug!(%version, %lower, "Protocol version not supported - trying lower one.");
+                    version = lower;
+                }
+                None => return Err(error),
+            },
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn is_unsupported_version_error(error: &Error) -> bool {
+    match error {
+        Error::UnsupportedProtocolVersion(_) => true,
+        Error::Server(error) => {
+            error.additional_info == AdditionalErrorInfo::Protocol && {
+                let message = error.message.to_lowercase();
+                message.contains("protocol version") || message.contains("beta version")
+            }
+        }
+        _ => false,
+    }
+}
+
+fn lower_version(version: Version) -> Option<Version> {
+    match version {
+        Version::V5 => Some(Version::V4),
+        Version::V4 => Some(Version::V3),
+        Version::V3 => None,
+    }
+}
+
+/// Sends OPTIONS to the server and returns the SUPPORTED response. Fails with
+/// [`Error::UnsupportedProtocolVersion`] if the server reports supported protocol versions, but
+/// the given one is not among them.
+pub async fn fetch_supported_options<T: CdrsTransport>(
+    transport: &T,
+    version: Version,
+) -> Result<BodyResSupported> {
+    let supported = transport
+        .write_frame(&Frame::new_req_options(version))
+        .await?
+        .response_body()?
+        .into_supported()
+        .ok_or_else(|| Error::General("Expected SUPPORTED response to OPTIONS!".into()))?;
+
+    let protocol_versions = supported.protocol_versions();
+    if !protocol_versions.is_empty() && !protocol_versions.contains(&version) {
+        return Err(Error::UnsupportedProtocolVersion(version));
+    }
+
+    Ok(supported)
+}
+
+/// Selects compression supported by both the driver and the server. The preferred compression is
+/// used if possible, otherwise another supported one is selected, falling back to no compression.
+/// If no compression is preferred, compression is never enabled.
+pub fn select_compression(
+    preferred: Compression,
+    supported: &BodyResSupported,
+    version: Version,
+) -> Compression {
+    if !preferred.is_compressed() {
+        return preferred;
+    }
+
+    let server_compression = supported.compression();
+    let is_usable = |compression: &Compression| {
+        server_compression.contains(compression)
+            && (version < Version::V5 || *compression != Compression::Snappy)
+    };
+
+    if is_usable(&preferred) {
+        return preferred;
+    }
+
+    let compression = [Compression::Lz4, Compression::Snappy]
+        .iter()
+        .copied()
+        .find(is_usable)
+        .unwrap_or(Compression::None);
+
+    warn!(%preferred, %compression, "Preferred compression not supported by the server.");
+    compression
+}
+
 /// Establishes Cassandra connection with given authentication, last used keyspace and compression.
 pub async fn startup<
     T: CdrsTransport + 'static,
@@ -53,6 +202,12 @@ pub async fn startup<
     compression: Compression,
     version: Version,
 ) -> Result<()> {
+    if version >= Version::V5 && compression == Compression::Snappy {
+        return Err(Error::General(
+            "Snappy compression is not supported by protocol V5".into(),
+        ));
+    }
+
     let startup_frame = Frame::new_req_startup(compression.as_str().map(String::from), version);
     let start_response = transport.write_frame(&startup_frame).await?;
 
@@ -138,6 +293,8 @@ async fn set_keyspace<T: CdrsTransport>(
             None,
             None,
             None,
+            None,
+            None,
             Default::default(),
             version,
         );
@@ -147,3 +304,123 @@ async fn set_keyspace<T: CdrsTransport>(
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::frame_error::{AdditionalErrorInfo, ErrorBody};
+    use cassandra_protocol::frame::frame_supported::{BodyResSupported, COMPRESSION};
+    use cassandra_protocol::frame::Version;
+    use std::sync::Mutex;
+
+    use crate::cluster::connection_manager::{negotiate_version, select_compression};
+    use crate::cluster::VersionHolder;
+
+    fn unsupported_version_error() -> Error {
+        Error::Server(ErrorBody {
+            error_code: 0xA,
+            message: "Invalid or unsupported protocol version (5)".into(),
+            additional_info: AdditionalErrorInfo::Protocol,
+        })
+    }
+
+    #[tokio::test]
+    async fn should_downgrade_version() {
+        let holder = VersionHolder::new(Version::V5);
+        let attempts = Mutex::new(vec![]);
+
+        let version = negotiate_version(&holder, Compression::None, |version| {
+            attempts.lock().unwrap().push(version);
+            async move {
+                if version > Version::V3 {
+                    Err(unsupported_version_error())
+                } else {
+                    Ok(version)
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(version, Version::V3);
+        assert_eq!(holder.version(), Version::V3);
+        assert!(holder.is_negotiated());
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            vec![Version::V5, Version::V4, Version::V3]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_downgrade_negotiated_version() {
+        let holder = VersionHolder::new_negotiated(Version::V5);
+
+        let result = negotiate_version(&holder, Compression::None, |_| async {
+            Err::<Version, _>(unsupported_version_error())
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(holder.version(), Version::V5);
+    }
+
+    #[tokio::test]
+    async fn should_skip_v5_with_snappy() {
+        let holder = VersionHolder::new(Version::V5);
+
+        let version = negotiate_version(&holder, Compression::Snappy, |version| async move {
+            Ok::<_, Error>(version)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(version, Version::V4);
+    }
+
+    #[test]
+    fn should_select_compression() {
+        let mut supported = BodyResSupported::default();
+        supported
+            .data
+            .insert(COMPRESSION.into(), vec!["snappy".into()]);
+
+        assert_eq!(
+            select_compression(Compression::Lz4, &supported, Version::V4),
+            Compression::Snappy
+        );
+        assert_eq!(
+            select_compression(Compression::Snappy, &supported, Version::V4),
+            Compression::Snappy
+        );
+        assert_eq!(
+            select_compression(Compression::Lz4, &supported, Version::V5),
+            Compression::None
+        );
+        assert_eq!(
+            select_compression(Compression::None, &supported, Version::V4),
+            Compression::None
+        );
+        assert_eq!(
+            select_compression(Compression::Lz4, &Default::default(), Version::V4),
+            Compression::None
+        );
+    }
+
+    #[tokio::test]
+    async fn should_downgrade_on_unsupported_version() {
+        let holder = VersionHolder::new(Version::V5);
+
+        let version = negotiate_version(&holder, Compression::None, |version| async move {
+            if version == Version::V5 {
+                Err(Error::UnsupportedProtocolVersion(version))
+            } else {
+                Ok(version)
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(version, Version::V4);
+    }
+}
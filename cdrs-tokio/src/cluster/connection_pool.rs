@@ -1,18 +1,20 @@
-use arc_swap::{ArcSwap, AsRaw};
-use cassandra_protocol::frame::{Frame, Version};
+use arc_swap::ArcSwap;
+use cassandra_protocol::frame::frame_supported::BodyResSupported;
+use cassandra_protocol::frame::Frame;
 use cassandra_protocol::query::utils::quote;
 use futures::future::{join_all, try_join_all};
 use std::marker::PhantomData;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::watch::Receiver;
 use tracing::*;
 
 use crate::cluster::topology::NodeDistance;
-use crate::cluster::ConnectionManager;
+use crate::cluster::{ConnectionManager, VersionHolder};
 use crate::error::{Error, Result as CdrsResult};
+use crate::metrics::MetricsSink;
 use crate::transport::CdrsTransport;
 
 async fn new_connection<T: CdrsTransport, CM: ConnectionManager<T>>(
@@ -75,24 +77,29 @@ impl ConnectionPoolConfig {
 /// Factory for node connection pools.
 pub struct ConnectionPoolFactory<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
     config: ConnectionPoolConfig,
-    version: Version,
+    version_holder: Arc<VersionHolder>,
     connection_manager: Arc<CM>,
     keyspace_receiver: Receiver<Option<String>>,
+    metrics: Arc<dyn MetricsSink + Send + Sync>,
+    is_closed: AtomicBool,
     _transport: PhantomData<T>,
 }
 
 impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPoolFactory<T, CM> {
     pub fn new(
         config: ConnectionPoolConfig,
-        version: Version,
+        version_holder: Arc<VersionHolder>,
         connection_manager: CM,
         keyspace_receiver: Receiver<Option<String>>,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
     ) -> Self {
         ConnectionPoolFactory {
             config,
-            version,
+            version_holder,
             connection_manager: Arc::new(connection_manager),
             keyspace_receiver,
+            metrics,
+            is_closed: AtomicBool::new(false),
             _transport: Default::default(),
         }
     }
@@ -102,17 +109,28 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPoolFactory<T, CM> {
         self.connection_manager.as_ref()
     }
 
+    /// Prevents creating new pools, e.g. when the session is closing.
+    #[inline]
+    pub fn close(&self) {
+        self.is_closed.store(true, Ordering::Relaxed);
+    }
+
     pub async fn create(
         &self,
         node_distance: NodeDistance,
         broadcast_rpc_address: SocketAddr,
     ) -> CdrsResult<Arc<ConnectionPool<T, CM>>> {
+        if self.is_closed.load(Ordering::Relaxed) {
+            return Err(Error::General("Connection pool factory is closed!".into()));
+        }
+
         let pool = Arc::new(
             ConnectionPool::new(
                 self.connection_manager.clone(),
                 broadcast_rpc_address,
                 node_distance,
                 self.config,
+                self.metrics.clone(),
             )
             .await?,
         );
@@ -120,7 +138,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPoolFactory<T, CM> {
         // watch for keyspace changes
         let mut keyspace_receiver = self.keyspace_receiver.clone();
         let pool_clone = pool.clone();
-        let version = self.version;
+        let version_holder = self.version_holder.clone();
 
         tokio::spawn(async move {
             while let Ok(()) = keyspace_receiver.changed().await {
@@ -135,8 +153,10 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPoolFactory<T, CM> {
                         None,
                         None,
                         None,
+                        None,
+                        None,
                         Default::default(),
-                        version,
+                        version_holder.version(),
                     ));
 
                     join_all(pool_clone.pool.iter()
@@ -165,6 +185,9 @@ pub struct ConnectionPool<T: CdrsTransport, CM: ConnectionManager<T>> {
     config: ConnectionPoolConfig,
     pool: Vec<ArcSwap<T>>,
     current_index: AtomicUsize,
+    supported_options: Option<BodyResSupported>,
+    metrics: Arc<dyn MetricsSink + Send + Sync>,
+    is_closed: AtomicBool,
 }
 
 impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPool<T, CM> {
@@ -173,6 +196,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPool<T, CM> {
         broadcast_rpc_address: SocketAddr,
         node_distance: NodeDistance,
         config: ConnectionPoolConfig,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
     ) -> CdrsResult<Self> {
         let size = if node_distance == NodeDistance::Local {
             config.local_size
@@ -181,7 +205,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPool<T, CM> {
         };
 
         // initialize the pool
-        let pool = try_join_all((0..size).into_iter().map(|_| {
+        let pool: Vec<_> = try_join_all((0..size).into_iter().map(|_| {
             new_connection(
                 connection_manager.as_ref(),
                 broadcast_rpc_address,
@@ -193,22 +217,49 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPool<T, CM> {
         .map(ArcSwap::from_pointee)
         .collect();
 
+        for _ in &pool {
+            metrics.record_connection_opened(broadcast_rpc_address);
+        }
+
+        // all connections are made to the same node, so any of them can describe it
+        let supported_options = pool[0].load().supported_options().cloned();
+
         Ok(ConnectionPool {
             connection_manager,
             broadcast_rpc_address,
             config,
             pool,
             current_index: AtomicUsize::new(0),
+            supported_options,
+            metrics,
+            is_closed: AtomicBool::new(false),
         })
     }
 
+    /// Returns options reported by the node when establishing the pool, if available.
+    #[inline]
+    pub fn supported_options(&self) -> Option<&BodyResSupported> {
+        self.supported_options.as_ref()
+    }
+
     #[inline]
     pub async fn connection(&self) -> CdrsResult<Arc<T>> {
+        if self.is_closed.load(Ordering::Relaxed) {
+            return Err(Error::General("Connection pool is closed!".into()));
+        }
+
         let index = self.current_index.fetch_add(1, Ordering::Relaxed) % self.pool.len();
         let slot = &self.pool[index];
 
         let connection = slot.load().clone();
         if !connection.is_broken() {
+            if connection.is_saturated() {
+                // prefer other connections over waiting for a free slot
+                if let Some(connection) = self.find_unsaturated_connection(index) {
+                    return Ok(connection);
+                }
+            }
+
             return Ok(connection);
         }
 
@@ -224,12 +275,101 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ConnectionPool<T, CM> {
         );
 
         // another thread might have already updated this slot, so try once and use current one
-        let previous = slot.compare_and_swap(&connection, new_connection.clone());
+        let previous: Arc<T> =
+            Arc::clone(&slot.compare_and_swap(&connection, new_connection.clone()));
+        if Arc::ptr_eq(&previous, &connection) {
+            self.metrics
+                .record_connection_broken(self.broadcast_rpc_address);
+            self.metrics
+                .record_connection_opened(self.broadcast_rpc_address);
 
-        Ok(if previous.as_raw() == (&connection).as_raw() {
-            new_connection
-        } else {
-            previous.clone()
-        })
+            return Ok(new_connection);
+        }
+
+        // nothing has been sent using the redundant connection, so there's nothing to drain
+        new_connection.close(Duration::ZERO).await;
+
+        Ok(previous)
+    }
+
+    /// Stops handing out connections and closes all pooled connections, after requests in flight
+    /// complete or given drain timeout elapses.
+    pub async fn close(&self, drain_timeout: Duration) {
+        self.is_closed.store(true, Ordering::Relaxed);
+
+        join_all(
+            self.pool
+                .iter()
+                .map(|connection| async move { connection.load().close(drain_timeout).await }),
+        )
+        .await;
+    }
+
+    fn find_unsaturated_connection(&self, start_index: usize) -> Option<Arc<T>> {
+        (1..self.pool.len())
+            .map(|offset| self.pool[(start_index + offset) % self.pool.len()].load_full())
+            .find(|connection| !connection.is_broken() && !connection.is_saturated())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Barrier;
+
+    use super::{ConnectionPool, ConnectionPoolConfig};
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::topology::NodeDistance;
+    use crate::metrics::{InMemoryMetricsSink, MetricsSink};
+    use crate::transport::MockCdrsTransport;
+
+    #[tokio::test]
+    async fn should_record_only_installed_replacement_connections() {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+        let connections = Arc::new(AtomicUsize::new(0));
+        // replacement connections are established concurrently
+        let barrier = Arc::new(Barrier::new(2));
+
+        let mut connection_manager = MockConnectionManager::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                // the initial connection is broken
+                let is_broken = connections.fetch_add(1, Ordering::Relaxed) == 0;
+                let barrier = barrier.clone();
+
+                async move {
+                    if !is_broken {
+                        barrier.wait().await;
+                    }
+
+                    let mut transport = MockCdrsTransport::new();
+                    transport.expect_is_broken().return_const(is_broken);
+                    transport.expect_address().return_const(addr);
+                    Ok(transport)
+                }
+                .boxed()
+            });
+
+        let metrics = Arc::new(InMemoryMetricsSink::default());
+        let pool = ConnectionPool::new(
+            Arc::new(connection_manager),
+            addr,
+            NodeDistance::Local,
+            ConnectionPoolConfig::default(),
+            metrics.clone(),
+        )
+        .await
+        .unwrap();
+
+        let (first, second) = tokio::join!(pool.connection(), pool.connection());
+        assert!(Arc::ptr_eq(&first.unwrap(), &second.unwrap()));
+
+        let node_metrics = metrics.snapshot().unwrap().nodes.remove(&addr).unwrap();
+        assert_eq!(node_metrics.connections_opened, 2);
+        assert_eq!(node_metrics.connections_broken, 1);
     }
 }
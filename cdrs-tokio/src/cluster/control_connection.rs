@@ -7,12 +7,12 @@ use tokio::time::sleep;
 use tracing::*;
 
 use crate::cluster::topology::Node;
-use crate::cluster::{ClusterMetadataManager, ConnectionManager, SessionContext};
+use crate::cluster::{ClusterMetadataManager, ConnectionManager, SessionContext, VersionHolder};
 use crate::load_balancing::LoadBalancingStrategy;
 use crate::retry::{ReconnectionPolicy, ReconnectionSchedule};
 use crate::transport::CdrsTransport;
 use cassandra_protocol::events::{ServerEvent, SimpleServerEvent};
-use cassandra_protocol::frame::{Frame, Version};
+use cassandra_protocol::frame::Frame;
 
 const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(10);
 const EVENT_CHANNEL_CAPACITY: usize = 32;
@@ -29,7 +29,7 @@ pub struct ControlConnection<
     cluster_metadata_manager: Arc<ClusterMetadataManager<T, CM>>,
     event_sender: Sender<ServerEvent>,
     session_context: Arc<SessionContext<T>>,
-    version: Version,
+    version_holder: Arc<VersionHolder>,
 }
 
 impl<
@@ -57,7 +57,7 @@ impl<
                         SimpleServerEvent::StatusChange,
                         SimpleServerEvent::TopologyChange,
                     ],
-                    self.version,
+                    self.version_holder.version(),
                 );
 
                 // in case of error, simply reconnect
@@ -150,8 +150,10 @@ mod tests {
     };
     use crate::cluster::topology::NodeMap;
     use crate::cluster::topology::{Node, NodeDistance, NodeState};
+    use crate::cluster::VersionHolder;
     use crate::cluster::{ClusterMetadata, NodeInfo};
     use crate::load_balancing::node_distance_evaluator::MockNodeDistanceEvaluator;
+    use crate::metrics::NoopMetricsSink;
     use crate::transport::MockCdrsTransport;
 
     fn create_connection_pool_factory(
@@ -161,9 +163,10 @@ mod tests {
         let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
         let connection_pool_factory = ConnectionPoolFactory::new(
             Default::default(),
-            Version::V4,
+            Arc::new(VersionHolder::new_negotiated(Version::V4)),
             connection_manager,
             keyspace_receiver,
+            Arc::new(NoopMetricsSink),
         );
 
         Arc::new(connection_pool_factory)
@@ -1,9 +1,17 @@
 use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error;
-use cassandra_protocol::frame::frame_result::{RowsMetadata, RowsMetadataFlags};
+use cassandra_protocol::frame::frame_response::ResponseBody;
+use cassandra_protocol::frame::frame_result::RowsMetadataFlags;
+use cassandra_protocol::frame::{Frame, TryFromRow};
 use cassandra_protocol::query::{PreparedQuery, QueryParams, QueryParamsBuilder, QueryValues};
-use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::rows::{Row, RowIterator};
 use cassandra_protocol::types::CBytes;
+use futures::future::BoxFuture;
+use futures::{FutureExt, Stream};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 use crate::cluster::session::Session;
 use crate::cluster::ConnectionManager;
@@ -11,6 +19,8 @@ use crate::load_balancing::LoadBalancingStrategy;
 use crate::statement::StatementParamsBuilder;
 use crate::transport::CdrsTransport;
 
+const DEFAULT_PREFETCH_DEPTH: usize = 1;
+
 pub struct SessionPager<
     'a,
     T: CdrsTransport + 'static,
@@ -18,6 +28,7 @@ pub struct SessionPager<
     LB: LoadBalancingStrategy<T, CM> + Send + Sync,
 > {
     page_size: i32,
+    prefetch_depth: usize,
     session: &'a Session<T, CM, LB>,
 }
 
@@ -29,7 +40,19 @@ impl<
     > SessionPager<'a, T, CM, LB>
 {
     pub fn new(session: &'a Session<T, CM, LB>, page_size: i32) -> SessionPager<'a, T, CM, LB> {
-        SessionPager { session, page_size }
+        SessionPager {
+            session,
+            page_size,
+            prefetch_depth: DEFAULT_PREFETCH_DEPTH,
+        }
+    }
+
+    /// Sets the number of pages fetched ahead of the one being consumed, when using row streams.
+    /// Zero means the next page is fetched only after the current one is consumed.
+    #[must_use]
+    pub fn with_prefetch_depth(mut self, prefetch_depth: usize) -> Self {
+        self.prefetch_depth = prefetch_depth;
+        self
     }
 
     pub fn query_with_pager_state<Q>(
@@ -124,6 +147,43 @@ impl<
         self.pager_state
     }
 
+    /// Converts the pager into a stream of rows from all remaining pages. Next pages are fetched
+    /// in the background, while the current one is being consumed.
+    pub fn into_stream(self) -> RowStream<'a> {
+        let session = self.pager.session;
+        let page_size = self.pager.page_size;
+        let consistency = self.consistency;
+        let query = self.query.to_string();
+        let qv = self.qv;
+
+        RowStream::new(
+            self.pager_state,
+            self.pager.prefetch_depth,
+            Box::new(move |paging_state| {
+                let mut params = StatementParamsBuilder::new()
+                    .with_consistency(consistency)
+                    .with_page_size(page_size);
+
+                if let Some(qv) = &qv {
+                    params = params.with_values(qv.clone());
+                }
+                if let Some(paging_state) = paging_state {
+                    params = params.with_paging_state(paging_state);
+                }
+
+                session
+                    .query_with_params(query.clone(), params.build())
+                    .boxed()
+            }),
+        )
+    }
+
+    /// Converts the pager into a stream of typed rows from all remaining pages.
+    #[inline]
+    pub fn into_typed_stream<R: TryFromRow>(self) -> TypedRowStream<'a, R> {
+        self.into_stream().into_typed()
+    }
+
     pub async fn next(&mut self) -> error::Result<Vec<Row>> {
         let mut params = StatementParamsBuilder::new()
             .with_consistency(self.consistency)
@@ -144,16 +204,7 @@ impl<
             .await
             .and_then(|frame| frame.response_body())?;
 
-        let metadata_res: error::Result<RowsMetadata> = body
-            .as_rows_metadata()
-            .ok_or_else(|| "Pager query should yield a vector of rows".into());
-        let metadata = metadata_res?;
-
-        self.pager_state.has_more_pages =
-            Some(metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES));
-        self.pager_state.cursor = metadata.paging_state;
-        body.into_rows()
-            .ok_or_else(|| "Pager query should yield a vector of rows".into())
+        into_page(body, &mut self.pager_state).map(Iterator::collect)
     }
 
     pub fn has_more(&self) -> bool {
@@ -184,6 +235,34 @@ impl<
         self.pager_state
     }
 
+    /// Converts the pager into a stream of rows from all remaining pages. Next pages are fetched
+    /// in the background, while the current one is being consumed.
+    pub fn into_stream(self) -> RowStream<'a> {
+        let session = self.pager.session;
+        let page_size = self.pager.page_size;
+        let query = self.query;
+
+        RowStream::new(
+            self.pager_state,
+            self.pager.prefetch_depth,
+            Box::new(move |paging_state| {
+                let mut params = StatementParamsBuilder::new().with_page_size(page_size);
+                if let Some(paging_state) = paging_state {
+                    params = params.with_paging_state(paging_state);
+                }
+
+                let params = params.build();
+                async move { session.exec_with_params(query, &params).await }.boxed()
+            }),
+        )
+    }
+
+    /// Converts the pager into a stream of typed rows from all remaining pages.
+    #[inline]
+    pub fn into_typed_stream<R: TryFromRow>(self) -> TypedRowStream<'a, R> {
+        self.into_stream().into_typed()
+    }
+
     pub async fn next(&mut self) -> error::Result<Vec<Row>> {
         let mut params = StatementParamsBuilder::new().with_page_size(self.pager.page_size);
         if let Some(cursor) = &self.pager_state.cursor {
@@ -197,17 +276,7 @@ impl<
             .await
             .and_then(|frame| frame.response_body())?;
 
-        let metadata_res: error::Result<RowsMetadata> = body
-            .as_rows_metadata()
-            .ok_or_else(|| "Pager query should yield a vector of rows".into());
-        let metadata = metadata_res?;
-
-        self.pager_state.has_more_pages =
-            Some(metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES));
-        self.pager_state.cursor = metadata.paging_state;
-
-        body.into_rows()
-            .ok_or_else(|| "Pager query should yield a vector of rows".into())
+        into_page(body, &mut self.pager_state).map(Iterator::collect)
     }
 
     #[inline]
@@ -223,6 +292,156 @@ impl<
     }
 }
 
+type FetchPage<'a> =
+    Box<dyn FnMut(Option<CBytes>) -> BoxFuture<'a, error::Result<Frame>> + Send + 'a>;
+
+/// Stream of rows from consecutive result pages. Pages are requested ahead of time, up to the
+/// configured prefetch depth, while the current one is being consumed. The stream ends after the
+/// first error.
+pub struct RowStream<'a> {
+    fetch_page: FetchPage<'a>,
+    pending_page: Option<BoxFuture<'a, error::Result<Frame>>>,
+    current_page: Option<RowIterator>,
+    pages: VecDeque<RowIterator>,
+    pager_state: PagerState,
+    prefetch_depth: usize,
+    error: Option<error::Error>,
+}
+
+impl<'a> RowStream<'a> {
+    fn new(pager_state: PagerState, prefetch_depth: usize, fetch_page: FetchPage<'a>) -> Self {
+        RowStream {
+            fetch_page,
+            pending_page: None,
+            current_page: None,
+            pages: VecDeque::with_capacity(prefetch_depth),
+            pager_state,
+            prefetch_depth,
+            error: None,
+        }
+    }
+
+    /// Converts this stream into a stream of typed rows.
+    #[inline]
+    pub fn into_typed<R: TryFromRow>(self) -> TypedRowStream<'a, R> {
+        TypedRowStream {
+            inner: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a copy of the state after the last received page, which can be used for continuing
+    /// paging later. Note: prefetched pages are included.
+    #[inline]
+    pub fn pager_state(&self) -> PagerState {
+        self.pager_state.clone()
+    }
+
+    fn should_fetch(&self) -> bool {
+        self.error.is_none()
+            && self.pager_state.has_more_pages.unwrap_or(true)
+            && (self.pages.len() < self.prefetch_depth
+                || (self.current_page.is_none() && self.pages.is_empty()))
+    }
+
+    /// Drives page fetching. Returns `Ready` when there is nothing more to fetch at the moment.
+    fn poll_pages(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if self.pending_page.is_none() {
+                if !self.should_fetch() {
+                    return Poll::Ready(());
+                }
+
+                self.pending_page = Some((self.fetch_page)(self.pager_state.cursor.clone()));
+            }
+
+            let result = match &mut self.pending_page {
+                Some(pending_page) => futures::ready!(pending_page.poll_unpin(cx)),
+                None => return Poll::Ready(()),
+            };
+            self.pending_page = None;
+
+            match result
+                .and_then(|frame| frame.response_body())
+                .and_then(|body| into_page(body, &mut self.pager_state))
+            {
+                Ok(page) => self.pages.push_back(page),
+                Err(error) => {
+                    self.pager_state.has_more_pages = Some(false);
+                    self.error = Some(error);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Stream for RowStream<'a> {
+    type Item = error::Result<Row>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(row) = this.current_page.as_mut().and_then(Iterator::next) {
+                // keep fetching next pages in the background
+                let _ = this.poll_pages(cx);
+                return Poll::Ready(Some(Ok(row)));
+            }
+
+            this.current_page = this.pages.pop_front();
+            if this.current_page.is_some() {
+                continue;
+            }
+
+            if let Some(error) = this.error.take() {
+                return Poll::Ready(Some(Err(error)));
+            }
+
+            futures::ready!(this.poll_pages(cx));
+            if this.pages.is_empty() && this.error.is_none() {
+                return Poll::Ready(None);
+            }
+        }
+    }
+}
+
+/// Stream of rows from consecutive result pages, converted to given type.
+pub struct TypedRowStream<'a, R> {
+    inner: RowStream<'a>,
+    _marker: PhantomData<fn() -> R>,
+}
+
+impl<'a, R> TypedRowStream<'a, R> {
+    /// Returns a copy of the state after the last received page.
+    #[inline]
+    pub fn pager_state(&self) -> PagerState {
+        self.inner.pager_state()
+    }
+}
+
+impl<'a, R: TryFromRow> Stream for TypedRowStream<'a, R> {
+    type Item = error::Result<R>;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner)
+            .poll_next(cx)
+            .map(|row| row.map(|row| row.and_then(R::try_from_row)))
+    }
+}
+
+fn into_page(body: ResponseBody, pager_state: &mut PagerState) -> error::Result<RowIterator> {
+    let metadata = &body
+        .as_cols()
+        .ok_or_else(|| error::Error::from("Pager query should yield a vector of rows"))?
+        .metadata;
+
+    pager_state.has_more_pages = Some(metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES));
+    pager_state.cursor = metadata.paging_state.clone();
+
+    body.into_rows_iter()
+        .ok_or_else(|| "Pager query should yield a vector of rows".into())
+}
+
 #[derive(Clone, PartialEq, Debug, Default)]
 pub struct PagerState {
     cursor: Option<CBytes>,
@@ -273,3 +492,149 @@ impl PagerState {
         self.cursor
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::frame_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, ResResultBody, RowsMetadata,
+        RowsMetadataFlags, TableSpec,
+    };
+    use cassandra_protocol::frame::{Direction, Flags, Opcode, Serialize, Version};
+    use cassandra_protocol::types::ByName;
+    use futures::{future, StreamExt};
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct Value(i32);
+
+    impl TryFromRow for Value {
+        fn try_from_row(row: Row) -> error::Result<Self> {
+            row.r_by_name("value").map(Value)
+        }
+    }
+
+    fn page(values: &[i32], paging_state: Option<&[u8]>) -> error::Result<Frame> {
+        let mut rows_content = vec![];
+        let mut cursor = Cursor::new(&mut rows_content);
+        for value in values {
+            4i32.serialize(&mut cursor);
+            value.serialize(&mut cursor);
+        }
+
+        let mut flags = RowsMetadataFlags::GLOBAL_TABLE_SPACE;
+        if paging_state.is_some() {
+            flags.insert(RowsMetadataFlags::HAS_MORE_PAGES);
+        }
+
+        let body = ResResultBody::Rows(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags,
+                columns_count: 1,
+                paging_state: paging_state.map(|state| CBytes::new(state.to_vec())),
+                new_metadata_id: None,
+                global_table_spec: Some(TableSpec {
+                    ks_name: "ks".into(),
+                    table_name: "table".into(),
+                }),
+                col_specs: vec![ColSpec {
+                    table_spec: None,
+                    name: "value".into(),
+                    col_type: ColTypeOption {
+                        id: ColType::Int,
+                        value: None,
+                    },
+                }],
+            },
+            rows_count: values.len() as i32,
+            rows_content: rows_content.into(),
+        });
+
+        Ok(Frame::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec().into(),
+            None,
+            vec![],
+            Default::default(),
+        ))
+    }
+
+    fn row_stream(
+        pages: Vec<error::Result<Frame>>,
+        prefetch_depth: usize,
+    ) -> (RowStream<'static>, Arc<Mutex<Vec<Option<CBytes>>>>) {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let mut pages = pages.into_iter();
+
+        let stream = RowStream::new(PagerState::new(), prefetch_depth, {
+            let requests = requests.clone();
+            Box::new(move |paging_state| {
+                requests.lock().unwrap().push(paging_state);
+                future::ready(pages.next().expect("unexpected page request")).boxed()
+            })
+        });
+
+        (stream, requests)
+    }
+
+    #[tokio::test]
+    async fn should_stream_all_pages() {
+        let (stream, requests) = row_stream(
+            vec![
+                page(&[1, 2], Some(&[1])),
+                page(&[], Some(&[2])),
+                page(&[3], None),
+            ],
+            1,
+        );
+
+        let values: Vec<i32> = stream
+            .into_typed::<Value>()
+            .map(|value| value.unwrap().0)
+            .collect()
+            .await;
+
+        assert_eq!(values, vec![1, 2, 3]);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![None, Some(CBytes::new(vec![1])), Some(CBytes::new(vec![2]))]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_prefetch_pages() {
+        let (mut stream, requests) = row_stream(vec![page(&[1], Some(&[1])), page(&[2], None)], 1);
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert!(!stream.pager_state().has_more());
+
+        let (mut stream, requests) = row_stream(vec![page(&[1], Some(&[1])), page(&[2], None)], 0);
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert!(stream.next().await.unwrap().is_ok());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_end_after_error() {
+        let (mut stream, _) = row_stream(
+            vec![
+                page(&[1], Some(&[1])),
+                Err(error::Error::General("test".into())),
+            ],
+            1,
+        );
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+}
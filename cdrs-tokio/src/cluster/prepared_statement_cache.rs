@@ -0,0 +1,429 @@
+use arc_swap::ArcSwap;
+use cassandra_protocol::frame::frame_result::BodyResResultPrepared;
+use cassandra_protocol::frame::{Frame, Version};
+use cassandra_protocol::query::utils::prepare_flags;
+use cassandra_protocol::query::{PreparedQuery, PreparedResultMetadata};
+use cassandra_protocol::types::CBytesShort;
+use futures::future::join_all;
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+use tracing::*;
+
+use crate::cluster::topology::Node;
+use crate::cluster::ConnectionManager;
+use crate::transport::CdrsTransport;
+
+pub const DEFAULT_PREPARED_STATEMENT_CACHE_SIZE: usize = 512;
+
+/// Cache slot for a single statement. Empty until the statement gets prepared, which allows
+/// concurrent users to wait for a single in-flight PREPARE.
+pub(crate) type PreparedStatementCell = Arc<OnceCell<Arc<PreparedQuery>>>;
+
+/// Statement prepared in the session - a query along with the keyspace it was prepared in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct StatementKey {
+    pub keyspace: Option<String>,
+    pub query: String,
+}
+
+struct CacheEntry {
+    cell: PreparedStatementCell,
+    last_used: u64,
+    // ids the statement has been prepared with - usually one, unless schema changed
+    ids: Vec<CBytesShort>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<StatementKey, CacheEntry>,
+    // last usage -> key, for finding least recently used entries
+    usage: BTreeMap<u64, StatementKey>,
+    tick: u64,
+    // prepared ids of cached statements, for re-preparing
+    keys_by_id: HashMap<CBytesShort, StatementKey>,
+}
+
+impl CacheState {
+    // returns the entry for given statement, creating an empty one if it's not cached and marking
+    // it as most recently used
+    fn touch(&mut self, key: StatementKey, capacity: usize) -> &mut CacheEntry {
+        let CacheState {
+            entries,
+            usage,
+            tick,
+            keys_by_id,
+        } = self;
+
+        *tick += 1;
+
+        if entries.contains_key(&key) {
+            let entry = entries.get_mut(&key).unwrap();
+            let previous_usage = mem::replace(&mut entry.last_used, *tick);
+            usage.remove(&previous_usage);
+            usage.insert(*tick, key);
+
+            return entry;
+        }
+
+        if entries.len() >= capacity {
+            if let Some((_, evicted)) = usage.pop_first() {
+                if let Some(evicted) = entries.remove(&evicted) {
+                    for id in &evicted.ids {
+                        keys_by_id.remove(id);
+                    }
+                }
+            }
+        }
+
+        usage.insert(*tick, key.clone());
+        entries.entry(key).or_insert(CacheEntry {
+            cell: Default::default(),
+            last_used: *tick,
+            ids: vec![],
+        })
+    }
+}
+
+/// LRU cache of prepared statements, keyed by query string and keyspace. Additionally keeps ids of
+/// cached statements, so they can be re-prepared when a node reports them as unknown. Statements
+/// evicted from the cache are forgotten along with their ids.
+pub(crate) struct PreparedStatementCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl PreparedStatementCache {
+    pub fn new(capacity: usize) -> Self {
+        PreparedStatementCache {
+            capacity,
+            state: Default::default(),
+        }
+    }
+
+    /// Returns the slot for given statement, creating an empty one if it's not cached. The least
+    /// recently used statement is evicted if the cache is full. Zero capacity disables caching.
+    pub fn entry(&self, keyspace: Option<&str>, query: &str) -> PreparedStatementCell {
+        if self.capacity == 0 {
+            return Default::default();
+        }
+
+        let key = StatementKey {
+            keyspace: keyspace.map(|keyspace| keyspace.to_string()),
+            query: query.to_string(),
+        };
+
+        self.state
+            .lock()
+            .unwrap()
+            .touch(key, self.capacity)
+            .cell
+            .clone()
+    }
+
+    /// Remembers a statement prepared in given keyspace, so it can be re-prepared in the same
+    /// keyspace when a node reports it as unknown. Registering counts as using the statement and
+    /// caches it, unless it's already cached or being prepared.
+    pub fn register(&self, keyspace: Option<String>, prepared: Arc<PreparedQuery>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let key = StatementKey {
+            keyspace,
+            query: prepared.query.clone(),
+        };
+        let id = prepared.id().as_ref().clone();
+
+        let mut state = self.state.lock().unwrap();
+        let entry = state.touch(key.clone(), self.capacity);
+        if !entry.ids.contains(&id) {
+            entry.ids.push(id.clone());
+        }
+
+        // fails if the statement is already cached or being prepared, which is fine
+        let _ = entry.cell.set(prepared);
+
+        state.keys_by_id.insert(id, key);
+    }
+
+    /// Updates a cached statement after re-preparing it. If the server assigned it a new id (e.g.
+    /// after a schema change), the cached [`PreparedQuery`] gets replaced by one with the new id,
+    /// otherwise only its result metadata is updated.
+    pub fn update_reprepared(
+        &self,
+        previous_id: &CBytesShort,
+        statement: &StatementKey,
+        prepared: &BodyResResultPrepared,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let CacheState {
+            entries,
+            keys_by_id,
+            ..
+        } = &mut *state;
+
+        // might have been evicted in the meantime
+        let entry = match entries.get_mut(statement) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        if !entry.ids.contains(&prepared.id) {
+            entry.ids.push(prepared.id.clone());
+            keys_by_id.insert(prepared.id.clone(), statement.clone());
+        }
+
+        let result_metadata = PreparedResultMetadata {
+            id: prepared.result_metadata_id.clone(),
+            metadata: prepared.result_metadata.clone(),
+        };
+
+        let cached = match entry.cell.get() {
+            Some(cached) => cached.clone(),
+            None => return,
+        };
+
+        let cached_id = cached.id();
+        if *cached_id == prepared.id {
+            cached.update_result_metadata(result_metadata);
+        } else if *cached_id == *previous_id {
+            let reprepared = PreparedQuery {
+                id: ArcSwap::from_pointee(prepared.id.clone()),
+                query: cached.query.clone(),
+                keyspace: cached.keyspace.clone(),
+                pk_indexes: prepared.metadata.pk_indexes.clone(),
+                result_metadata: ArcSwap::from_pointee(result_metadata),
+            };
+
+            entry.cell = Arc::new(OnceCell::new_with(Some(Arc::new(reprepared))));
+        }
+    }
+
+    /// Returns a cached statement with given id.
+    pub fn statement_by_id(&self, id: &CBytesShort) -> Option<StatementKey> {
+        self.state.lock().unwrap().keys_by_id.get(id).cloned()
+    }
+
+    /// Returns all cached statements, which have been prepared.
+    pub fn prepared_statements(&self) -> Vec<StatementKey> {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.ids.is_empty())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+/// Prepares given statements on a single node, so it doesn't need to re-prepare them when
+/// receiving traffic. Errors are only logged, since failed statements will get re-prepared on
+/// first use anyway.
+pub(crate) async fn prepare_on_node<T: CdrsTransport, CM: ConnectionManager<T>>(
+    node: &Node<T, CM>,
+    statements: &[StatementKey],
+    version: Version,
+) {
+    if statements.is_empty() {
+        return;
+    }
+
+    let broadcast_rpc_address = node.broadcast_rpc_address();
+    let transport = match node.persistent_connection().await {
+        Ok(transport) => transport,
+        Err(error) => {
+            warn!(%error, %broadcast_rpc_address, "Cannot connect to node to prepare statements.");
+            return;
+        }
+    };
+
+    debug!(%broadcast_rpc_address, count = statements.len(), "Preparing statements on node.");
+
+    let flags = prepare_flags(false, false);
+    let results = join_all(statements.iter().map(|statement| {
+        let frame = Frame::new_req_prepare(
+            statement.query.clone(),
+            statement.keyspace.clone(),
+            flags,
+            version,
+        );
+        let transport = transport.clone();
+        async move { transport.write_frame(&frame).await }
+    }))
+    .await;
+
+    for error in results.into_iter().filter_map(|result| result.err()) {
+        warn!(%error, %broadcast_rpc_address, "Error preparing statement on node.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PreparedStatementCache, StatementKey};
+    use arc_swap::ArcSwap;
+    use cassandra_protocol::frame::frame_result::{
+        BodyResResultPrepared, PreparedMetadata, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::query::{PreparedQuery, PreparedResultMetadata};
+    use cassandra_protocol::types::CBytesShort;
+    use std::sync::Arc;
+
+    fn empty_rows_metadata() -> RowsMetadata {
+        RowsMetadata {
+            flags: RowsMetadataFlags::NO_METADATA,
+            columns_count: 0,
+            paging_state: None,
+            new_metadata_id: None,
+            global_table_spec: None,
+            col_specs: vec![],
+        }
+    }
+
+    fn prepared_query(id: &CBytesShort, query: &str) -> Arc<PreparedQuery> {
+        Arc::new(PreparedQuery {
+            id: ArcSwap::from_pointee(id.clone()),
+            query: query.into(),
+            keyspace: Some("ks".into()),
+            pk_indexes: vec![],
+            result_metadata: ArcSwap::from_pointee(PreparedResultMetadata {
+                id: None,
+                metadata: empty_rows_metadata(),
+            }),
+        })
+    }
+
+    #[test]
+    fn should_evict_least_recently_used() {
+        let cache = PreparedStatementCache::new(2);
+
+        let first = cache.entry(None, "first");
+        let second = cache.entry(Some("ks"), "second");
+
+        assert!(Arc::ptr_eq(&first, &cache.entry(None, "first")));
+        assert!(!Arc::ptr_eq(&second, &cache.entry(None, "second")));
+
+        // "second" without keyspace evicted "second" with keyspace, as the least recently used
+        assert!(Arc::ptr_eq(&first, &cache.entry(None, "first")));
+        assert!(!Arc::ptr_eq(&second, &cache.entry(Some("ks"), "second")));
+
+        assert_eq!(cache.state.lock().unwrap().entries.len(), 2);
+        assert_eq!(cache.state.lock().unwrap().usage.len(), 2);
+    }
+
+    #[test]
+    fn should_not_cache_without_capacity() {
+        let cache = PreparedStatementCache::new(0);
+
+        let first = cache.entry(None, "first");
+        assert!(!Arc::ptr_eq(&first, &cache.entry(None, "first")));
+        assert!(cache.state.lock().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn should_register_prepared_queries() {
+        let cache = PreparedStatementCache::new(1);
+        let id = CBytesShort::new(vec![1, 2, 3]);
+
+        cache.register(Some("ks".into()), prepared_query(&id, "query"));
+
+        let statement = StatementKey {
+            keyspace: Some("ks".into()),
+            query: "query".into(),
+        };
+
+        assert_eq!(cache.statement_by_id(&id), Some(statement.clone()));
+        assert_eq!(cache.statement_by_id(&CBytesShort::new(vec![4])), None);
+        assert_eq!(cache.prepared_statements(), vec![statement]);
+    }
+
+    #[test]
+    fn should_cache_registered_statements() {
+        let cache = PreparedStatementCache::new(1);
+        let first_id = CBytesShort::new(vec![1]);
+        let second_id = CBytesShort::new(vec![2]);
+
+        cache.register(None, prepared_query(&first_id, "query"));
+        assert_eq!(*cache.entry(None, "query").get().unwrap().id(), first_id);
+
+        // already cached statements are not replaced
+        cache.register(None, prepared_query(&second_id, "query"));
+        assert_eq!(*cache.entry(None, "query").get().unwrap().id(), first_id);
+        assert!(cache.statement_by_id(&second_id).is_some());
+    }
+
+    #[test]
+    fn should_forget_ids_of_evicted_statements() {
+        let cache = PreparedStatementCache::new(1);
+        let first_id = CBytesShort::new(vec![1]);
+        let second_id = CBytesShort::new(vec![2]);
+
+        cache.register(None, prepared_query(&first_id, "first"));
+        cache.register(None, prepared_query(&second_id, "second"));
+
+        assert_eq!(cache.statement_by_id(&first_id), None);
+        assert!(cache.statement_by_id(&second_id).is_some());
+        assert_eq!(cache.state.lock().unwrap().keys_by_id.len(), 1);
+
+        // a statement waiting to be prepared evicts prepared ones too
+        cache.entry(None, "third");
+        assert_eq!(cache.statement_by_id(&second_id), None);
+        assert!(cache.state.lock().unwrap().keys_by_id.is_empty());
+        assert!(cache.prepared_statements().is_empty());
+    }
+
+    #[test]
+    fn should_not_register_without_capacity() {
+        let cache = PreparedStatementCache::new(0);
+        let id = CBytesShort::new(vec![1]);
+
+        cache.register(None, prepared_query(&id, "query"));
+        assert_eq!(cache.statement_by_id(&id), None);
+    }
+
+    #[test]
+    fn should_replace_statement_reprepared_with_new_id() {
+        let cache = PreparedStatementCache::new(1);
+        let statement = StatementKey {
+            keyspace: Some("ks".into()),
+            query: "query".into(),
+        };
+
+        let old_id = CBytesShort::new(vec![1]);
+        cache.register(Some("ks".into()), prepared_query(&old_id, "query"));
+        let cell = cache.entry(Some("ks"), "query");
+
+        let new_id = CBytesShort::new(vec![2]);
+        cache.update_reprepared(
+            &old_id,
+            &statement,
+            &BodyResResultPrepared {
+                id: new_id.clone(),
+                result_metadata_id: Some(CBytesShort::new(vec![3])),
+                metadata: PreparedMetadata {
+                    pk_indexes: vec![0],
+                    global_table_spec: None,
+                    col_specs: vec![],
+                },
+                result_metadata: empty_rows_metadata(),
+            },
+        );
+
+        // both ids lead to the statement
+        assert_eq!(cache.statement_by_id(&old_id), Some(statement.clone()));
+        assert_eq!(cache.statement_by_id(&new_id), Some(statement));
+
+        let reprepared = cache.entry(Some("ks"), "query");
+        assert!(!Arc::ptr_eq(&cell, &reprepared));
+
+        let reprepared = reprepared.get().unwrap();
+        assert_eq!(*reprepared.id(), new_id);
+        assert_eq!(reprepared.pk_indexes, vec![0]);
+        assert_eq!(
+            reprepared.result_metadata_id(),
+            Some(CBytesShort::new(vec![3]))
+        );
+    }
+}
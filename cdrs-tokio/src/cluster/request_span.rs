@@ -0,0 +1,140 @@
+//! Tracing spans for requests, with fields following OpenTelemetry database semantic conventions.
+
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::error::Error;
+use cassandra_protocol::frame::Opcode;
+use std::net::SocketAddr;
+use tracing::{field, info_span, Span};
+use uuid::Uuid;
+
+const DB_SYSTEM: &str = "cassandra";
+
+/// Creates a span covering a whole request, including retries and speculative executions.
+pub(crate) fn request_span(
+    opcode: Opcode,
+    keyspace: Option<&str>,
+    consistency: Option<Consistency>,
+    is_idempotent: bool,
+) -> Span {
+    let operation = operation_name(opcode);
+    let span = info_span!(
+        "cassandra.request",
+        otel.name = operation,
+        otel.kind = "client",
+        otel.status_code = field::Empty,
+        db.system = DB_SYSTEM,
+        db.operation = operation,
+        db.name = field::Empty,
+        db.cassandra.consistency_level = field::Empty,
+        db.cassandra.idempotence = is_idempotent,
+        db.cassandra.speculative_execution_count = field::Empty,
+    );
+
+    if let Some(keyspace) = keyspace {
+        span.record("db.name", keyspace);
+    }
+
+    if let Some(consistency) = consistency {
+        span.record(
+            "db.cassandra.consistency_level",
+            consistency_level_name(consistency),
+        );
+    }
+
+    span
+}
+
+/// Creates a span covering a single execution of a request with speculative executions enabled.
+/// The initial execution is numbered 0, with additional ones following.
+pub(crate) fn speculative_execution_span(execution: usize) -> Span {
+    info_span!(
+        "cassandra.speculative_execution",
+        db.cassandra.speculative_execution = execution
+    )
+}
+
+/// Creates a span covering a single attempt of sending a request to a node.
+pub(crate) fn attempt_span(
+    broadcast_rpc_address: SocketAddr,
+    host_id: Option<Uuid>,
+    datacenter: &str,
+) -> Span {
+    let span = info_span!(
+        "cassandra.attempt",
+        otel.status_code = field::Empty,
+        server.address = %broadcast_rpc_address.ip(),
+        server.port = broadcast_rpc_address.port(),
+        db.cassandra.coordinator.id = field::Empty,
+        db.cassandra.coordinator.dc = datacenter,
+    );
+
+    if let Some(host_id) = host_id {
+        span.record("db.cassandra.coordinator.id", field::display(host_id));
+    }
+
+    span
+}
+
+/// Creates a span covering a retry decision after a failed attempt.
+pub(crate) fn retry_decision_span(attempt_span: &Span, error: &Error) -> Span {
+    info_span!(
+        parent: attempt_span,
+        "cassandra.retry_decision",
+        error.message = %error,
+        db.cassandra.retry_decision = field::Empty,
+    )
+}
+
+/// Marks given span as failed.
+#[inline]
+pub(crate) fn record_error(span: &Span) {
+    span.record("otel.status_code", "ERROR");
+}
+
+fn operation_name(opcode: Opcode) -> &'static str {
+    match opcode {
+        Opcode::Query => "QUERY",
+        Opcode::Execute => "EXECUTE",
+        Opcode::Batch => "BATCH",
+        Opcode::Prepare => "PREPARE",
+        Opcode::Register => "REGISTER",
+        Opcode::Options => "OPTIONS",
+        Opcode::Startup => "STARTUP",
+        Opcode::AuthResponse => "AUTH_RESPONSE",
+        _ => "UNKNOWN",
+    }
+}
+
+fn consistency_level_name(consistency: Consistency) -> &'static str {
+    match consistency {
+        Consistency::Any => "any",
+        Consistency::One => "one",
+        Consistency::Two => "two",
+        Consistency::Three => "three",
+        Consistency::Quorum => "quorum",
+        Consistency::All => "all",
+        Consistency::LocalQuorum => "local_quorum",
+        Consistency::EachQuorum => "each_quorum",
+        Consistency::Serial => "serial",
+        Consistency::LocalSerial => "local_serial",
+        Consistency::LocalOne => "local_one",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_name_consistency_levels_in_snake_case() {
+        assert_eq!(
+            consistency_level_name(Consistency::LocalQuorum),
+            "local_quorum"
+        );
+        assert_eq!(
+            consistency_level_name(Consistency::EachQuorum),
+            "each_quorum"
+        );
+        assert_eq!(consistency_level_name(Consistency::One), "one");
+    }
+}
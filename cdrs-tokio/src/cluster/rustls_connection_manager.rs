@@ -5,11 +5,14 @@ use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::time::sleep;
 
-use crate::cluster::connection_manager::{startup, ConnectionManager};
-use crate::cluster::KeyspaceHolder;
+use crate::cluster::connection_manager::{
+    fetch_supported_options, negotiate_version, select_compression, startup, ConnectionManager,
+};
+use crate::cluster::{KeyspaceHolder, VersionHolder};
 use crate::future::BoxFuture;
+use crate::metrics::MetricsSink;
 use crate::retry::ReconnectionPolicy;
-use crate::transport::TransportRustls;
+use crate::transport::{HeartbeatConfig, TransportRustls};
 use cassandra_protocol::authenticators::SaslAuthenticatorProvider;
 use cassandra_protocol::compression::Compression;
 use cassandra_protocol::error::{Error, Result};
@@ -24,7 +27,10 @@ pub struct RustlsConnectionManager {
     compression: Compression,
     buffer_size: usize,
     tcp_nodelay: bool,
-    version: Version,
+    max_in_flight_requests: usize,
+    heartbeat: Option<HeartbeatConfig>,
+    metrics: Arc<dyn MetricsSink + Send + Sync>,
+    version_holder: Arc<VersionHolder>,
 }
 
 impl ConnectionManager<TransportRustls> for RustlsConnectionManager {
@@ -65,7 +71,10 @@ impl RustlsConnectionManager {
         compression: Compression,
         buffer_size: usize,
         tcp_nodelay: bool,
-        version: Version,
+        max_in_flight_requests: usize,
+        heartbeat: Option<HeartbeatConfig>,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
+        version_holder: Arc<VersionHolder>,
     ) -> Self {
         RustlsConnectionManager {
             dns_name,
@@ -76,7 +85,10 @@ impl RustlsConnectionManager {
             compression,
             buffer_size,
             tcp_nodelay,
-            version,
+            max_in_flight_requests,
+            heartbeat,
+            metrics,
+            version_holder,
         }
     }
 
@@ -86,28 +98,55 @@ impl RustlsConnectionManager {
         error_handler: Option<Sender<Error>>,
         addr: SocketAddr,
     ) -> Result<TransportRustls> {
-        let transport = TransportRustls::new(
+        negotiate_version(&self.version_holder, self.compression, |version| {
+            self.establish_connection_with_version(
+                event_handler.clone(),
+                error_handler.clone(),
+                addr,
+                version,
+            )
+        })
+        .await
+    }
+
+    async fn establish_connection_with_version(
+        &self,
+        event_handler: Option<Sender<Frame>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+        version: Version,
+    ) -> Result<TransportRustls> {
+        let mut transport = TransportRustls::new(
             addr,
             self.dns_name.clone(),
             self.config.clone(),
             self.keyspace_holder.clone(),
             event_handler,
             error_handler,
-            self.compression,
+            Compression::None,
             self.buffer_size,
             self.tcp_nodelay,
+            self.max_in_flight_requests,
+            self.heartbeat,
+            self.metrics.clone(),
         )
         .await?;
 
+        let supported_options = fetch_supported_options(&transport, version).await?;
+        let compression = select_compression(self.compression, &supported_options, version);
+        transport.set_compression(compression);
+
         startup(
             &transport,
             self.authenticator_provider.deref(),
             self.keyspace_holder.deref(),
-            self.compression,
-            self.version,
+            compression,
+            version,
         )
         .await?;
 
+        transport.set_supported_options(supported_options);
+
         Ok(transport)
     }
 }
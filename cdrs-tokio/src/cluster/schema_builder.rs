@@ -0,0 +1,1012 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::frame_result::{
+    CTuple, CUdt, ColType, ColTypeOption, ColTypeOptionValue,
+};
+use cassandra_protocol::types::list::List;
+use cassandra_protocol::types::map::Map;
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::{AsRustType, IntoRustByName};
+use fxhash::FxHashMap;
+use itertools::Itertools;
+use tracing::*;
+
+use crate::cluster::topology::{
+    AggregateMetadata, ClusteringOrder, ColumnKind, ColumnMetadata, FunctionMetadata, IndexKind,
+    IndexMetadata, KeyspaceMetadata, TableMetadata, UserTypeMetadata, ViewMetadata,
+};
+
+const NATIVE_TYPES: [(&str, ColType); 21] = [
+    ("ascii", ColType::Ascii),
+    ("bigint", ColType::Bigint),
+    ("blob", ColType::Blob),
+    ("boolean", ColType::Boolean),
+    ("counter", ColType::Counter),
+    ("decimal", ColType::Decimal),
+    ("double", ColType::Double),
+    ("float", ColType::Float),
+    ("int", ColType::Int),
+    ("timestamp", ColType::Timestamp),
+    ("uuid", ColType::Uuid),
+    ("varchar", ColType::Varchar),
+    ("text", ColType::Varchar),
+    ("varint", ColType::Varint),
+    ("timeuuid", ColType::Timeuuid),
+    ("inet", ColType::Inet),
+    ("date", ColType::Date),
+    ("time", ColType::Time),
+    ("smallint", ColType::Smallint),
+    ("tinyint", ColType::Tinyint),
+    ("duration", ColType::Duration),
+];
+
+/// Rows from `system_schema` tables, describing schema elements of one or more keyspaces.
+#[derive(Default)]
+pub(crate) struct SchemaRows {
+    pub tables: Vec<Row>,
+    pub columns: Vec<Row>,
+    pub indexes: Vec<Row>,
+    pub views: Vec<Row>,
+    pub types: Vec<Row>,
+    pub functions: Vec<Row>,
+    pub aggregates: Vec<Row>,
+}
+
+/// Adds schema elements described by given rows to their keyspaces. Elements of unknown keyspaces
+/// are ignored, as are elements which cannot be parsed - a single unsupported element should not
+/// make the whole metadata unavailable.
+pub(crate) fn add_schema(keyspaces: &mut FxHashMap<String, KeyspaceMetadata>, rows: &SchemaRows) {
+    for (keyspace_name, types) in group_by_keyspace(&rows.types) {
+        if let Some(keyspace) = keyspaces.get_mut(&keyspace_name) {
+            keyspace
+                .user_types
+                .extend(build_user_types(&keyspace_name, &types));
+        }
+    }
+
+    let columns = group_by_element(&rows.columns, "table_name");
+    let indexes = group_by_element(&rows.indexes, "table_name");
+
+    for row in &rows.tables {
+        let result = element_key(row, "table_name").and_then(|key| {
+            let keyspace = match keyspaces.get_mut(&key.0) {
+                Some(keyspace) => keyspace,
+                None => return Ok(()),
+            };
+
+            let table = build_table(
+                &key.0,
+                row,
+                columns.get(&key).map(Vec::as_slice).unwrap_or_default(),
+                indexes.get(&key).map(Vec::as_slice).unwrap_or_default(),
+                &keyspace.user_types,
+            )?;
+
+            keyspace.tables.insert(key.1, table);
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            warn!(%error, "Error building table metadata - skipping.");
+        }
+    }
+
+    for row in &rows.views {
+        let result = element_key(row, "view_name").and_then(|key| {
+            let keyspace = match keyspaces.get_mut(&key.0) {
+                Some(keyspace) => keyspace,
+                None => return Ok(()),
+            };
+
+            let view = build_view(
+                &key.0,
+                row,
+                columns.get(&key).map(Vec::as_slice).unwrap_or_default(),
+                &keyspace.user_types,
+            )?;
+
+            keyspace.views.insert(key.1, view);
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            warn!(%error, "Error building view metadata - skipping.");
+        }
+    }
+
+    for row in &rows.functions {
+        let result = keyspace_name(row).and_then(|keyspace_name| {
+            if let Some(keyspace) = keyspaces.get_mut(&keyspace_name) {
+                let function = build_function(&keyspace_name, row, &keyspace.user_types)?;
+                keyspace.functions.push(function);
+            }
+
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            warn!(%error, "Error building function metadata - skipping.");
+        }
+    }
+
+    for row in &rows.aggregates {
+        let result = keyspace_name(row).and_then(|keyspace_name| {
+            if let Some(keyspace) = keyspaces.get_mut(&keyspace_name) {
+                let aggregate = build_aggregate(&keyspace_name, row, &keyspace.user_types)?;
+                keyspace.aggregates.push(aggregate);
+            }
+
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            warn!(%error, "Error building aggregate metadata - skipping.");
+        }
+    }
+}
+
+fn keyspace_name(row: &Row) -> Result<String> {
+    row.get_r_by_name("keyspace_name")
+}
+
+fn element_key(row: &Row, name_column: &str) -> Result<(String, String)> {
+    Ok((
+        row.get_r_by_name("keyspace_name")?,
+        row.get_r_by_name(name_column)?,
+    ))
+}
+
+fn group_by_keyspace(rows: &[Row]) -> FxHashMap<String, Vec<&Row>> {
+    let mut result: FxHashMap<String, Vec<&Row>> = FxHashMap::default();
+    for row in rows {
+        match keyspace_name(row) {
+            Ok(keyspace_name) => result.entry(keyspace_name).or_default().push(row),
+            Err(error) => warn!(%error, "Error reading schema row - skipping."),
+        }
+    }
+
+    result
+}
+
+fn group_by_element<'a>(
+    rows: &'a [Row],
+    name_column: &str,
+) -> FxHashMap<(String, String), Vec<&'a Row>> {
+    let mut result: FxHashMap<(String, String), Vec<&Row>> = FxHashMap::default();
+    for row in rows {
+        match element_key(row, name_column) {
+            Ok(key) => result.entry(key).or_default().push(row),
+            Err(error) => warn!(%error, "Error reading schema row - skipping."),
+        }
+    }
+
+    result
+}
+
+/// User type name, field names and field types.
+type UserTypeDefinition = (String, Vec<String>, Vec<String>);
+
+fn build_user_type_definition(row: &Row) -> Result<UserTypeDefinition> {
+    let field_names: List = row.get_r_by_name("field_names")?;
+    let field_types: List = row.get_r_by_name("field_types")?;
+
+    Ok((
+        row.get_r_by_name("type_name")?,
+        field_names.as_r_type()?,
+        field_types.as_r_type()?,
+    ))
+}
+
+fn build_user_types(keyspace: &str, rows: &[&Row]) -> FxHashMap<String, UserTypeMetadata> {
+    let definitions = rows
+        .iter()
+        .filter_map(|row| {
+            build_user_type_definition(row)
+                .map_err(|error| warn!(%error, %keyspace, "Error reading user type - skipping."))
+                .ok()
+        })
+        .collect_vec();
+
+    let (user_types, error) = resolve_user_types(keyspace, definitions);
+    if let Some(error) = error {
+        warn!(%error, %keyspace, "Error building user types - skipping unresolved ones.");
+    }
+
+    user_types
+}
+
+/// Builds user types from their definitions. Types can reference other types, so they are
+/// resolved in dependency order. Returns all types which could be resolved, along with the last
+/// error for the remaining ones.
+fn resolve_user_types(
+    keyspace: &str,
+    mut definitions: Vec<UserTypeDefinition>,
+) -> (FxHashMap<String, UserTypeMetadata>, Option<Error>) {
+    let mut user_types = FxHashMap::default();
+
+    while !definitions.is_empty() {
+        let pending_count = definitions.len();
+        let mut last_error = None;
+
+        definitions.retain(|(name, field_names, field_types)| {
+            let field_types: Result<Vec<_>> = field_types
+                .iter()
+                .map(|field_type| parse_cql_type(field_type, keyspace, &user_types))
+                .try_collect();
+
+            match field_types {
+                Ok(field_types) => {
+                    user_types.insert(
+                        name.clone(),
+                        UserTypeMetadata {
+                            fields: field_names.iter().cloned().zip(field_types).collect(),
+                        },
+                    );
+
+                    false
+                }
+                Err(error) => {
+                    last_error = Some(error);
+                    true
+                }
+            }
+        });
+
+        if definitions.len() == pending_count {
+            return (user_types, last_error);
+        }
+    }
+
+    (user_types, None)
+}
+
+struct ColumnsMetadata {
+    columns: FxHashMap<String, ColumnMetadata>,
+    partition_key: Vec<String>,
+    clustering_key: Vec<(String, ClusteringOrder)>,
+}
+
+fn build_columns(
+    keyspace: &str,
+    rows: &[&Row],
+    user_types: &FxHashMap<String, UserTypeMetadata>,
+) -> Result<ColumnsMetadata> {
+    let mut columns = FxHashMap::default();
+    let mut partition_key = vec![];
+    let mut clustering_key = vec![];
+
+    for row in rows {
+        let name: String = row.get_r_by_name("column_name")?;
+        let kind: String = row.get_r_by_name("kind")?;
+        let position: i32 = row.get_r_by_name("position")?;
+        let col_type: String = row.get_r_by_name("type")?;
+
+        let kind = match kind.as_str() {
+            "partition_key" => {
+                partition_key.push((position, name.clone()));
+                ColumnKind::PartitionKey
+            }
+            "clustering" => {
+                let clustering_order: String = row.get_r_by_name("clustering_order")?;
+                let clustering_order = if clustering_order.eq_ignore_ascii_case("desc") {
+                    ClusteringOrder::Descending
+                } else {
+                    ClusteringOrder::Ascending
+                };
+
+                clustering_key.push((position, (name.clone(), clustering_order)));
+                ColumnKind::Clustering
+            }
+            "static" => ColumnKind::Static,
+            _ => ColumnKind::Regular,
+        };
+
+        columns.insert(
+            name,
+            ColumnMetadata {
+                kind,
+                col_type: parse_cql_type(&col_type, keyspace, user_types)?,
+            },
+        );
+    }
+
+    partition_key.sort_unstable_by_key(|(position, _)| *position);
+    clustering_key.sort_unstable_by_key(|(position, _)| *position);
+
+    Ok(ColumnsMetadata {
+        columns,
+        partition_key: partition_key.into_iter().map(|(_, name)| name).collect(),
+        clustering_key: clustering_key
+            .into_iter()
+            .map(|(_, column)| column)
+            .collect(),
+    })
+}
+
+fn build_table(
+    keyspace: &str,
+    row: &Row,
+    columns: &[&Row],
+    indexes: &[&Row],
+    user_types: &FxHashMap<String, UserTypeMetadata>,
+) -> Result<TableMetadata> {
+    let columns = build_columns(keyspace, columns, user_types)?;
+    let indexes = indexes
+        .iter()
+        .map(|row| {
+            let name: String = row.get_r_by_name("index_name")?;
+            build_index(row).map(|index| (name, index))
+        })
+        .try_collect()?;
+
+    Ok(TableMetadata {
+        id: row.get_by_name("id")?,
+        columns: columns.columns,
+        partition_key: columns.partition_key,
+        clustering_key: columns.clustering_key,
+        indexes,
+    })
+}
+
+fn build_index(row: &Row) -> Result<IndexMetadata> {
+    let kind: String = row.get_r_by_name("kind")?;
+    let kind = match kind.as_str() {
+        "KEYS" => IndexKind::Keys,
+        "CUSTOM" => IndexKind::Custom,
+        "COMPOSITES" => IndexKind::Composites,
+        _ => return Err(format!("Unknown index kind: {}", kind).into()),
+    };
+
+    let options: Option<Map> = row.get_by_name("options")?;
+
+    Ok(IndexMetadata {
+        kind,
+        options: options
+            .map(|options| options.as_r_type())
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+fn build_view(
+    keyspace: &str,
+    row: &Row,
+    columns: &[&Row],
+    user_types: &FxHashMap<String, UserTypeMetadata>,
+) -> Result<ViewMetadata> {
+    let columns = build_columns(keyspace, columns, user_types)?;
+
+    Ok(ViewMetadata {
+        id: row.get_by_name("id")?,
+        base_table_name: row.get_r_by_name("base_table_name")?,
+        include_all_columns: row.get_by_name("include_all_columns")?.unwrap_or_default(),
+        where_clause: row.get_by_name("where_clause")?.unwrap_or_default(),
+        columns: columns.columns,
+        partition_key: columns.partition_key,
+        clustering_key: columns.clustering_key,
+    })
+}
+
+fn build_function(
+    keyspace: &str,
+    row: &Row,
+    user_types: &FxHashMap<String, UserTypeMetadata>,
+) -> Result<FunctionMetadata> {
+    let argument_names: List = row.get_r_by_name("argument_names")?;
+    let return_type: String = row.get_r_by_name("return_type")?;
+
+    Ok(FunctionMetadata {
+        name: row.get_r_by_name("function_name")?,
+        argument_names: argument_names.as_r_type()?,
+        argument_types: build_argument_types(keyspace, row, user_types)?,
+        return_type: parse_cql_type(&return_type, keyspace, user_types)?,
+        language: row.get_r_by_name("language")?,
+        body: row.get_r_by_name("body")?,
+        called_on_null_input: row.get_by_name("called_on_null_input")?.unwrap_or_default(),
+    })
+}
+
+fn build_aggregate(
+    keyspace: &str,
+    row: &Row,
+    user_types: &FxHashMap<String, UserTypeMetadata>,
+) -> Result<AggregateMetadata> {
+    let return_type: String = row.get_r_by_name("return_type")?;
+    let state_type: String = row.get_r_by_name("state_type")?;
+
+    Ok(AggregateMetadata {
+        name: row.get_r_by_name("aggregate_name")?,
+        argument_types: build_argument_types(keyspace, row, user_types)?,
+        return_type: parse_cql_type(&return_type, keyspace, user_types)?,
+        state_func: row.get_r_by_name("state_func")?,
+        state_type: parse_cql_type(&state_type, keyspace, user_types)?,
+        final_func: row.get_by_name("final_func")?,
+        initcond: row.get_by_name("initcond")?,
+    })
+}
+
+fn build_argument_types(
+    keyspace: &str,
+    row: &Row,
+    user_types: &FxHashMap<String, UserTypeMetadata>,
+) -> Result<Vec<ColTypeOption>> {
+    let argument_types: List = row.get_r_by_name("argument_types")?;
+    let argument_types: Vec<String> = argument_types.as_r_type()?;
+
+    argument_types
+        .iter()
+        .map(|argument_type| parse_cql_type(argument_type, keyspace, user_types))
+        .try_collect()
+}
+
+/// Parses a CQL type, as stored in `system_schema` tables, e.g. `frozen<map<text, address>>`.
+/// User types are looked up in given keyspace types.
+pub(crate) fn parse_cql_type(
+    cql_type: &str,
+    keyspace: &str,
+    user_types: &FxHashMap<String, UserTypeMetadata>,
+) -> Result<ColTypeOption> {
+    let cql_type = cql_type.trim();
+    let invalid_type = || Error::General(format!("Invalid CQL type: {}", cql_type));
+
+    if let Some(class_name) = cql_type
+        .strip_prefix('\'')
+        .and_then(|class_name| class_name.strip_suffix('\''))
+    {
+        return Ok(ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CString(class_name.to_string())),
+        });
+    }
+
+    let (name, parameters) = match cql_type.find('<') {
+        Some(start) => {
+            let parameters = cql_type[start + 1..]
+                .strip_suffix('>')
+                .ok_or_else(invalid_type)?;
+
+            (
+                cql_type[..start].trim(),
+                Some(split_type_parameters(parameters)),
+            )
+        }
+        None => (cql_type, None),
+    };
+
+    let parse = |cql_type: &str| parse_cql_type(cql_type, keyspace, user_types);
+
+    match (name.to_lowercase().as_str(), parameters.as_deref()) {
+        ("frozen", Some([inner])) => parse(inner),
+        ("list", Some([element])) => Ok(ColTypeOption {
+            id: ColType::List,
+            value: Some(ColTypeOptionValue::CList(Box::new(parse(element)?))),
+        }),
+        ("set", Some([element])) => Ok(ColTypeOption {
+            id: ColType::Set,
+            value: Some(ColTypeOptionValue::CSet(Box::new(parse(element)?))),
+        }),
+        ("map", Some([key, value])) => Ok(ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap(
+                Box::new(parse(key)?),
+                Box::new(parse(value)?),
+            )),
+        }),
+        ("tuple", Some(types)) => Ok(ColTypeOption {
+            id: ColType::Tuple,
+            value: Some(ColTypeOptionValue::TupleType(CTuple {
+                types: types.iter().map(|cql_type| parse(cql_type)).try_collect()?,
+            })),
+        }),
+        ("vector", Some([element, dimensions])) => Ok(ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CVector(
+                Box::new(parse(element)?),
+                dimensions.parse().map_err(|_| invalid_type())?,
+            )),
+        }),
+        (_, Some(_)) => Err(invalid_type()),
+        (native_name, None) => {
+            if let Some((_, id)) = NATIVE_TYPES.iter().find(|(name, _)| *name == native_name) {
+                return Ok(ColTypeOption {
+                    id: *id,
+                    value: None,
+                });
+            }
+
+            let name = name
+                .strip_prefix('"')
+                .and_then(|name| name.strip_suffix('"'))
+                .unwrap_or(name);
+
+            user_types
+                .get(name)
+                .map(|user_type| ColTypeOption {
+                    id: ColType::Udt,
+                    value: Some(ColTypeOptionValue::UdtType(CUdt {
+                        ks: keyspace.into(),
+                        udt_name: name.into(),
+                        descriptions: user_type.fields.clone(),
+                    })),
+                })
+                .ok_or_else(|| format!("Unknown user type: {}.{}", keyspace, name).into())
+        }
+    }
+}
+
+// splits type parameters on top-level commas
+fn split_type_parameters(parameters: &str) -> Vec<&str> {
+    let mut result = vec![];
+    let mut depth = 0;
+    let mut is_quoted = false;
+    let mut start = 0;
+
+    for (index, c) in parameters.char_indices() {
+        match c {
+            '\'' | '"' => is_quoted = !is_quoted,
+            '<' if !is_quoted => depth += 1,
+            '>' if !is_quoted => depth -= 1,
+            ',' if !is_quoted && depth == 0 => {
+                result.push(parameters[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    result.push(parameters[start..].trim());
+    result
+}
+
+// builds a result with given system table rows - column types are taken from the first row
+#[cfg(test)]
+pub(crate) fn test_rows(
+    column_names: &[&str],
+    rows: &[Vec<TestValue>],
+) -> cassandra_protocol::frame::frame_result::BodyResResultRows {
+    use cassandra_protocol::frame::frame_result::{
+        BodyResResultRows, ColSpec, RowsMetadata, RowsMetadataFlags, TableSpec,
+    };
+    use cassandra_protocol::frame::Serialize;
+    use cassandra_protocol::types::CBytes;
+    use std::io::Cursor;
+
+    let mut rows_content = vec![];
+    let mut cursor = Cursor::new(&mut rows_content);
+    for row in rows {
+        for value in row {
+            CBytes::from(value).serialize(&mut cursor);
+        }
+    }
+
+    let col_specs = match rows.first() {
+        Some(row) => column_names
+            .iter()
+            .zip(row)
+            .map(|(name, value)| ColSpec {
+                table_spec: None,
+                name: name.to_string(),
+                col_type: value.col_type(),
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    BodyResResultRows {
+        metadata: RowsMetadata {
+            flags: RowsMetadataFlags::GLOBAL_TABLE_SPACE,
+            columns_count: col_specs.len() as i32,
+            paging_state: None,
+            new_metadata_id: None,
+            global_table_spec: Some(TableSpec {
+                ks_name: "system_schema".into(),
+                table_name: "test".into(),
+            }),
+            col_specs,
+        },
+        rows_count: rows.len() as i32,
+        rows_content: rows_content.into(),
+    }
+}
+
+/// Value of a single column in [`test_rows`].
+#[cfg(test)]
+pub(crate) enum TestValue {
+    Text(&'static str),
+    Int(i32),
+    Boolean(bool),
+    Uuid(uuid::Uuid),
+    Timestamp(i64),
+    Inet(std::net::IpAddr),
+    TextMap(Vec<(&'static str, &'static str)>),
+    Null(ColType),
+}
+
+#[cfg(test)]
+impl TestValue {
+    fn col_type(&self) -> ColTypeOption {
+        let native = |id| ColTypeOption { id, value: None };
+        match self {
+            TestValue::Text(_) => native(ColType::Varchar),
+            TestValue::Null(id) => native(*id),
+            TestValue::Int(_) => native(ColType::Int),
+            TestValue::Boolean(_) => native(ColType::Boolean),
+            TestValue::Uuid(_) => native(ColType::Uuid),
+            TestValue::Timestamp(_) => native(ColType::Timestamp),
+            TestValue::Inet(_) => native(ColType::Inet),
+            TestValue::TextMap(_) => ColTypeOption {
+                id: ColType::Map,
+                value: Some(ColTypeOptionValue::CMap(
+                    Box::new(native(ColType::Varchar)),
+                    Box::new(native(ColType::Varchar)),
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+impl From<&TestValue> for cassandra_protocol::types::CBytes {
+    fn from(value: &TestValue) -> Self {
+        use cassandra_protocol::types::CBytes;
+
+        match value {
+            TestValue::Text(value) => CBytes::new(value.as_bytes().to_vec()),
+            TestValue::Int(value) => CBytes::new(value.to_be_bytes().to_vec()),
+            TestValue::Boolean(value) => CBytes::new(vec![*value as u8]),
+            TestValue::Uuid(value) => CBytes::new(value.as_bytes().to_vec()),
+            TestValue::Timestamp(value) => CBytes::new(value.to_be_bytes().to_vec()),
+            TestValue::Inet(std::net::IpAddr::V4(value)) => CBytes::new(value.octets().to_vec()),
+            TestValue::Inet(std::net::IpAddr::V6(value)) => CBytes::new(value.octets().to_vec()),
+            TestValue::TextMap(entries) => {
+                let mut bytes = (entries.len() as i32).to_be_bytes().to_vec();
+                for (key, value) in entries {
+                    for element in [key, value] {
+                        bytes.extend_from_slice(&(element.len() as i32).to_be_bytes());
+                        bytes.extend_from_slice(element.as_bytes());
+                    }
+                }
+
+                CBytes::new(bytes)
+            }
+            TestValue::Null(_) => CBytes::new_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn native(id: ColType) -> ColTypeOption {
+        ColTypeOption { id, value: None }
+    }
+
+    #[test]
+    fn should_parse_collection_types() {
+        let user_types = FxHashMap::default();
+
+        assert_eq!(
+            parse_cql_type("frozen<map<text, list<int>>>", "ks", &user_types).unwrap(),
+            ColTypeOption {
+                id: ColType::Map,
+                value: Some(ColTypeOptionValue::CMap(
+                    Box::new(native(ColType::Varchar)),
+                    Box::new(ColTypeOption {
+                        id: ColType::List,
+                        value: Some(ColTypeOptionValue::CList(Box::new(native(ColType::Int)))),
+                    }),
+                )),
+            }
+        );
+
+        assert_eq!(
+            parse_cql_type("tuple<bigint, frozen<set<uuid>>, text>", "ks", &user_types).unwrap(),
+            ColTypeOption {
+                id: ColType::Tuple,
+                value: Some(ColTypeOptionValue::TupleType(CTuple {
+                    types: vec![
+                        native(ColType::Bigint),
+                        ColTypeOption {
+                            id: ColType::Set,
+                            value: Some(ColTypeOptionValue::CSet(Box::new(native(ColType::Uuid)))),
+                        },
+                        native(ColType::Varchar),
+                    ],
+                })),
+            }
+        );
+
+        assert_eq!(
+            parse_cql_type("vector<float, 3>", "ks", &user_types).unwrap(),
+            ColTypeOption {
+                id: ColType::Custom,
+                value: Some(ColTypeOptionValue::CVector(
+                    Box::new(native(ColType::Float)),
+                    3
+                )),
+            }
+        );
+
+        assert!(parse_cql_type("map<text>", "ks", &user_types).is_err());
+        assert!(parse_cql_type("unknown", "ks", &user_types).is_err());
+    }
+
+    #[test]
+    fn should_resolve_user_types_in_dependency_order() {
+        let (user_types, error) = resolve_user_types(
+            "ks",
+            vec![
+                (
+                    "person".into(),
+                    vec!["name".into(), "address".into()],
+                    vec!["text".into(), "frozen<address>".into()],
+                ),
+                ("address".into(), vec!["street".into()], vec!["text".into()]),
+                ("broken".into(), vec!["x".into()], vec!["missing".into()]),
+            ],
+        );
+
+        assert!(error.is_some());
+        assert!(!user_types.contains_key("broken"));
+        assert_eq!(
+            user_types["person"].fields[1],
+            (
+                "address".to_string(),
+                ColTypeOption {
+                    id: ColType::Udt,
+                    value: Some(ColTypeOptionValue::UdtType(CUdt {
+                        ks: "ks".into(),
+                        udt_name: "address".into(),
+                        descriptions: vec![("street".into(), native(ColType::Varchar))],
+                    })),
+                }
+            )
+        );
+    }
+
+    const COLUMN_NAMES: [&str; 7] = [
+        "keyspace_name",
+        "table_name",
+        "column_name",
+        "kind",
+        "position",
+        "type",
+        "clustering_order",
+    ];
+
+    fn column(
+        table: &'static str,
+        name: &'static str,
+        kind: &'static str,
+        position: i32,
+        col_type: &'static str,
+        clustering_order: &'static str,
+    ) -> Vec<TestValue> {
+        vec![
+            TestValue::Text("ks"),
+            TestValue::Text(table),
+            TestValue::Text(name),
+            TestValue::Text(kind),
+            TestValue::Int(position),
+            TestValue::Text(col_type),
+            TestValue::Text(clustering_order),
+        ]
+    }
+
+    fn rows(column_names: &[&str], rows: &[Vec<TestValue>]) -> Vec<Row> {
+        Row::from_frame_body(test_rows(column_names, rows))
+    }
+
+    fn keyspaces() -> FxHashMap<String, KeyspaceMetadata> {
+        let mut keyspaces = FxHashMap::default();
+        keyspaces.insert(
+            "ks".to_string(),
+            KeyspaceMetadata::new(crate::cluster::topology::ReplicationStrategy::Other),
+        );
+        keyspaces
+    }
+
+    #[test]
+    fn should_build_table_with_ordered_keys_and_indexes() {
+        let id = uuid::Uuid::new_v4();
+        let mut keyspaces = keyspaces();
+
+        add_schema(
+            &mut keyspaces,
+            &SchemaRows {
+                tables: rows(
+                    &["keyspace_name", "table_name", "id"],
+                    &[vec![
+                        TestValue::Text("ks"),
+                        TestValue::Text("t"),
+                        TestValue::Uuid(id),
+                    ]],
+                ),
+                // columns in arbitrary order
+                columns: rows(
+                    &COLUMN_NAMES,
+                    &[
+                        column("t", "v", "regular", -1, "list<int>", "none"),
+                        column("t", "ck2", "clustering", 1, "int", "DESC"),
+                        column("t", "pk2", "partition_key", 1, "int", "none"),
+                        column("t", "s", "static", -1, "text", "none"),
+                        column("t", "ck1", "clustering", 0, "text", "asc"),
+                        column("t", "pk1", "partition_key", 0, "uuid", "none"),
+                    ],
+                ),
+                indexes: rows(
+                    &[
+                        "keyspace_name",
+                        "table_name",
+                        "index_name",
+                        "kind",
+                        "options",
+                    ],
+                    &[vec![
+                        TestValue::Text("ks"),
+                        TestValue::Text("t"),
+                        TestValue::Text("t_s_idx"),
+                        TestValue::Text("COMPOSITES"),
+                        TestValue::TextMap(vec![("target", "s")]),
+                    ]],
+                ),
+                ..Default::default()
+            },
+        );
+
+        let table = &keyspaces["ks"].tables["t"];
+        assert_eq!(table.id, Some(id));
+        assert_eq!(table.partition_key, vec!["pk1", "pk2"]);
+        assert_eq!(
+            table.clustering_key,
+            vec![
+                ("ck1".to_string(), ClusteringOrder::Ascending),
+                ("ck2".to_string(), ClusteringOrder::Descending),
+            ]
+        );
+
+        assert_eq!(table.columns.len(), 6);
+        assert_eq!(
+            table.columns["pk1"],
+            ColumnMetadata {
+                kind: ColumnKind::PartitionKey,
+                col_type: native(ColType::Uuid),
+            }
+        );
+        assert_eq!(table.columns["s"].kind, ColumnKind::Static);
+        assert_eq!(
+            table.columns["v"],
+            ColumnMetadata {
+                kind: ColumnKind::Regular,
+                col_type: ColTypeOption {
+                    id: ColType::List,
+                    value: Some(ColTypeOptionValue::CList(Box::new(native(ColType::Int)))),
+                },
+            }
+        );
+
+        assert_eq!(
+            table.indexes["t_s_idx"],
+            IndexMetadata {
+                kind: IndexKind::Composites,
+                options: vec![("target".to_string(), "s".to_string())]
+                    .into_iter()
+                    .collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn should_build_views() {
+        let mut keyspaces = keyspaces();
+
+        add_schema(
+            &mut keyspaces,
+            &SchemaRows {
+                columns: rows(
+                    &COLUMN_NAMES,
+                    &[
+                        column("t_by_v", "k", "clustering", 0, "int", "desc"),
+                        column("t_by_v", "v", "partition_key", 0, "text", "none"),
+                    ],
+                ),
+                views: rows(
+                    &[
+                        "keyspace_name",
+                        "view_name",
+                        "base_table_name",
+                        "include_all_columns",
+                        "where_clause",
+                        "id",
+                    ],
+                    &[vec![
+                        TestValue::Text("ks"),
+                        TestValue::Text("t_by_v"),
+                        TestValue::Text("t"),
+                        TestValue::Boolean(true),
+                        TestValue::Text("v IS NOT NULL AND k IS NOT NULL"),
+                        TestValue::Null(ColType::Uuid),
+                    ]],
+                ),
+                ..Default::default()
+            },
+        );
+
+        let keyspace = &keyspaces["ks"];
+        assert!(keyspace.tables.is_empty());
+        assert_eq!(
+            keyspace.views["t_by_v"],
+            ViewMetadata {
+                id: None,
+                base_table_name: "t".into(),
+                include_all_columns: true,
+                where_clause: "v IS NOT NULL AND k IS NOT NULL".into(),
+                columns: vec![
+                    (
+                        "k".to_string(),
+                        ColumnMetadata {
+                            kind: ColumnKind::Clustering,
+                            col_type: native(ColType::Int),
+                        },
+                    ),
+                    (
+                        "v".to_string(),
+                        ColumnMetadata {
+                            kind: ColumnKind::PartitionKey,
+                            col_type: native(ColType::Varchar),
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                partition_key: vec!["v".into()],
+                clustering_key: vec![("k".into(), ClusteringOrder::Descending)],
+            }
+        );
+    }
+
+    #[test]
+    fn should_skip_invalid_and_unknown_elements() {
+        let mut keyspaces = keyspaces();
+
+        add_schema(
+            &mut keyspaces,
+            &SchemaRows {
+                tables: rows(
+                    &["keyspace_name", "table_name", "id"],
+                    &[
+                        vec![
+                            TestValue::Text("ks"),
+                            TestValue::Text("valid"),
+                            TestValue::Null(ColType::Uuid),
+                        ],
+                        vec![
+                            TestValue::Text("ks"),
+                            TestValue::Text("invalid"),
+                            TestValue::Null(ColType::Uuid),
+                        ],
+                        vec![
+                            TestValue::Text("other_ks"),
+                            TestValue::Text("t"),
+                            TestValue::Null(ColType::Uuid),
+                        ],
+                    ],
+                ),
+                columns: rows(
+                    &COLUMN_NAMES,
+                    &[
+                        column("valid", "k", "partition_key", 0, "int", "none"),
+                        column("invalid", "k", "partition_key", 0, "missing_type", "none"),
+                    ],
+                ),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(keyspaces.len(), 1);
+
+        let tables = &keyspaces["ks"].tables;
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables["valid"].partition_key, vec!["k"]);
+    }
+}
@@ -1,9 +1,24 @@
 use cassandra_protocol::error;
-use cassandra_protocol::frame::Frame;
+use cassandra_protocol::frame::frame_batch::BatchQuerySubj;
+use cassandra_protocol::frame::frame_error::AdditionalErrorInfo;
+use cassandra_protocol::frame::frame_request::RequestBody;
+use cassandra_protocol::frame::frame_result::BodyResResultPrepared;
+use cassandra_protocol::frame::{Frame, FromCursor, Serialize, Version};
+use cassandra_protocol::query::utils::prepare_flags;
+use cassandra_protocol::query::{PreparedQuery, PreparedResultMetadata};
+use cassandra_protocol::types::CBytesShort;
+use std::borrow::Cow;
+use std::io::Cursor;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use tracing::*;
 
+use crate::cluster::prepared_statement_cache::{PreparedStatementCache, StatementKey};
+use crate::cluster::request_span::{attempt_span, record_error, retry_decision_span};
 use crate::cluster::topology::Node;
 use crate::cluster::ConnectionManager;
+use crate::metrics::{MetricsSink, NoopMetricsSink};
 use crate::retry::{QueryInfo, RetryDecision, RetrySession};
 use crate::transport::CdrsTransport;
 
@@ -11,34 +26,702 @@ use crate::transport::CdrsTransport;
 /// appropriate node, and retry policy for error handling. Returns `None` if no nodes were present
 /// in the query plan.
 pub async fn send_frame<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>(
+    query_plan: impl Iterator<Item = Arc<Node<T, CM>>>,
+    frame: &Frame,
+    is_idempotent: bool,
+    retry_session: Box<dyn RetrySession + Send + Sync>,
+) -> Option<error::Result<Frame>> {
+    send_frame_with_reprepare(
+        query_plan,
+        frame,
+        is_idempotent,
+        retry_session,
+        None,
+        None,
+        None,
+        &NoopMetricsSink,
+    )
+    .await
+}
+
+/// Same as [`send_frame`], but additionally re-prepares statements reported as unprepared on the
+/// node which reported them, and sends the frame again. Statements are looked up in the given
+/// cache or, for EXECUTE frames, taken from the executed statement, which also gets its result
+/// metadata updated. Waiting for a response from a single node can be limited by a timeout, which
+/// results in [`error::Error::Timeout`]. Attempts are reported to given metrics sink.
+pub(crate) async fn send_frame_with_reprepare<
+    T: CdrsTransport + 'static,
+    CM: ConnectionManager<T> + 'static,
+>(
     query_plan: impl Iterator<Item = Arc<Node<T, CM>>>,
     frame: &Frame,
     is_idempotent: bool,
     mut retry_session: Box<dyn RetrySession + Send + Sync>,
+    prepared_statements: Option<&PreparedStatementCache>,
+    executed_statement: Option<&PreparedQuery>,
+    request_timeout: Option<Duration>,
+    metrics: &(dyn MetricsSink + Send + Sync),
 ) -> Option<error::Result<Frame>> {
+    // gets rewritten when a re-prepared statement gets a new id
+    let mut frame = Cow::Borrowed(frame);
+
     'next_node: for node in query_plan {
+        let broadcast_rpc_address = node.broadcast_rpc_address();
+
+        // a batch can contain multiple unprepared statements, but each should be re-prepared only
+        // once to avoid looping forever
+        let mut reprepared_ids = vec![];
+
         loop {
-            let transport = node.persistent_connection().await;
+            let attempt_span =
+                attempt_span(broadcast_rpc_address, node.host_id(), node.datacenter());
+
+            let transport = node
+                .persistent_connection()
+                .instrument(attempt_span.clone())
+                .await;
             match transport {
-                Ok(transport) => match transport.write_frame(frame).await {
-                    Ok(frame) => return Some(Ok(frame)),
-                    Err(error) => {
-                        let query_info = QueryInfo {
-                            error: &error,
-                            is_idempotent,
-                        };
-
-                        match retry_session.decide(query_info) {
-                            RetryDecision::RetrySameNode => continue,
-                            RetryDecision::RetryNextNode => continue 'next_node,
-                            RetryDecision::DontRetry => return Some(Err(error)),
+                Ok(transport) => {
+                    let start = Instant::now();
+                    let result =
+                        write_frame_with_timeout(transport.as_ref(), &frame, request_timeout)
+                            .instrument(attempt_span.clone())
+                            .await;
+
+                    metrics.record_node_latency(broadcast_rpc_address, start.elapsed());
+
+                    match result {
+                        Ok(frame) => return Some(Ok(frame)),
+                        Err(error) => {
+                            record_error(&attempt_span);
+
+                            match &error {
+                                error::Error::Timeout(_) => {
+                                    metrics.record_timeout(broadcast_rpc_address)
+                                }
+                                error => metrics.record_error(broadcast_rpc_address, error.into()),
+                            }
+
+                            let error = match reprepare(
+                                transport.as_ref(),
+                                &mut frame,
+                                &error,
+                                prepared_statements,
+                                executed_statement,
+                                &mut reprepared_ids,
+                                request_timeout,
+                            )
+                            .instrument(attempt_span.clone())
+                            .await
+                            {
+                                Ok(true) => continue,
+                                Ok(false) => error,
+                                Err(error) => error,
+                            };
+
+                            let query_info = QueryInfo {
+                                error: &error,
+                                is_idempotent,
+                            };
+
+                            let retry_decision_span = retry_decision_span(&attempt_span, &error);
+                            let retry_decision =
+                                retry_decision_span.in_scope(|| retry_session.decide(query_info));
+
+                            retry_decision_span.record(
+                                "db.cassandra.retry_decision",
+                                field::display(retry_decision),
+                            );
+
+                            match retry_decision {
+                                RetryDecision::RetrySameNode => {
+                                    metrics.record_retry(broadcast_rpc_address);
+                                    continue;
+                                }
+                                RetryDecision::RetryNextNode => {
+                                    metrics.record_retry(broadcast_rpc_address);
+                                    continue 'next_node;
+                                }
+                                RetryDecision::DontRetry => return Some(Err(error)),
+                            }
                         }
                     }
-                },
-                Err(error) => return Some(Err(error)),
+                }
+                Err(error) => {
+                    record_error(&attempt_span);
+                    return Some(Err(error));
+                }
             }
         }
     }
 
     None
 }
+
+// Re-prepares the statement from an UNPREPARED error on given transport. Returns false if the
+// error is of a different kind or the statement cannot be re-prepared. If the statement got a new
+// id, the frame is rewritten to use it.
+async fn reprepare<T: CdrsTransport + ?Sized>(
+    transport: &T,
+    frame: &mut Cow<'_, Frame>,
+    error: &error::Error,
+    prepared_statements: Option<&PreparedStatementCache>,
+    executed_statement: Option<&PreparedQuery>,
+    reprepared_ids: &mut Vec<CBytesShort>,
+    request_timeout: Option<Duration>,
+) -> error::Result<bool> {
+    let id = match error {
+        error::Error::Server(error) => match &error.additional_info {
+            AdditionalErrorInfo::Unprepared(unprepared) => &unprepared.id,
+            _ => return Ok(false),
+        },
+        _ => return Ok(false),
+    };
+
+    if reprepared_ids.contains(id) {
+        return Ok(false);
+    }
+
+    let executed_statement = executed_statement.filter(|statement| *statement.id() == *id);
+    let statement = prepared_statements
+        .and_then(|statements| statements.statement_by_id(id))
+        .or_else(|| {
+            executed_statement.map(|statement| StatementKey {
+                keyspace: statement.keyspace.clone(),
+                query: statement.query.clone(),
+            })
+        });
+
+    let statement = match statement {
+        Some(statement) => statement,
+        None => {
+            debug!(?id, "Cannot re-prepare unknown statement.");
+            return Ok(false);
+        }
+    };
+
+    debug!(query = %statement.query, node = %transport.address(), "Re-preparing statement.");
+
+    let prepare_frame = Frame::new_req_prepare(
+        statement.query.clone(),
+        statement.keyspace.clone(),
+        prepare_flags(false, false),
+        frame.version,
+    );
+    let prepared = write_frame_with_timeout(transport, &prepare_frame, request_timeout)
+        .await
+        .and_then(|response| response.response_body())
+        .and_then(|body| {
+            body.into_prepared()
+                .ok_or_else(|| "CDRS BUG: cannot convert frame into prepared".into())
+        })?;
+
+    if let Some(prepared_statements) = prepared_statements {
+        prepared_statements.update_reprepared(id, &statement, &prepared);
+    }
+
+    if let Some(executed_statement) = executed_statement {
+        if prepared.id != *id {
+            executed_statement.update_id(prepared.id.clone());
+        }
+
+        executed_statement.update_result_metadata(PreparedResultMetadata {
+            id: prepared.result_metadata_id.clone(),
+            metadata: prepared.result_metadata.clone(),
+        });
+    }
+
+    // the id should remain the same as the old one, except when schema changed in the meantime
+    // see: https://issues.apache.org/jira/browse/CASSANDRA-10786
+    if prepared.id != *id {
+        warn!(query = %statement.query, "Re-preparing an unprepared statement resulted in a different id - probably schema changed on the server.");
+
+        *frame = Cow::Owned(replace_statement_id(frame, id, &prepared)?);
+        reprepared_ids.push(prepared.id);
+    }
+
+    reprepared_ids.push(id.clone());
+    Ok(true)
+}
+
+// Rewrites an EXECUTE or BATCH frame to use a new id of a re-prepared statement.
+fn replace_statement_id(
+    frame: &Frame,
+    id: &CBytesShort,
+    prepared: &BodyResResultPrepared,
+) -> error::Result<Frame> {
+    let body = match frame.request_body()? {
+        RequestBody::Execute(_) => {
+            // only the ids at the beginning need replacing - the rest is copied as-is
+            let mut cursor = Cursor::new(frame.body.as_ref());
+            CBytesShort::from_cursor(&mut cursor)?;
+            if frame.version >= Version::V5 {
+                CBytesShort::from_cursor(&mut cursor)?;
+            }
+
+            let mut body = prepared.id.serialize_to_vec();
+            if frame.version >= Version::V5 {
+                let result_metadata_id = prepared
+                    .result_metadata_id
+                    .clone()
+                    .unwrap_or_else(|| CBytesShort::new(vec![]));
+                body.extend_from_slice(&result_metadata_id.serialize_to_vec());
+            }
+
+            body.extend_from_slice(&frame.body[cursor.position() as usize..]);
+            body
+        }
+        RequestBody::Batch(mut batch) => {
+            for query in &mut batch.queries {
+                if let BatchQuerySubj::PreparedId(query_id) = &mut query.subject {
+                    if query_id == id {
+                        *query_id = prepared.id.clone();
+                    }
+                }
+            }
+
+            batch.serialize_to_vec(frame.version)
+        }
+        _ => return Err("Only EXECUTE and BATCH frames can contain prepared statements!".into()),
+    };
+
+    let mut frame = frame.clone();
+    frame.body = body.into();
+    Ok(frame)
+}
+
+/// Writes given frame and waits for the response, for at most the given time.
+async fn write_frame_with_timeout<T: CdrsTransport + ?Sized>(
+    transport: &T,
+    frame: &Frame,
+    request_timeout: Option<Duration>,
+) -> error::Result<Frame> {
+    match request_timeout {
+        // dropping the pending future on timeout releases its stream
+        Some(request_timeout) => timeout(request_timeout, transport.write_frame(frame))
+            .await
+            .unwrap_or_else(|_| {
+                transport.report_timeout();
+
+                Err(error::Error::Timeout(format!(
+                    "Timeout waiting for response from: {}",
+                    transport.address()
+                )))
+            }),
+        None => transport.write_frame(frame).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arc_swap::ArcSwap;
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::frame::frame_batch::{
+        BatchQuery, BatchQuerySubj, BatchType, BodyReqBatch,
+    };
+    use cassandra_protocol::frame::frame_error::{AdditionalErrorInfo, ErrorBody, UnpreparedError};
+    use cassandra_protocol::frame::frame_execute::BodyReqExecuteOwned;
+    use cassandra_protocol::frame::frame_prepare::BodyReqPrepare;
+    use cassandra_protocol::frame::frame_request::RequestBody;
+    use cassandra_protocol::frame::frame_result::{
+        BodyResResultPrepared, PreparedMetadata, ResResultBody, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::{
+        Direction, Flags, Frame, FromCursor, Opcode, Serialize, Version,
+    };
+    use cassandra_protocol::query::{
+        PreparedQuery, PreparedResultMetadata, QueryParams, QueryValues,
+    };
+    use cassandra_protocol::types::{CBytes, CBytesShort};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::watch;
+
+    use super::send_frame_with_reprepare;
+    use crate::cluster::connection_manager::{mock_connection_manager, MockConnectionManager};
+    use crate::cluster::connection_pool::ConnectionPoolFactory;
+    use crate::cluster::prepared_statement_cache::PreparedStatementCache;
+    use crate::cluster::topology::{Node, NodeDistance, NodeState};
+    use crate::cluster::VersionHolder;
+    use crate::metrics::NoopMetricsSink;
+    use crate::retry::{DefaultRetryPolicy, RetryPolicy};
+    use crate::transport::MockCdrsTransport;
+
+    const KEYSPACE: &str = "ks";
+
+    type MockNode = Node<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+
+    // node which doesn't know any prepared statements, e.g. after a restart, and prepares given
+    // queries with given ids
+    struct Server {
+        ids: HashMap<&'static str, CBytesShort>,
+        prepared: Mutex<Vec<CBytesShort>>,
+        frames: Mutex<Vec<Frame>>,
+    }
+
+    impl Server {
+        fn new(ids: &[(&'static str, u8)]) -> Arc<Self> {
+            Arc::new(Server {
+                ids: ids
+                    .iter()
+                    .map(|(query, id)| (*query, CBytesShort::new(vec![*id])))
+                    .collect(),
+                prepared: Default::default(),
+                frames: Default::default(),
+            })
+        }
+
+        fn respond(&self, frame: &Frame) -> cassandra_protocol::error::Result<Frame> {
+            self.frames.lock().unwrap().push(frame.clone());
+
+            let ids = match frame.request_body()? {
+                RequestBody::Prepare(body) => {
+                    let (_, id) = self
+                        .ids
+                        .iter()
+                        .find(|(query, _)| {
+                            body == BodyReqPrepare::new(query.to_string(), Some(KEYSPACE.into()))
+                        })
+                        .expect("unexpected statement");
+
+                    self.prepared.lock().unwrap().push(id.clone());
+                    return Ok(prepared_response(id));
+                }
+                RequestBody::Execute(_) => {
+                    vec![CBytesShort::from_cursor(&mut Cursor::new(
+                        frame.body.as_ref(),
+                    ))?]
+                }
+                RequestBody::Batch(body) => body
+                    .queries
+                    .into_iter()
+                    .filter_map(|query| match query.subject {
+                        BatchQuerySubj::PreparedId(id) => Some(id),
+                        BatchQuerySubj::QueryString(_) => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+
+            let prepared = self.prepared.lock().unwrap();
+            match ids.into_iter().find(|id| !prepared.contains(id)) {
+                Some(id) => Err(cassandra_protocol::error::Error::Server(ErrorBody {
+                    error_code: 0x2500,
+                    message: "Unprepared".into(),
+                    additional_info: AdditionalErrorInfo::Unprepared(UnpreparedError { id }),
+                })),
+                None => Ok(response(ResResultBody::Void)),
+            }
+        }
+
+        fn sent_frames(&self, opcode: Opcode) -> Vec<Frame> {
+            self.frames
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|frame| frame.opcode == opcode)
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn empty_rows_metadata() -> RowsMetadata {
+        RowsMetadata {
+            flags: RowsMetadataFlags::NO_METADATA,
+            columns_count: 0,
+            paging_state: None,
+            new_metadata_id: None,
+            global_table_spec: None,
+            col_specs: vec![],
+        }
+    }
+
+    fn response(body: ResResultBody) -> Frame {
+        Frame::new(
+            Version::V5,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec().into(),
+            None,
+            vec![],
+            Default::default(),
+        )
+    }
+
+    fn prepared_response(id: &CBytesShort) -> Frame {
+        response(ResResultBody::Prepared(BodyResResultPrepared {
+            id: id.clone(),
+            result_metadata_id: Some(CBytesShort::new(vec![100])),
+            metadata: PreparedMetadata {
+                pk_indexes: vec![],
+                global_table_spec: None,
+                col_specs: vec![],
+            },
+            result_metadata: empty_rows_metadata(),
+        }))
+    }
+
+    fn create_node(server: &Arc<Server>, port: u16) -> Arc<MockNode> {
+        let connection_manager = mock_connection_manager(Arc::new({
+            let server = server.clone();
+            move |frame| server.respond(frame)
+        }));
+
+        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+            Default::default(),
+            Arc::new(VersionHolder::new_negotiated(Version::V5)),
+            connection_manager,
+            watch::channel(None).1,
+            Arc::new(NoopMetricsSink),
+        ));
+
+        Arc::new(Node::new_with_state(
+            connection_pool_factory,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            None,
+            None,
+            Some(NodeDistance::Local),
+            NodeState::Up,
+            vec![],
+            "".into(),
+            "".into(),
+        ))
+    }
+
+    fn prepared_query(query: &str, id: u8) -> PreparedQuery {
+        PreparedQuery {
+            id: ArcSwap::from_pointee(CBytesShort::new(vec![id])),
+            query: query.into(),
+            keyspace: Some(KEYSPACE.into()),
+            pk_indexes: vec![],
+            result_metadata: ArcSwap::from_pointee(PreparedResultMetadata {
+                id: Some(CBytesShort::new(vec![1])),
+                metadata: empty_rows_metadata(),
+            }),
+        }
+    }
+
+    fn execute_frame(prepared: &PreparedQuery, query_parameters: &QueryParams) -> Frame {
+        Frame::new_req_execute(
+            &prepared.id(),
+            prepared.result_metadata_id().as_ref(),
+            query_parameters,
+            Flags::empty(),
+            Version::V5,
+        )
+    }
+
+    fn cache_with(statements: &[(&str, u8)]) -> PreparedStatementCache {
+        let cache = PreparedStatementCache::new(10);
+        for (query, id) in statements {
+            cache.register(Some(KEYSPACE.into()), Arc::new(prepared_query(query, *id)));
+        }
+
+        cache
+    }
+
+    fn prepare_frames(server: &Server) -> Vec<BodyReqPrepare> {
+        server
+            .sent_frames(Opcode::Prepare)
+            .into_iter()
+            .map(|frame| match frame.request_body().unwrap() {
+                RequestBody::Prepare(body) => body,
+                _ => panic!("PREPARE expected"),
+            })
+            .collect()
+    }
+
+    async fn send(
+        nodes: &[Arc<MockNode>],
+        frame: &Frame,
+        cache: &PreparedStatementCache,
+        executed_statement: Option<&PreparedQuery>,
+    ) -> cassandra_protocol::error::Result<Frame> {
+        send_frame_with_reprepare(
+            nodes.iter().cloned(),
+            frame,
+            false,
+            DefaultRetryPolicy.new_session(),
+            Some(cache),
+            executed_statement,
+            None,
+            &NoopMetricsSink,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_reprepare_unprepared_execute_on_reporting_node() {
+        let server = Server::new(&[("SELECT * FROM t", 1)]);
+        let other_server = Server::new(&[]);
+        let nodes = [create_node(&server, 1), create_node(&other_server, 2)];
+
+        let cache = cache_with(&[("SELECT * FROM t", 1)]);
+        let prepared = prepared_query("SELECT * FROM t", 1);
+        let frame = execute_frame(&prepared, &QueryParams::default());
+
+        send(&nodes, &frame, &cache, Some(&prepared)).await.unwrap();
+
+        assert_eq!(
+            prepare_frames(&server),
+            vec![BodyReqPrepare::new(
+                "SELECT * FROM t".into(),
+                Some(KEYSPACE.into())
+            )]
+        );
+        assert_eq!(
+            server.sent_frames(Opcode::Execute),
+            vec![frame.clone(), frame]
+        );
+        assert!(other_server.frames.lock().unwrap().is_empty());
+
+        // result metadata of the executed statement comes from the new PREPARE
+        assert_eq!(
+            prepared.result_metadata_id(),
+            Some(CBytesShort::new(vec![100]))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reprepare_all_statements_in_batch() {
+        let server = Server::new(&[("INSERT INTO t1", 1), ("INSERT INTO t2", 2)]);
+        let nodes = [create_node(&server, 1)];
+
+        let cache = cache_with(&[("INSERT INTO t1", 1), ("INSERT INTO t2", 2)]);
+        let frame = Frame::new_req_batch(
+            BodyReqBatch {
+                batch_type: BatchType::Logged,
+                queries: vec![
+                    BatchQuery::new(
+                        BatchQuerySubj::PreparedId(CBytesShort::new(vec![1])),
+                        QueryValues::SimpleValues(vec![]),
+                    ),
+                    BatchQuery::new(
+                        BatchQuerySubj::QueryString("INSERT INTO t3".into()),
+                        QueryValues::SimpleValues(vec![]),
+                    ),
+                    BatchQuery::new(
+                        BatchQuerySubj::PreparedId(CBytesShort::new(vec![2])),
+                        QueryValues::SimpleValues(vec![]),
+                    ),
+                ],
+                consistency: Consistency::One,
+                serial_consistency: None,
+                timestamp: None,
+                keyspace: None,
+                now_in_seconds: None,
+            },
+            Flags::empty(),
+            Version::V5,
+        );
+
+        send(&nodes, &frame, &cache, None).await.unwrap();
+
+        assert_eq!(
+            prepare_frames(&server),
+            vec![
+                BodyReqPrepare::new("INSERT INTO t1".into(), Some(KEYSPACE.into())),
+                BodyReqPrepare::new("INSERT INTO t2".into(), Some(KEYSPACE.into())),
+            ]
+        );
+        assert_eq!(server.sent_frames(Opcode::Batch).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_reprepare_paged_execution() {
+        let server = Server::new(&[("SELECT * FROM t", 1)]);
+        let nodes = [create_node(&server, 1)];
+
+        let cache = cache_with(&[("SELECT * FROM t", 1)]);
+        let prepared = prepared_query("SELECT * FROM t", 1);
+        let query_parameters = QueryParams {
+            page_size: Some(10),
+            paging_state: Some(CBytes::new(vec![1, 2, 3])),
+            ..Default::default()
+        };
+        let frame = execute_frame(&prepared, &query_parameters);
+
+        send(&nodes, &frame, &cache, Some(&prepared)).await.unwrap();
+
+        assert_eq!(prepare_frames(&server).len(), 1);
+
+        // the next page is requested again from the same position
+        let execute_frames = server.sent_frames(Opcode::Execute);
+        assert_eq!(execute_frames.len(), 2);
+        match execute_frames[1].request_body().unwrap() {
+            RequestBody::Execute(body) => assert_eq!(
+                body,
+                BodyReqExecuteOwned::new(
+                    CBytesShort::new(vec![1]),
+                    Some(CBytesShort::new(vec![1])),
+                    query_parameters
+                )
+            ),
+            _ => panic!("EXECUTE expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_execute_statement_reprepared_with_new_id() {
+        let server = Server::new(&[("SELECT * FROM t", 2)]);
+        let nodes = [create_node(&server, 1)];
+
+        let cache = cache_with(&[("SELECT * FROM t", 1)]);
+        let prepared = prepared_query("SELECT * FROM t", 1);
+        let query_parameters = QueryParams {
+            page_size: Some(10),
+            ..Default::default()
+        };
+        let frame = execute_frame(&prepared, &query_parameters);
+
+        send(&nodes, &frame, &cache, Some(&prepared)).await.unwrap();
+
+        let execute_frames = server.sent_frames(Opcode::Execute);
+        assert_eq!(execute_frames.len(), 2);
+        match execute_frames[1].request_body().unwrap() {
+            RequestBody::Execute(body) => assert_eq!(
+                body,
+                BodyReqExecuteOwned::new(
+                    CBytesShort::new(vec![2]),
+                    Some(CBytesShort::new(vec![100])),
+                    query_parameters
+                )
+            ),
+            _ => panic!("EXECUTE expected"),
+        }
+
+        let cached = cache
+            .entry(Some(KEYSPACE), "SELECT * FROM t")
+            .get()
+            .cloned()
+            .unwrap();
+        assert_eq!(*cached.id(), CBytesShort::new(vec![2]));
+        assert!(cache.statement_by_id(&CBytesShort::new(vec![2])).is_some());
+    }
+
+    #[tokio::test]
+    async fn should_update_id_of_statement_reprepared_with_new_id() {
+        let server = Server::new(&[("SELECT * FROM t", 2)]);
+        let nodes = [create_node(&server, 1)];
+
+        let cache = cache_with(&[]);
+        let prepared = prepared_query("SELECT * FROM t", 1);
+        let frame = execute_frame(&prepared, &QueryParams::default());
+
+        send(&nodes, &frame, &cache, Some(&prepared)).await.unwrap();
+
+        assert_eq!(*prepared.id(), CBytesShort::new(vec![2]));
+
+        // subsequent executions use the new id without preparing again
+        let frame = execute_frame(&prepared, &QueryParams::default());
+        send(&nodes, &frame, &cache, Some(&prepared)).await.unwrap();
+
+        assert_eq!(prepare_frames(&server).len(), 1);
+        assert_eq!(server.sent_frames(Opcode::Execute).len(), 3);
+    }
+}
@@ -1,58 +1,78 @@
+use arc_swap::ArcSwap;
 use cassandra_protocol::compression::Compression;
 use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error;
 use cassandra_protocol::events::ServerEvent;
-use cassandra_protocol::frame::frame_result::{BodyResResultPrepared, TableSpec};
-use cassandra_protocol::frame::{Frame, Serialize, Version};
+use cassandra_protocol::frame::frame_result::{
+    BodyResResultPrepared, ResultKind, RowsMetadata, RowsMetadataFlags, TableSpec,
+};
+use cassandra_protocol::frame::{Frame, FromBytes, FromCursor, Opcode, Serialize, Version};
 use cassandra_protocol::query::utils::prepare_flags;
-use cassandra_protocol::query::{PreparedQuery, Query, QueryBatch, QueryValues};
+use cassandra_protocol::query::{PreparedQuery, PreparedResultMetadata, QueryBatch, QueryValues};
 use cassandra_protocol::token::Murmur3Token;
+use cassandra_protocol::types::rows::Row;
 use cassandra_protocol::types::value::Value;
-use cassandra_protocol::types::{CIntShort, SHORT_LEN};
+use cassandra_protocol::types::{try_i32_from_bytes, CIntShort, INT_LEN, SHORT_LEN};
+use futures::future::join_all;
 use futures::stream::FuturesUnordered;
 use futures::{FutureExt, StreamExt};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use std::borrow::Cow;
 use std::io::{Cursor, Write};
 use std::marker::PhantomData;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
 use tokio::{pin, select};
 use tracing::*;
+use uuid::Uuid;
 
 use crate::cluster::connection_manager::ConnectionManager;
 use crate::cluster::connection_pool::{ConnectionPoolConfig, ConnectionPoolFactory};
 use crate::cluster::control_connection::ControlConnection;
+use crate::cluster::prepared_statement_cache::PreparedStatementCache;
+use crate::cluster::request_span::{record_error, request_span, speculative_execution_span};
 #[cfg(feature = "rust-tls")]
 use crate::cluster::rustls_connection_manager::RustlsConnectionManager;
-use crate::cluster::send_frame::send_frame;
+use crate::cluster::send_frame::send_frame_with_reprepare;
 use crate::cluster::tcp_connection_manager::TcpConnectionManager;
 use crate::cluster::topology::{Node, NodeDistance, NodeState};
 #[cfg(feature = "rust-tls")]
 use crate::cluster::NodeRustlsConfig;
 use crate::cluster::{ClusterMetadata, ClusterMetadataManager, SessionContext};
-use crate::cluster::{GenericClusterConfig, KeyspaceHolder};
-use crate::cluster::{NodeTcpConfig, SessionPager};
+use crate::cluster::{GenericClusterConfig, KeyspaceHolder, VersionHolder};
+use crate::cluster::{NodeTcpConfig, SessionPager, DEFAULT_PREPARED_STATEMENT_CACHE_SIZE};
 use crate::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
 use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
 use crate::load_balancing::{
     InitializingWrapperLoadBalancingStrategy, LoadBalancingStrategy, QueryPlan, Request,
 };
+use crate::metrics::{MetricsSink, MetricsSnapshot, NoopMetricsSink};
+use crate::query_trace::{
+    build_query_trace, is_trace_complete, QueryTrace, QueryTraceConfig, SELECT_EVENTS_QUERY,
+    SELECT_SESSION_QUERY,
+};
+use crate::request_interceptor::{intercept, RequestInterceptor};
 use crate::retry::{
     DefaultRetryPolicy, ExponentialReconnectionPolicy, ReconnectionPolicy, RetryPolicy,
 };
 use crate::speculative_execution::{Context, SpeculativeExecutionPolicy};
 use crate::statement::{StatementParams, StatementParamsBuilder};
+use crate::timestamp_generator::{AtomicMonotonicTimestampGenerator, TimestampGenerator};
 #[cfg(feature = "rust-tls")]
 use crate::transport::TransportRustls;
-use crate::transport::{CdrsTransport, TransportTcp};
+use crate::transport::{CdrsTransport, HeartbeatConfig, TransportTcp};
 
 pub const DEFAULT_TRANSPORT_BUFFER_SIZE: usize = 1024;
+pub const DEFAULT_MAX_IN_FLIGHT_REQUESTS: usize = 1024;
 const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 128;
+const PREPARE_ON_NODE_TIMEOUT: Duration = Duration::from_secs(10);
 
 lazy_static! {
     static ref DEFAULT_STATEMET_PARAMETERS: StatementParams = Default::default();
@@ -109,6 +129,76 @@ fn serialize_routing_key_with_indexes(values: &[Value], pk_indexes: &[i16]) -> O
     }
 }
 
+/// Updates cached result metadata of a prepared statement, if the server reported a change.
+fn update_result_metadata(prepared: &PreparedQuery, frame: &Frame) -> error::Result<()> {
+    // checks are done manually to avoid parsing the whole body
+    if frame.opcode != Opcode::Result || frame.body.len() < 2 * INT_LEN {
+        return Ok(());
+    }
+
+    if ResultKind::from_bytes(&frame.body[..INT_LEN])? != ResultKind::Rows {
+        return Ok(());
+    }
+
+    let flags = RowsMetadataFlags::from_bits_truncate(try_i32_from_bytes(
+        &frame.body[INT_LEN..2 * INT_LEN],
+    )?);
+    if !flags.contains(RowsMetadataFlags::METADATA_CHANGED) {
+        return Ok(());
+    }
+
+    let mut metadata = RowsMetadata::from_cursor(&mut Cursor::new(&frame.body[INT_LEN..]))?;
+
+    // paging state is specific to given response, not the statement
+    metadata.flags.remove(RowsMetadataFlags::HAS_MORE_PAGES);
+    metadata.paging_state = None;
+
+    debug!("Updating result metadata of a prepared statement.");
+    prepared.update_result_metadata(PreparedResultMetadata {
+        id: metadata.new_metadata_id.clone(),
+        metadata,
+    });
+
+    Ok(())
+}
+
+// parameters for preparing statements with given flags only
+fn prepare_params(with_tracing: bool, with_warnings: bool) -> StatementParams {
+    StatementParams {
+        tracing: with_tracing,
+        warnings: with_warnings,
+        ..Default::default()
+    }
+}
+
+// creates a prepared statement from a PREPARE result for a query prepared in given keyspace
+fn new_prepared_query(
+    query: String,
+    keyspace: Option<String>,
+    result: BodyResResultPrepared,
+) -> PreparedQuery {
+    PreparedQuery {
+        id: ArcSwap::from_pointee(result.id),
+        query,
+        // unqualified statements without bound values don't report any table
+        keyspace: result
+            .metadata
+            .global_table_spec
+            .map(|TableSpec { ks_name, .. }| ks_name)
+            .or(keyspace),
+        pk_indexes: result.metadata.pk_indexes,
+        result_metadata: ArcSwap::from_pointee(PreparedResultMetadata {
+            id: result.result_metadata_id,
+            metadata: result.result_metadata,
+        }),
+    }
+}
+
+#[inline]
+fn session_closed_error() -> error::Error {
+    error::Error::General("Session is closed!".into())
+}
+
 fn serialize_routing_key(values: &[Value]) -> Vec<u8> {
     match values.len() {
         0 => vec![],
@@ -142,7 +232,15 @@ pub struct Session<
     cluster_metadata_manager: Arc<ClusterMetadataManager<T, CM>>,
     _transport: PhantomData<T>,
     _connection_manager: PhantomData<CM>,
-    version: Version,
+    version_holder: Arc<VersionHolder>,
+    prepared_statement_cache: Arc<PreparedStatementCache>,
+    prepare_on_all_nodes: bool,
+    timeout: Option<Duration>,
+    timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    session_context: Arc<SessionContext<T>>,
+    is_closed: AtomicBool,
+    metrics: Arc<dyn MetricsSink + Send + Sync>,
+    request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
 }
 
 impl<
@@ -173,81 +271,72 @@ impl<
         prepared: &PreparedQuery,
         parameters: &StatementParams,
     ) -> error::Result<Frame> {
-        let consistency = parameters.query_params.consistency;
-        let flags = prepare_flags(parameters.tracing, parameters.warnings);
-        let options_frame =
-            Frame::new_req_execute(&prepared.id, &parameters.query_params, flags, self.version);
-
-        let keyspace = prepared
-            .keyspace
-            .as_deref()
-            .or(parameters.keyspace.as_deref());
-
-        let routing_key = parameters
-            .query_params
-            .values
-            .as_ref()
-            .and_then(|values| match values {
-                QueryValues::SimpleValues(values) => {
-                    serialize_routing_key_with_indexes(values, &prepared.pk_indexes).or_else(|| {
-                        parameters
-                            .routing_key
-                            .as_ref()
-                            .map(|values| serialize_routing_key(values))
-                    })
-                }
-                QueryValues::NamedValues(_) => None,
-            });
-
-        let mut result = self
-            .send_frame(
-                options_frame,
-                parameters.is_idempotent,
-                keyspace,
-                parameters.token,
-                routing_key.as_deref(),
-                Some(consistency),
-                parameters.speculative_execution_policy.as_ref(),
-                parameters.retry_policy.as_ref(),
-            )
-            .await;
+        let version = self.negotiated_version().await;
+        let generated_timestamp = if parameters.query_params.timestamp.is_none() {
+            self.timestamp_generator.next_timestamp()
+        } else {
+            None
+        };
 
-        if let Err(error::Error::Server(error)) = &result {
-            // if query is unprepared
-            if error.error_code == 0x2500 {
-                debug!("Re-preparing statement.");
-                if let Ok(new) = self.prepare_raw(&prepared.query).await {
-                    // re-prepare the statement and check the resulting id - it should remain the
-                    // same as the old one, except when schema changed in the meantime, in which
-                    // case, the client should have the knowledge how to handle it
-                    // see: https://issues.apache.org/jira/browse/CASSANDRA-10786
-
-                    if prepared.id != new.id {
-                        return Err("Re-preparing an unprepared statement resulted in a different id - probably schema changed on the server.".into());
-                    }
+        let result = intercept(
+            &self.request_interceptors,
+            &mut Cow::Borrowed(parameters),
+            |parameters| {
+                Frame::new_req_execute_with_timestamp(
+                    &prepared.id(),
+                    prepared.result_metadata_id().as_ref(),
+                    &parameters.query_params,
+                    parameters.query_params.timestamp.or(generated_timestamp),
+                    prepare_flags(parameters.tracing, parameters.warnings),
+                    version,
+                )
+            },
+            |frame, parameters| async move {
+                let keyspace = prepared
+                    .keyspace
+                    .as_deref()
+                    .or(parameters.keyspace.as_deref());
+
+                let routing_key =
+                    parameters
+                        .query_params
+                        .values
+                        .as_ref()
+                        .and_then(|values| match values {
+                            QueryValues::SimpleValues(values) => {
+                                serialize_routing_key_with_indexes(values, &prepared.pk_indexes)
+                                    .or_else(|| {
+                                        parameters
+                                            .routing_key
+                                            .as_ref()
+                                            .map(|values| serialize_routing_key(values))
+                                    })
+                            }
+                            QueryValues::NamedValues(_) => None,
+                        });
+
+                self.send_frame(
+                    frame,
+                    // re-preparing the statement updates its result metadata
+                    Some(prepared),
+                    parameters.is_idempotent,
+                    keyspace,
+                    parameters.token,
+                    routing_key.as_deref(),
+                    Some(parameters.query_params.consistency),
+                    parameters.speculative_execution_policy.as_ref(),
+                    parameters.retry_policy.as_ref(),
+                    parameters.timeout,
+                )
+                .await
+            },
+        )
+        .await;
 
-                    let flags = prepare_flags(parameters.tracing, parameters.warnings);
-                    let options_frame = Frame::new_req_execute(
-                        &new.id,
-                        &parameters.query_params,
-                        flags,
-                        self.version,
-                    );
-                    result = self
-                        .send_frame(
-                            options_frame,
-                            parameters.is_idempotent,
-                            keyspace,
-                            parameters.token,
-                            routing_key.as_deref(),
-                            Some(consistency),
-                            parameters.speculative_execution_policy.as_ref(),
-                            parameters.retry_policy.as_ref(),
-                        )
-                        .await;
-                }
-            }
+        if let Ok(frame) = &result {
+            update_result_metadata(prepared, frame)?;
         }
+
         result
     }
 
@@ -283,12 +372,87 @@ impl<
         with_tracing: bool,
         with_warnings: bool,
     ) -> error::Result<BodyResResultPrepared> {
-        let flags = prepare_flags(with_tracing, with_warnings);
+        self.prepare_raw_with_params(query, &prepare_params(with_tracing, with_warnings))
+            .await
+    }
 
-        let query_frame = Frame::new_req_prepare(query.to_string(), flags, self.version);
+    /// Prepares a query for execution with given parameters. Apart from tracing and warnings
+    /// flags, the statement keyspace is used - the query gets prepared in
+    /// [`StatementParams::keyspace`] or, if not set, in the current global keyspace. With protocol
+    /// V5+, the keyspace is sent along with the request, so it doesn't depend on the keyspace
+    /// set on the connection. Returns the raw prepared query result.
+    pub async fn prepare_raw_with_params<Q: ToString>(
+        &self,
+        query: Q,
+        parameters: &StatementParams,
+    ) -> error::Result<BodyResResultPrepared> {
+        let query = query.to_string();
+        let keyspace = self.statement_keyspace(parameters);
+        let prepared = self
+            .send_prepare(query.clone(), keyspace.clone(), parameters)
+            .await?;
+
+        self.prepared_statement_cache.register(
+            keyspace.clone(),
+            Arc::new(new_prepared_query(query, keyspace, prepared.clone())),
+        );
 
-        self.send_frame(query_frame, false, None, None, None, None, None, None)
-            .await
+        Ok(prepared)
+    }
+
+    async fn send_prepare(
+        &self,
+        query: String,
+        keyspace: Option<String>,
+        parameters: &StatementParams,
+    ) -> error::Result<BodyResResultPrepared> {
+        let version = self.negotiated_version().await;
+        let routing_keyspace = keyspace.as_deref();
+
+        let response = intercept(
+            &self.request_interceptors,
+            &mut Cow::Borrowed(parameters),
+            |parameters| {
+                Frame::new_req_prepare(
+                    query.clone(),
+                    keyspace.clone(),
+                    prepare_flags(parameters.tracing, parameters.warnings),
+                    version,
+                )
+            },
+            |frame, parameters| async move {
+                // the frame is only needed again when preparing on remaining nodes
+                let remaining_nodes_frame = if self.prepare_on_all_nodes {
+                    Some(frame.clone())
+                } else {
+                    None
+                };
+
+                let response = self
+                    .send_frame(
+                        frame,
+                        None,
+                        false,
+                        routing_keyspace,
+                        None,
+                        None,
+                        None,
+                        None,
+                        parameters.retry_policy.as_ref(),
+                        parameters.timeout,
+                    )
+                    .await;
+
+                if let (Ok(_), Some(frame)) = (&response, remaining_nodes_frame) {
+                    self.prepare_on_remaining_nodes(frame);
+                }
+
+                response
+            },
+        )
+        .await;
+
+        response
             .and_then(|response| response.response_body())
             .and_then(|body| {
                 body.into_prepared()
@@ -296,6 +460,55 @@ impl<
             })
     }
 
+    // keyspace in which a statement with given parameters should be prepared
+    fn statement_keyspace(&self, parameters: &StatementParams) -> Option<String> {
+        parameters.keyspace.clone().or_else(|| {
+            self.current_keyspace()
+                .map(|keyspace| keyspace.as_ref().clone())
+        })
+    }
+
+    // sends the PREPARE request to all up nodes in the background, so they don't need to re-prepare
+    // the statement on first execution - the node which already prepared it is not known here, so
+    // it gets the request again
+    fn prepare_on_remaining_nodes(&self, frame: Frame) {
+        let nodes = self
+            .cluster_metadata()
+            .nodes()
+            .values()
+            .filter(|node| node.state() == NodeState::Up && !node.is_ignored())
+            .cloned()
+            .collect_vec();
+
+        if nodes.is_empty() {
+            return;
+        }
+
+        tokio::spawn(async move {
+            join_all(nodes.iter().map(|node| {
+                let frame = &frame;
+                async move {
+                    let prepare = async {
+                        let transport = node.persistent_connection().await?;
+                        transport.write_frame(frame).await
+                    };
+
+                    // a slow node shouldn't keep the task running indefinitely
+                    match timeout(PREPARE_ON_NODE_TIMEOUT, prepare).await {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(error)) => {
+                            warn!(%error, broadcast_rpc_address = %node.broadcast_rpc_address(), "Error preparing statement on node.");
+                        }
+                        Err(_) => {
+                            warn!(broadcast_rpc_address = %node.broadcast_rpc_address(), "Timeout preparing statement on node.");
+                        }
+                    }
+                }
+            }))
+            .await;
+        });
+    }
+
     /// Prepares query without additional tracing information and warnings.
     /// Returns the raw prepared query result.
     #[inline]
@@ -313,18 +526,37 @@ impl<
         with_tracing: bool,
         with_warnings: bool,
     ) -> error::Result<PreparedQuery> {
-        let s = query.to_string();
-        self.prepare_raw_tw(query, with_tracing, with_warnings)
+        self.prepare_with_params(query, &prepare_params(with_tracing, with_warnings))
+            .await
+    }
+
+    /// Prepares a query for execution with given parameters. See
+    /// [`Session::prepare_raw_with_params`]. Returns the prepared query.
+    pub async fn prepare_with_params<Q: ToString>(
+        &self,
+        query: Q,
+        parameters: &StatementParams,
+    ) -> error::Result<PreparedQuery> {
+        let keyspace = self.statement_keyspace(parameters);
+        self.prepare_in_keyspace(query.to_string(), keyspace, parameters)
             .await
-            .map(|result| PreparedQuery {
-                id: result.id,
-                query: s,
-                keyspace: result
-                    .metadata
-                    .global_table_spec
-                    .map(|TableSpec { ks_name, .. }| ks_name),
-                pk_indexes: result.metadata.pk_indexes,
-            })
+    }
+
+    async fn prepare_in_keyspace(
+        &self,
+        query: String,
+        keyspace: Option<String>,
+        parameters: &StatementParams,
+    ) -> error::Result<PreparedQuery> {
+        let result = self
+            .send_prepare(query.clone(), keyspace.clone(), parameters)
+            .await?;
+        let prepared = new_prepared_query(query, keyspace.clone(), result);
+
+        self.prepared_statement_cache
+            .register(keyspace, Arc::new(prepared.clone()));
+
+        Ok(prepared)
     }
 
     /// It prepares query without additional tracing information and warnings.
@@ -334,6 +566,60 @@ impl<
         self.prepare_tw(query, false, false).await
     }
 
+    /// Returns a prepared query from the session cache, preparing it on first use. Statements are
+    /// cached per query string and current keyspace. Concurrent first uses of the same statement
+    /// share a single PREPARE request.
+    #[inline]
+    pub async fn prepare_cached<Q: ToString>(&self, query: Q) -> error::Result<Arc<PreparedQuery>> {
+        self.prepare_cached_with_params(query, &DEFAULT_STATEMET_PARAMETERS)
+            .await
+    }
+
+    // same as prepare_cached(), but the statement keyspace comes from given parameters
+    async fn prepare_cached_with_params<Q: ToString>(
+        &self,
+        query: Q,
+        parameters: &StatementParams,
+    ) -> error::Result<Arc<PreparedQuery>> {
+        let query = query.to_string();
+        let keyspace = self.statement_keyspace(parameters);
+
+        let cell = self
+            .prepared_statement_cache
+            .entry(keyspace.as_deref(), &query);
+
+        cell.get_or_try_init(|| async {
+            self.prepare_in_keyspace(query.clone(), keyspace.clone(), parameters)
+                .await
+                .map(Arc::new)
+        })
+        .await
+        .map(Arc::clone)
+    }
+
+    /// Executes given query as a prepared statement, preparing it on first use. See
+    /// [`Session::prepare_cached`].
+    pub async fn query_prepared<Q: ToString, V: Into<QueryValues>>(
+        &self,
+        query: Q,
+        values: V,
+    ) -> error::Result<Frame> {
+        let prepared = self.prepare_cached(query).await?;
+        self.exec_with_values(&prepared, values).await
+    }
+
+    /// Executes given query as a prepared statement with parameters, preparing it on first use.
+    /// See [`Session::prepare_cached`]. The statement is prepared in [`StatementParams::keyspace`],
+    /// if set.
+    pub async fn query_prepared_with_params<Q: ToString>(
+        &self,
+        query: Q,
+        parameters: &StatementParams,
+    ) -> error::Result<Frame> {
+        let prepared = self.prepare_cached_with_params(query, parameters).await?;
+        self.exec_with_params(&prepared, parameters).await
+    }
+
     /// Executes batch query.
     #[inline]
     pub async fn batch(&self, batch: QueryBatch) -> error::Result<Frame> {
@@ -344,23 +630,46 @@ impl<
     /// Executes batch query with parameters.
     pub async fn batch_with_params(
         &self,
-        batch: QueryBatch,
+        mut batch: QueryBatch,
         parameters: &StatementParams,
     ) -> error::Result<Frame> {
-        let flags = prepare_flags(parameters.tracing, parameters.warnings);
         let consistency = batch.consistency;
+        let has_keyspace = batch.keyspace.is_some();
+
+        if batch.timestamp.is_none() {
+            batch.timestamp = self.timestamp_generator.next_timestamp();
+        }
 
-        let query_frame = Frame::new_req_batch(batch, flags, self.version);
+        let version = self.negotiated_version().await;
 
-        self.send_frame(
-            query_frame,
-            parameters.is_idempotent,
-            parameters.keyspace.as_deref(),
-            None,
-            None,
-            Some(consistency),
-            parameters.speculative_execution_policy.as_ref(),
-            parameters.retry_policy.as_ref(),
+        intercept(
+            &self.request_interceptors,
+            &mut Cow::Borrowed(parameters),
+            |parameters| {
+                if !has_keyspace {
+                    batch.keyspace = parameters.keyspace.clone();
+                }
+
+                Frame::new_req_batch_borrowed(
+                    &batch,
+                    prepare_flags(parameters.tracing, parameters.warnings),
+                    version,
+                )
+            },
+            |frame, parameters| {
+                self.send_frame(
+                    frame,
+                    None,
+                    parameters.is_idempotent,
+                    parameters.keyspace.as_deref(),
+                    None,
+                    None,
+                    Some(consistency),
+                    parameters.speculative_execution_policy.as_ref(),
+                    parameters.retry_policy.as_ref(),
+                    parameters.timeout,
+                )
+            },
         )
         .await
     }
@@ -392,44 +701,136 @@ impl<
     pub async fn query_with_params<Q: ToString>(
         &self,
         query: Q,
-        parameters: StatementParams,
+        mut parameters: StatementParams,
     ) -> error::Result<Frame> {
-        let is_idempotent = parameters.is_idempotent;
-        let consistency = parameters.query_params.consistency;
-        let keyspace = parameters.keyspace;
-        let token = parameters.token;
-        let routing_key = parameters
-            .routing_key
-            .as_ref()
-            .map(|values| serialize_routing_key(values));
-
-        let query = Query {
-            query: query.to_string(),
-            params: parameters.query_params,
-        };
+        let query = query.to_string();
+
+        if parameters.query_params.keyspace.is_none() {
+            parameters.query_params.keyspace = parameters.keyspace.clone();
+        }
 
-        let flags = prepare_flags(parameters.tracing, parameters.warnings);
-        let query_frame = Frame::new_query(query, flags, self.version);
-
-        self.send_frame(
-            query_frame,
-            is_idempotent,
-            keyspace.as_deref(),
-            token,
-            routing_key.as_deref(),
-            Some(consistency),
-            parameters.speculative_execution_policy.as_ref(),
-            parameters.retry_policy.as_ref(),
+        if parameters.query_params.timestamp.is_none() {
+            parameters.query_params.timestamp = self.timestamp_generator.next_timestamp();
+        }
+
+        let version = self.negotiated_version().await;
+
+        intercept(
+            &self.request_interceptors,
+            &mut Cow::Owned(parameters),
+            |parameters| {
+                Frame::new_query_borrowed(
+                    &query,
+                    &parameters.query_params,
+                    prepare_flags(parameters.tracing, parameters.warnings),
+                    version,
+                )
+            },
+            |frame, parameters| async move {
+                let routing_key = parameters
+                    .routing_key
+                    .as_ref()
+                    .map(|values| serialize_routing_key(values));
+
+                // the keyspace sent along with the query takes precedence
+                let keyspace = parameters
+                    .query_params
+                    .keyspace
+                    .as_deref()
+                    .or(parameters.keyspace.as_deref());
+
+                self.send_frame(
+                    frame,
+                    None,
+                    parameters.is_idempotent,
+                    keyspace,
+                    parameters.token,
+                    routing_key.as_deref(),
+                    Some(parameters.query_params.consistency),
+                    parameters.speculative_execution_policy.as_ref(),
+                    parameters.retry_policy.as_ref(),
+                    parameters.timeout,
+                )
+                .await
+            },
         )
         .await
     }
 
+    /// Fetches the server-side trace of a request sent with tracing enabled, using its tracing id
+    /// (see [`Frame::tracing_id`]). Polls trace tables until the trace is complete, according to
+    /// default [`QueryTraceConfig`].
+    #[inline]
+    pub async fn query_trace(&self, tracing_id: Uuid) -> error::Result<QueryTrace> {
+        self.query_trace_with_config(tracing_id, &QueryTraceConfig::default())
+            .await
+    }
+
+    /// Fetches the server-side trace of a request, polling trace tables according to given
+    /// configuration. See [`Session::query_trace`].
+    pub async fn query_trace_with_config(
+        &self,
+        tracing_id: Uuid,
+        config: &QueryTraceConfig,
+    ) -> error::Result<QueryTrace> {
+        let parameters = StatementParamsBuilder::new()
+            .with_values(QueryValues::SimpleValues(vec![tracing_id.into()]))
+            .with_consistency(config.consistency)
+            .idempotent(true)
+            .build();
+
+        for attempt in 0..config.attempts {
+            if attempt > 0 {
+                sleep(config.delay).await;
+            }
+
+            let session = self
+                .query_rows(SELECT_SESSION_QUERY, parameters.clone())
+                .await?
+                .into_iter()
+                .next();
+
+            if let Some(session) = session {
+                if is_trace_complete(&session)? {
+                    let events = self
+                        .query_rows(SELECT_EVENTS_QUERY, parameters.clone())
+                        .await?;
+
+                    return build_query_trace(&session, &events);
+                }
+            }
+        }
+
+        Err(error::Error::General(format!(
+            "Trace {} is not complete after {} attempts!",
+            tracing_id, config.attempts
+        )))
+    }
+
+    async fn query_rows(
+        &self,
+        query: &str,
+        parameters: StatementParams,
+    ) -> error::Result<Vec<Row>> {
+        self.query_with_params(query, parameters)
+            .await
+            .and_then(|frame| frame.response_body())
+            .map(|body| body.into_rows().unwrap_or_default())
+    }
+
     /// Returns currently set global keyspace.
     #[inline]
     pub fn current_keyspace(&self) -> Option<Arc<String>> {
         self.keyspace_holder.current_keyspace()
     }
 
+    /// Returns protocol version used by this session. Until the first connection to the cluster is
+    /// established, this is the highest version which will be tried.
+    #[inline]
+    pub fn protocol_version(&self) -> Version {
+        self.version_holder.version()
+    }
+
     /// Returns current cluster metadata.
     #[inline]
     pub fn cluster_metadata(&self) -> Arc<ClusterMetadata<T, CM>> {
@@ -456,10 +857,72 @@ impl<
         self.retry_policy.as_ref()
     }
 
+    /// Gracefully closes the session. New requests are rejected right away, while requests in
+    /// flight get up to `drain_timeout` to complete. Afterwards, all node connection pools and the
+    /// control connection are closed, failing any requests still in flight. Closing an already
+    /// closed session does nothing.
+    pub async fn close(&self, drain_timeout: Duration) {
+        if self.is_closed.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        debug!("Closing session...");
+
+        // stop reconnecting and refreshing metadata
+        self.control_connection_handle.abort();
+
+        let control_connection = self.session_context.control_connection_transport.swap(None);
+
+        let nodes = self
+            .cluster_metadata()
+            .nodes()
+            .values()
+            .cloned()
+            .collect_vec();
+
+        let close_control_connection = async {
+            if let Some(control_connection) = control_connection {
+                control_connection.close(drain_timeout).await;
+            }
+        };
+
+        let close_nodes = join_all(nodes.iter().map(|node| node.close(drain_timeout)));
+
+        tokio::join!(close_control_connection, close_nodes);
+
+        debug!("Session closed.");
+    }
+
+    /// Returns a snapshot of driver metrics, if the configured [`MetricsSink`] keeps them.
+    #[inline]
+    pub fn metrics_snapshot(&self) -> Option<MetricsSnapshot> {
+        self.metrics.snapshot()
+    }
+
+    /// Checks if the session has been closed with [`Session::close`].
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.is_closed.load(Ordering::Relaxed)
+    }
+
+    /// Returns protocol version to use for new frames. If it hasn't been negotiated yet, tries to
+    /// connect to the first node in the query plan, which triggers the negotiation.
+    async fn negotiated_version(&self) -> Version {
+        if !self.version_holder.is_negotiated() {
+            if let Some(node) = self.query_plan(None).first() {
+                // errors will be reported when sending the actual frame
+                let _ = node.persistent_connection().await;
+            }
+        }
+
+        self.version_holder.version()
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn send_frame(
         &self,
         frame: Frame,
+        executed_statement: Option<&PreparedQuery>,
         is_idempotent: bool,
         keyspace: Option<&str>,
         token: Option<Murmur3Token>,
@@ -467,14 +930,16 @@ impl<
         consistency: Option<Consistency>,
         speculative_execution_policy: Option<&Arc<dyn SpeculativeExecutionPolicy + Send + Sync>>,
         retry_policy: Option<&Arc<dyn RetryPolicy + Send + Sync>>,
+        timeout: Option<Duration>,
     ) -> error::Result<Frame> {
+        if self.is_closed() {
+            return Err(session_closed_error());
+        }
+
+        let timeout = timeout.or(self.timeout);
         let current_keyspace = self.current_keyspace();
-        let request = Request::new(
-            keyspace.or_else(|| current_keyspace.as_ref().map(|keyspace| &***keyspace)),
-            token,
-            routing_key,
-            consistency,
-        );
+        let keyspace = keyspace.or_else(|| current_keyspace.as_ref().map(|keyspace| &***keyspace));
+        let request = Request::new(keyspace, token, routing_key, consistency);
 
         let query_plan = self.query_plan(Some(request));
 
@@ -520,79 +985,120 @@ impl<
             .map(|retry_policy| retry_policy.as_ref())
             .unwrap_or_else(|| self.retry_policy.as_ref());
 
-        match speculative_execution_policy {
-            Some(speculative_execution_policy) if is_idempotent => {
-                let shared_query_plan = SharedQueryPlan::new(query_plan.into_iter());
-
-                let mut context = Context::new(1);
-                let mut async_tasks = FuturesUnordered::new();
-                async_tasks.push(send_frame(
-                    &shared_query_plan,
-                    &frame,
-                    is_idempotent,
-                    retry_policy.new_session(),
-                ));
+        let request_span = request_span(frame.opcode, keyspace, consistency, is_idempotent);
+
+        let start = Instant::now();
+        let result = async {
+            match speculative_execution_policy {
+                Some(speculative_execution_policy) if is_idempotent => {
+                    let shared_query_plan = SharedQueryPlan::new(query_plan.into_iter());
+
+                    let mut context = Context::new(1);
+                    let mut async_tasks = FuturesUnordered::new();
+                    async_tasks.push(
+                        send_frame_with_reprepare(
+                            &shared_query_plan,
+                            &frame,
+                            is_idempotent,
+                            retry_policy.new_session(),
+                            Some(&self.prepared_statement_cache),
+                            executed_statement,
+                            timeout,
+                            self.metrics.as_ref(),
+                        )
+                        .instrument(speculative_execution_span(0)),
+                    );
 
-                let sleep_fut = sleep(
-                    speculative_execution_policy
-                        .execution_interval(&context)
-                        .unwrap_or_default(),
-                )
-                .fuse();
-
-                pin!(sleep_fut);
-
-                let mut last_error = None;
-
-                loop {
-                    select! {
-                        _ = &mut sleep_fut => {
-                            if let Some(interval) =
-                                speculative_execution_policy.execution_interval(&context)
-                            {
-                                context.running_executions += 1;
-                                async_tasks.push(send_frame(
-                                    &shared_query_plan,
-                                    &frame,
-                                    is_idempotent,
-                                    retry_policy.new_session(),
-                                ));
-
-                                sleep_fut.set(sleep(interval).fuse());
+                    let sleep_fut = sleep(
+                        speculative_execution_policy
+                            .execution_interval(&context)
+                            .unwrap_or_default(),
+                    )
+                    .fuse();
+
+                    pin!(sleep_fut);
+
+                    let mut last_error = None;
+
+                    loop {
+                        select! {
+                            _ = &mut sleep_fut => {
+                                if let Some(interval) =
+                                    speculative_execution_policy.execution_interval(&context)
+                                {
+                                    context.running_executions += 1;
+                                    async_tasks.push(
+                                        send_frame_with_reprepare(
+                                            &shared_query_plan,
+                                            &frame,
+                                            is_idempotent,
+                                            retry_policy.new_session(),
+                                            Some(&self.prepared_statement_cache),
+                                            executed_statement,
+                                            timeout,
+                                            self.metrics.as_ref(),
+                                        )
+                                        .instrument(speculative_execution_span(
+                                            context.running_executions - 1,
+                                        )),
+                                    );
+
+                                    self.metrics.record_speculative_execution();
+                                    request_span.record(
+                                        "db.cassandra.speculative_execution_count",
+                                        context.running_executions - 1,
+                                    );
+
+                                    sleep_fut.set(sleep(interval).fuse());
+                                }
                             }
-                        }
-                        result = async_tasks.select_next_some() => {
-                            match result {
-                                Some(result) => {
-                                    match result {
-                                        Err(error::Error::Io(_)) | Err(error::Error::Timeout(_)) => {
-                                            last_error = Some(result);
-                                        },
-                                        _ => return result,
+                            result = async_tasks.select_next_some() => {
+                                match result {
+                                    Some(result) => {
+                                        match result {
+                                            Err(error::Error::Io(_)) | Err(error::Error::Timeout(_)) => {
+                                                last_error = Some(result);
+                                            },
+                                            _ => return result,
+                                        }
                                     }
-                                }
-                                None => {
-                                    if async_tasks.is_empty() {
-                                        // at this point, we exhausted all available nodes and
-                                        // there's no request in flight, which can potentially
-                                        // reach a node
-                                        return last_error.unwrap_or_else(|| Err("No nodes available in query plan!".into()));
+                                    None => {
+                                        if async_tasks.is_empty() {
+                                            // at this point, we exhausted all available nodes and
+                                            // there's no request in flight, which can potentially
+                                            // reach a node
+                                            return last_error.unwrap_or_else(|| Err("No nodes available in query plan!".into()));
+                                        }
                                     }
                                 }
                             }
                         }
                     }
                 }
+                _ => send_frame_with_reprepare(
+                    query_plan.into_iter(),
+                    &frame,
+                    is_idempotent,
+                    retry_policy.new_session(),
+                    Some(&self.prepared_statement_cache),
+                    executed_statement,
+                    timeout,
+                    self.metrics.as_ref(),
+                )
+                .await
+                .unwrap_or_else(|| Err("No nodes available in query plan!".into())),
             }
-            _ => send_frame(
-                query_plan.into_iter(),
-                &frame,
-                is_idempotent,
-                retry_policy.new_session(),
-            )
-            .await
-            .unwrap_or_else(|| Err("No nodes available in query plan!".into())),
         }
+        .instrument(request_span.clone())
+        .await;
+
+        self.metrics.record_request_latency(start.elapsed());
+
+        if result.is_err() {
+            record_error(&request_span);
+        }
+
+        result
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -607,14 +1113,22 @@ impl<
         contact_points: Vec<SocketAddr>,
         connection_manager: CM,
         event_channel_capacity: usize,
-        version: Version,
+        version_holder: Arc<VersionHolder>,
         connection_pool_config: ConnectionPoolConfig,
+        prepared_statement_cache_size: usize,
+        prepare_on_all_nodes: bool,
+        schema_metadata: bool,
+        timeout: Option<Duration>,
+        timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+        metrics_sink: Arc<dyn MetricsSink + Send + Sync>,
+        request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
     ) -> Self {
         let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
             connection_pool_config,
-            version,
+            version_holder.clone(),
             connection_manager,
             keyspace_receiver,
+            metrics_sink.clone(),
         ));
 
         let contact_points = contact_points
@@ -644,13 +1158,17 @@ impl<
         let (event_sender, event_receiver) = channel(event_channel_capacity);
 
         let session_context = Arc::new(SessionContext::default());
+        let prepared_statement_cache =
+            Arc::new(PreparedStatementCache::new(prepared_statement_cache_size));
 
         let cluster_metadata_manager = Arc::new(ClusterMetadataManager::new(
             contact_points.clone(),
             connection_pool_factory,
             session_context.clone(),
             node_distance_evaluator,
-            version,
+            version_holder.clone(),
+            prepared_statement_cache.clone(),
+            schema_metadata,
         ));
 
         cluster_metadata_manager
@@ -663,8 +1181,8 @@ impl<
             reconnection_policy.clone(),
             cluster_metadata_manager.clone(),
             event_sender.clone(),
-            session_context,
-            version,
+            session_context.clone(),
+            version_holder.clone(),
         );
 
         let control_connection_handle = tokio::spawn(control_connection.run());
@@ -679,7 +1197,15 @@ impl<
             cluster_metadata_manager,
             _transport: Default::default(),
             _connection_manager: Default::default(),
-            version,
+            version_holder,
+            prepared_statement_cache,
+            prepare_on_all_nodes,
+            timeout,
+            timestamp_generator,
+            session_context,
+            is_closed: AtomicBool::new(false),
+            metrics: metrics_sink,
+            request_interceptors,
         }
     }
 }
@@ -725,7 +1251,10 @@ where
     LB: LoadBalancingStrategy<T, CM> + Sized + Send + Sync + 'static,
 {
     let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
-    let connection_manager = config.create_manager(keyspace_holder.clone()).await?;
+    let version_holder = Arc::new(VersionHolder::new(config.version()));
+    let connection_manager = config
+        .create_manager(keyspace_holder.clone(), version_holder.clone())
+        .await?;
     Ok(Session::new(
         load_balancing,
         keyspace_holder,
@@ -737,8 +1266,15 @@ where
         initial_nodes.into_iter().collect(),
         connection_manager,
         config.event_channel_capacity(),
-        config.version(),
+        version_holder,
         config.connection_pool_config(),
+        config.prepared_statement_cache_size(),
+        config.prepare_on_all_nodes(),
+        config.schema_metadata(),
+        config.timeout(),
+        config.timestamp_generator(),
+        config.metrics_sink(),
+        config.request_interceptors(),
     ))
 }
 
@@ -750,6 +1286,8 @@ struct SessionConfig<
     compression: Compression,
     transport_buffer_size: usize,
     tcp_nodelay: bool,
+    max_in_flight_requests: usize,
+    heartbeat: Option<HeartbeatConfig>,
     load_balancing: LB,
     retry_policy: Box<dyn RetryPolicy + Send + Sync>,
     reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
@@ -758,6 +1296,13 @@ struct SessionConfig<
     event_channel_capacity: usize,
     connection_pool_config: ConnectionPoolConfig,
     keyspace: Option<String>,
+    prepared_statement_cache_size: usize,
+    prepare_on_all_nodes: bool,
+    schema_metadata: bool,
+    timeout: Option<Duration>,
+    timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    metrics_sink: Arc<dyn MetricsSink + Send + Sync>,
+    request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
     _connection_manager: PhantomData<CM>,
     _transport: PhantomData<T>,
 }
@@ -773,6 +1318,8 @@ impl<
             compression: Compression::None,
             transport_buffer_size: DEFAULT_TRANSPORT_BUFFER_SIZE,
             tcp_nodelay: true,
+            max_in_flight_requests: DEFAULT_MAX_IN_FLIGHT_REQUESTS,
+            heartbeat: None,
             load_balancing,
             retry_policy: Box::new(DefaultRetryPolicy::default()),
             reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
@@ -781,6 +1328,13 @@ impl<
             event_channel_capacity: DEFAULT_EVENT_CHANNEL_CAPACITY,
             connection_pool_config: Default::default(),
             keyspace: None,
+            prepared_statement_cache_size: DEFAULT_PREPARED_STATEMENT_CACHE_SIZE,
+            prepare_on_all_nodes: false,
+            schema_metadata: true,
+            timeout: None,
+            timestamp_generator: Box::new(AtomicMonotonicTimestampGenerator::default()),
+            metrics_sink: Arc::new(NoopMetricsSink),
+            request_interceptors: vec![],
             _connection_manager: Default::default(),
             _transport: Default::default(),
         }
@@ -792,7 +1346,7 @@ impl<
         keyspace_receiver: watch::Receiver<Option<String>>,
         contact_points: Vec<SocketAddr>,
         connection_manager: CM,
-        version: Version,
+        version_holder: Arc<VersionHolder>,
     ) -> Session<T, CM, LB> {
         if let Some(keyspace) = self.keyspace {
             keyspace_holder.update_current_keyspace_without_notification(keyspace);
@@ -809,8 +1363,15 @@ impl<
             contact_points,
             connection_manager,
             self.event_channel_capacity,
-            version,
+            version_holder,
             self.connection_pool_config,
+            self.prepared_statement_cache_size,
+            self.prepare_on_all_nodes,
+            self.schema_metadata,
+            self.timeout,
+            self.timestamp_generator,
+            self.metrics_sink,
+            self.request_interceptors,
         )
     }
 }
@@ -863,6 +1424,17 @@ pub trait SessionBuilder<
     #[must_use]
     fn with_tcp_nodelay(self, tcp_nodelay: bool) -> Self;
 
+    /// Sets the maximum number of requests in flight on a single connection, up to
+    /// [`MAX_STREAM_IDS`](crate::transport::MAX_STREAM_IDS). Requests are routed to other
+    /// connections in the pool when one is saturated, or wait for a free slot if all of them are.
+    #[must_use]
+    fn with_max_in_flight_requests(self, max_in_flight_requests: usize) -> Self;
+
+    /// Enables heartbeats on idle connections, including the control connection. Connections
+    /// which don't respond to heartbeats in time are considered broken and get re-established.
+    #[must_use]
+    fn with_heartbeat(self, heartbeat: HeartbeatConfig) -> Self;
+
     /// Sets event channel capacity. If the driver receives more server events than the capacity,
     /// some events might get dropped. This can result in the driver operating in a sub-optimal way.
     #[must_use]
@@ -881,6 +1453,52 @@ pub trait SessionBuilder<
     #[must_use]
     fn with_keyspace(self, keyspace: String) -> Self;
 
+    /// Sets the maximum number of statements cached by [`Session::query_prepared`]. Least recently
+    /// used statements are evicted when the cache is full. Zero disables caching.
+    #[must_use]
+    fn with_prepared_statement_cache_size(self, prepared_statement_cache_size: usize) -> Self;
+
+    /// Makes preparing statements also send the PREPARE request to all up nodes in the background,
+    /// after a single node prepares it. This avoids re-preparing on first execution on other
+    /// nodes, at the cost of more work upfront.
+    #[must_use]
+    fn with_prepare_on_all_nodes(self, prepare_on_all_nodes: bool) -> Self;
+
+    /// Enables fetching full schema metadata - tables, views, user types, functions and
+    /// aggregates - available via [`ClusterMetadata`](crate::cluster::ClusterMetadata). Enabled by
+    /// default. When disabled, only keyspaces are fetched, which avoids querying schema tables of
+    /// large clusters.
+    #[must_use]
+    fn with_schema_metadata(self, schema_metadata: bool) -> Self;
+
+    /// Sets the default time to wait for a response from a single node, after which the retry
+    /// policy decides what to do next. Can be overridden by [`StatementParams::timeout`].
+    #[must_use]
+    fn with_timeout(self, timeout: Duration) -> Self;
+
+    /// Sets new timestamp generator, used for requests without an explicit timestamp. Defaults to
+    /// [`AtomicMonotonicTimestampGenerator`].
+    #[must_use]
+    fn with_timestamp_generator(
+        self,
+        timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    ) -> Self;
+
+    /// Sets new sink for driver metrics. Use
+    /// [`InMemoryMetricsSink`](crate::metrics::InMemoryMetricsSink) to be able to take snapshots
+    /// with [`Session::metrics_snapshot`]. Metrics are discarded by default.
+    #[must_use]
+    fn with_metrics_sink(self, metrics_sink: Arc<dyn MetricsSink + Send + Sync>) -> Self;
+
+    /// Adds a request interceptor to the end of the interceptor chain. Interceptors see all
+    /// requests sent by the session, in order of registration, apart from internal ones - see
+    /// [`request_interceptor`](crate::request_interceptor).
+    #[must_use]
+    fn with_request_interceptor(
+        self,
+        request_interceptor: Arc<dyn RequestInterceptor + Send + Sync>,
+    ) -> Self;
+
     /// Builds the resulting session.
     fn build(self) -> Session<T, CM, LB>;
 }
@@ -953,6 +1571,16 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
         self
     }
 
+    fn with_max_in_flight_requests(mut self, max_in_flight_requests: usize) -> Self {
+        self.config.max_in_flight_requests = max_in_flight_requests;
+        self
+    }
+
+    fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.config.heartbeat = Some(heartbeat);
+        self
+    }
+
     fn with_event_channel_capacity(mut self, event_channel_capacity: usize) -> Self {
         self.config.event_channel_capacity = event_channel_capacity;
         self
@@ -968,8 +1596,50 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
         self
     }
 
+    fn with_prepared_statement_cache_size(mut self, prepared_statement_cache_size: usize) -> Self {
+        self.config.prepared_statement_cache_size = prepared_statement_cache_size;
+        self
+    }
+
+    fn with_prepare_on_all_nodes(mut self, prepare_on_all_nodes: bool) -> Self {
+        self.config.prepare_on_all_nodes = prepare_on_all_nodes;
+        self
+    }
+
+    fn with_schema_metadata(mut self, schema_metadata: bool) -> Self {
+        self.config.schema_metadata = schema_metadata;
+        self
+    }
+
+    fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    fn with_timestamp_generator(
+        mut self,
+        timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    ) -> Self {
+        self.config.timestamp_generator = timestamp_generator;
+        self
+    }
+
+    fn with_metrics_sink(mut self, metrics_sink: Arc<dyn MetricsSink + Send + Sync>) -> Self {
+        self.config.metrics_sink = metrics_sink;
+        self
+    }
+
+    fn with_request_interceptor(
+        mut self,
+        request_interceptor: Arc<dyn RequestInterceptor + Send + Sync>,
+    ) -> Self {
+        self.config.request_interceptors.push(request_interceptor);
+        self
+    }
+
     fn build(self) -> Session<TransportTcp, TcpConnectionManager, LB> {
         let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+        let version_holder = Arc::new(VersionHolder::new(self.node_config.version));
         let connection_manager = TcpConnectionManager::new(
             self.node_config.authenticator_provider,
             keyspace_holder.clone(),
@@ -977,7 +1647,10 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
             self.config.compression,
             self.config.transport_buffer_size,
             self.config.tcp_nodelay,
-            self.node_config.version,
+            self.config.max_in_flight_requests,
+            self.config.heartbeat,
+            self.config.metrics_sink.clone(),
+            version_holder.clone(),
         );
 
         self.config.into_session(
@@ -985,7 +1658,7 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
             keyspace_receiver,
             self.node_config.contact_points,
             connection_manager,
-            self.node_config.version,
+            version_holder,
         )
     }
 }
@@ -1062,6 +1735,16 @@ impl<
         self
     }
 
+    fn with_max_in_flight_requests(mut self, max_in_flight_requests: usize) -> Self {
+        self.config.max_in_flight_requests = max_in_flight_requests;
+        self
+    }
+
+    fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.config.heartbeat = Some(heartbeat);
+        self
+    }
+
     fn with_event_channel_capacity(mut self, event_channel_capacity: usize) -> Self {
         self.config.event_channel_capacity = event_channel_capacity;
         self
@@ -1077,8 +1760,50 @@ impl<
         self
     }
 
+    fn with_prepared_statement_cache_size(mut self, prepared_statement_cache_size: usize) -> Self {
+        self.config.prepared_statement_cache_size = prepared_statement_cache_size;
+        self
+    }
+
+    fn with_prepare_on_all_nodes(mut self, prepare_on_all_nodes: bool) -> Self {
+        self.config.prepare_on_all_nodes = prepare_on_all_nodes;
+        self
+    }
+
+    fn with_schema_metadata(mut self, schema_metadata: bool) -> Self {
+        self.config.schema_metadata = schema_metadata;
+        self
+    }
+
+    fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    fn with_timestamp_generator(
+        mut self,
+        timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    ) -> Self {
+        self.config.timestamp_generator = timestamp_generator;
+        self
+    }
+
+    fn with_metrics_sink(mut self, metrics_sink: Arc<dyn MetricsSink + Send + Sync>) -> Self {
+        self.config.metrics_sink = metrics_sink;
+        self
+    }
+
+    fn with_request_interceptor(
+        mut self,
+        request_interceptor: Arc<dyn RequestInterceptor + Send + Sync>,
+    ) -> Self {
+        self.config.request_interceptors.push(request_interceptor);
+        self
+    }
+
     fn build(self) -> Session<TransportRustls, RustlsConnectionManager, LB> {
         let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+        let version_holder = Arc::new(VersionHolder::new(self.node_config.version));
         let connection_manager = RustlsConnectionManager::new(
             self.node_config.dns_name,
             self.node_config.authenticator_provider,
@@ -1088,7 +1813,10 @@ impl<
             self.config.compression,
             self.config.transport_buffer_size,
             self.config.tcp_nodelay,
-            self.node_config.version,
+            self.config.max_in_flight_requests,
+            self.config.heartbeat,
+            self.config.metrics_sink.clone(),
+            version_holder.clone(),
         );
 
         self.config.into_session(
@@ -1096,7 +1824,355 @@ impl<
             keyspace_receiver,
             self.node_config.contact_points,
             connection_manager,
-            self.node_config.version,
+            version_holder,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error;
+    use cassandra_protocol::frame::frame_prepare::BodyReqPrepare;
+    use cassandra_protocol::frame::frame_request::RequestBody;
+    use cassandra_protocol::frame::frame_result::{
+        BodyResResultPrepared, PreparedMetadata, ResResultBody, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::{Direction, Flags, Frame, Opcode, Serialize, Version};
+    use cassandra_protocol::query::{QueryParams, QueryValues};
+    use cassandra_protocol::types::CBytesShort;
+    use futures::{future, FutureExt};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::{create_keyspace_holder, Session, DEFAULT_EVENT_CHANNEL_CAPACITY};
+    use crate::cluster::connection_manager::{mock_connection_manager, MockConnectionManager};
+    use crate::cluster::{VersionHolder, DEFAULT_PREPARED_STATEMENT_CACHE_SIZE};
+    use crate::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
+    use crate::load_balancing::RoundRobinLoadBalancingStrategy;
+    use crate::metrics::NoopMetricsSink;
+    use crate::request_interceptor::RequestInterceptor;
+    use crate::retry::{DefaultRetryPolicy, ExponentialReconnectionPolicy};
+    use crate::statement::{StatementParams, StatementParamsBuilder};
+    use crate::timestamp_generator::AtomicMonotonicTimestampGenerator;
+    use crate::transport::MockCdrsTransport;
+
+    type MockSession = Session<
+        MockCdrsTransport,
+        MockConnectionManager<MockCdrsTransport>,
+        RoundRobinLoadBalancingStrategy<
+            MockCdrsTransport,
+            MockConnectionManager<MockCdrsTransport>,
+        >,
+    >;
+
+    fn response(body: ResResultBody) -> Frame {
+        Frame::new(
+            Version::V5,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec().into(),
+            None,
+            vec![],
+            Default::default(),
+        )
+    }
+
+    // responds with a prepared statement to PREPARE and with VOID to everything else
+    fn respond(frame: &Frame) -> Frame {
+        if frame.opcode == Opcode::Prepare {
+            response(ResResultBody::Prepared(BodyResResultPrepared {
+                id: CBytesShort::new(vec![1]),
+                result_metadata_id: Some(CBytesShort::new(vec![2])),
+                metadata: PreparedMetadata {
+                    pk_indexes: vec![],
+                    global_table_spec: None,
+                    col_specs: vec![],
+                },
+                result_metadata: RowsMetadata {
+                    flags: RowsMetadataFlags::NO_METADATA,
+                    columns_count: 0,
+                    paging_state: None,
+                    new_metadata_id: None,
+                    global_table_spec: None,
+                    col_specs: vec![],
+                },
+            }))
+        } else {
+            response(ResResultBody::Void)
+        }
+    }
+
+    // creates a session with a single node, which records all received frames
+    fn create_session(
+        request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
+    ) -> (MockSession, Arc<Mutex<Vec<Frame>>>) {
+        let frames = Arc::new(Mutex::new(vec![]));
+        let connection_manager = mock_connection_manager(Arc::new({
+            let frames = frames.clone();
+            move |frame| {
+                frames.lock().unwrap().push(frame.clone());
+                Ok(respond(frame))
+            }
+        }));
+
+        let session = create_session_with_manager(connection_manager, false, request_interceptors);
+        (session, frames)
+    }
+
+    fn create_session_with_manager(
+        connection_manager: MockConnectionManager<MockCdrsTransport>,
+        prepare_on_all_nodes: bool,
+        request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
+    ) -> MockSession {
+        let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+        Session::new(
+            RoundRobinLoadBalancingStrategy::new(),
+            keyspace_holder,
+            keyspace_receiver,
+            Box::<DefaultRetryPolicy>::default(),
+            Arc::new(ExponentialReconnectionPolicy::default()),
+            Box::<AllLocalNodeDistanceEvaluator>::default(),
+            None,
+            vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042)],
+            connection_manager,
+            DEFAULT_EVENT_CHANNEL_CAPACITY,
+            Arc::new(VersionHolder::new_negotiated(Version::V5)),
+            Default::default(),
+            DEFAULT_PREPARED_STATEMENT_CACHE_SIZE,
+            prepare_on_all_nodes,
+            true,
+            None,
+            Box::<AtomicMonotonicTimestampGenerator>::default(),
+            Arc::new(NoopMetricsSink),
+            request_interceptors,
         )
     }
+
+    fn sent_frames(frames: &Mutex<Vec<Frame>>, opcode: Opcode) -> Vec<Frame> {
+        frames
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|frame| frame.opcode == opcode)
+            .cloned()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_prepare_in_statement_keyspace() {
+        let (session, frames) = create_session(vec![]);
+
+        let prepared = session
+            .prepare_with_params(
+                "SELECT * FROM t",
+                &StatementParamsBuilder::new()
+                    .with_keyspace("ks".into())
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(prepared.keyspace.as_deref(), Some("ks"));
+
+        let prepare_frames = sent_frames(&frames, Opcode::Prepare);
+        assert_eq!(prepare_frames.len(), 1);
+
+        // query, followed by the keyspace flag and keyspace name
+        let body = &prepare_frames[0].body;
+        assert_eq!(&body[19..23], &[0, 0, 0, 1]);
+        assert_eq!(&body[23..], &[0, 2, b'k', b's']);
+
+        match prepare_frames[0].request_body().unwrap() {
+            RequestBody::Prepare(body) => assert_eq!(
+                body,
+                BodyReqPrepare::new("SELECT * FROM t".into(), Some("ks".into()))
+            ),
+            _ => panic!("PREPARE expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reuse_prepared_statement_in_cache() {
+        let (session, frames) = create_session(vec![]);
+
+        let prepared = session.prepare("SELECT * FROM ks.t").await.unwrap();
+        let cached = session.prepare_cached("SELECT * FROM ks.t").await.unwrap();
+
+        assert_eq!(cached.id(), prepared.id());
+        assert_eq!(sent_frames(&frames, Opcode::Prepare).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_send_custom_payload_with_all_requests() {
+        let (session, frames) = create_session(vec![]);
+
+        let custom_payload: HashMap<String, Vec<u8>> = vec![("tag".to_string(), b"value".to_vec())]
+            .into_iter()
+            .collect();
+        let parameters = StatementParamsBuilder::new()
+            .with_custom_payload(custom_payload.clone())
+            .build();
+
+        let prepared = session
+            .prepare_with_params("SELECT * FROM ks.t", &parameters)
+            .await
+            .unwrap();
+        session
+            .exec_with_params(&prepared, &parameters)
+            .await
+            .unwrap();
+        session
+            .query_with_params("SELECT * FROM ks.t", parameters)
+            .await
+            .unwrap();
+
+        for &opcode in &[Opcode::Prepare, Opcode::Execute, Opcode::Query] {
+            let sent_frames = sent_frames(&frames, opcode);
+            assert_eq!(sent_frames.len(), 1);
+
+            let encoded = sent_frames[0].encode_with(Compression::None).unwrap();
+            let decoded = Frame::from_buffer(&encoded, Compression::None)
+                .unwrap()
+                .frame;
+
+            assert!(decoded.flags.contains(Flags::CUSTOM_PAYLOAD));
+            assert_eq!(decoded.custom_payload, custom_payload);
+            assert_eq!(decoded.body, sent_frames[0].body);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_prepare_on_all_nodes_with_request_timeout() {
+        // the node never responds
+        let mut connection_manager = MockConnectionManager::new();
+        connection_manager
+            .expect_connection()
+            .returning(|_, _, addr| {
+                let mut transport = MockCdrsTransport::new();
+                transport
+                    .expect_write_frame()
+                    .returning(|_| future::pending().boxed());
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+
+                future::ready(Ok(transport)).boxed()
+            });
+
+        let session = create_session_with_manager(connection_manager, true, vec![]);
+
+        let result = session
+            .prepare_with_params(
+                "SELECT * FROM ks.t",
+                &StatementParamsBuilder::new()
+                    .with_timeout(Duration::from_millis(10))
+                    .build(),
+            )
+            .await;
+
+        assert!(matches!(result, Err(error::Error::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn should_prepare_with_primary_request_on_all_nodes() {
+        let frames = Arc::new(Mutex::new(vec![]));
+        let connection_manager = mock_connection_manager(Arc::new({
+            let frames = frames.clone();
+            move |frame| {
+                frames.lock().unwrap().push(frame.clone());
+                Ok(respond(frame))
+            }
+        }));
+
+        let session = create_session_with_manager(connection_manager, true, vec![]);
+
+        let prepared = session.prepare("SELECT * FROM ks.t").await.unwrap();
+        assert_eq!(*prepared.id(), CBytesShort::new(vec![1]));
+        assert_eq!(sent_frames(&frames, Opcode::Prepare).len(), 1);
+    }
+
+    struct TaggingInterceptor;
+
+    impl RequestInterceptor for TaggingInterceptor {
+        fn before_request(
+            &self,
+            _frame: &mut Frame,
+            parameters: &mut StatementParams,
+        ) -> error::Result<Option<Frame>> {
+            parameters
+                .custom_payload
+                .insert("tag".into(), b"value".to_vec());
+            Ok(None)
+        }
+    }
+
+    struct RewritingInterceptor;
+
+    impl RequestInterceptor for RewritingInterceptor {
+        fn before_request(
+            &self,
+            _frame: &mut Frame,
+            parameters: &mut StatementParams,
+        ) -> error::Result<Option<Frame>> {
+            parameters.query_params.consistency = Consistency::Quorum;
+            parameters.query_params.values = Some(QueryValues::SimpleValues(vec![5.into()]));
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_query_params_modified_by_interceptor() {
+        let (session, frames) = create_session(vec![Arc::new(RewritingInterceptor)]);
+
+        let prepared = session.prepare("SELECT * FROM ks.t").await.unwrap();
+        session.exec(&prepared).await.unwrap();
+        session.query("SELECT * FROM ks.t").await.unwrap();
+
+        let execute_frames = sent_frames(&frames, Opcode::Execute);
+        assert_eq!(execute_frames.len(), 1);
+
+        // skip statement and result metadata ids
+        let mut cursor = Cursor::new(&execute_frames[0].body[6..]);
+        let query_params = QueryParams::from_cursor(&mut cursor, Version::V5).unwrap();
+        assert_eq!(query_params.consistency, Consistency::Quorum);
+        assert_eq!(
+            query_params.values,
+            Some(QueryValues::SimpleValues(vec![5.into()]))
+        );
+
+        let query_frames = sent_frames(&frames, Opcode::Query);
+        assert_eq!(query_frames.len(), 1);
+
+        match query_frames[0].request_body().unwrap() {
+            RequestBody::Query(body) => {
+                assert_eq!(body.query_params.consistency, Consistency::Quorum);
+                assert_eq!(
+                    body.query_params.values,
+                    Some(QueryValues::SimpleValues(vec![5.into()]))
+                );
+            }
+            _ => panic!("QUERY expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_custom_payload_set_by_interceptor() {
+        let (session, frames) = create_session(vec![Arc::new(TaggingInterceptor)]);
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+
+        let query_frames = sent_frames(&frames, Opcode::Query);
+        assert_eq!(query_frames.len(), 1);
+        assert_eq!(
+            query_frames[0].custom_payload,
+            vec![("tag".to_string(), b"value".to_vec())]
+                .into_iter()
+                .collect()
+        );
+    }
 }
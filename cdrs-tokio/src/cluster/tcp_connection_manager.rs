@@ -1,4 +1,3 @@
-use derive_more::Constructor;
 use futures::FutureExt;
 use std::net::SocketAddr;
 use std::ops::Deref;
@@ -6,17 +5,19 @@ use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::time::sleep;
 
-use crate::cluster::connection_manager::{startup, ConnectionManager};
-use crate::cluster::KeyspaceHolder;
+use crate::cluster::connection_manager::{
+    fetch_supported_options, negotiate_version, select_compression, startup, ConnectionManager,
+};
+use crate::cluster::{KeyspaceHolder, VersionHolder};
 use crate::future::BoxFuture;
+use crate::metrics::MetricsSink;
 use crate::retry::ReconnectionPolicy;
-use crate::transport::TransportTcp;
+use crate::transport::{HeartbeatConfig, TransportTcp};
 use cassandra_protocol::authenticators::SaslAuthenticatorProvider;
 use cassandra_protocol::compression::Compression;
 use cassandra_protocol::error::{Error, Result};
 use cassandra_protocol::frame::{Frame, Version};
 
-#[derive(Constructor)]
 pub struct TcpConnectionManager {
     authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
     keyspace_holder: Arc<KeyspaceHolder>,
@@ -24,7 +25,10 @@ pub struct TcpConnectionManager {
     compression: Compression,
     buffer_size: usize,
     tcp_nodelay: bool,
-    version: Version,
+    max_in_flight_requests: usize,
+    heartbeat: Option<HeartbeatConfig>,
+    metrics: Arc<dyn MetricsSink + Send + Sync>,
+    version_holder: Arc<VersionHolder>,
 }
 
 impl ConnectionManager<TransportTcp> for TcpConnectionManager {
@@ -55,32 +59,86 @@ impl ConnectionManager<TransportTcp> for TcpConnectionManager {
 }
 
 impl TcpConnectionManager {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
+        keyspace_holder: Arc<KeyspaceHolder>,
+        reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+        compression: Compression,
+        buffer_size: usize,
+        tcp_nodelay: bool,
+        max_in_flight_requests: usize,
+        heartbeat: Option<HeartbeatConfig>,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
+        version_holder: Arc<VersionHolder>,
+    ) -> Self {
+        TcpConnectionManager {
+            authenticator_provider,
+            keyspace_holder,
+            reconnection_policy,
+            compression,
+            buffer_size,
+            tcp_nodelay,
+            max_in_flight_requests,
+            heartbeat,
+            metrics,
+            version_holder,
+        }
+    }
+
     async fn establish_connection(
         &self,
         event_handler: Option<Sender<Frame>>,
         error_handler: Option<Sender<Error>>,
         addr: SocketAddr,
     ) -> Result<TransportTcp> {
-        let transport = TransportTcp::new(
+        negotiate_version(&self.version_holder, self.compression, |version| {
+            self.establish_connection_with_version(
+                event_handler.clone(),
+                error_handler.clone(),
+                addr,
+                version,
+            )
+        })
+        .await
+    }
+
+    async fn establish_connection_with_version(
+        &self,
+        event_handler: Option<Sender<Frame>>,
+        error_handler: Option<Sender<Error>>,
+        addr: SocketAddr,
+        version: Version,
+    ) -> Result<TransportTcp> {
+        let mut transport = TransportTcp::new(
             addr,
             self.keyspace_holder.clone(),
             event_handler,
             error_handler,
-            self.compression,
+            Compression::None,
             self.buffer_size,
             self.tcp_nodelay,
+            self.max_in_flight_requests,
+            self.heartbeat,
+            self.metrics.clone(),
         )
         .await?;
 
+        let supported_options = fetch_supported_options(&transport, version).await?;
+        let compression = select_compression(self.compression, &supported_options, version);
+        transport.set_compression(compression);
+
         startup(
             &transport,
             self.authenticator_provider.deref(),
             self.keyspace_holder.deref(),
-            self.compression,
-            self.version,
+            compression,
+            version,
         )
         .await?;
 
+        transport.set_supported_options(supported_options);
+
         Ok(transport)
     }
 }
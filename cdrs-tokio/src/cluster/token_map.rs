@@ -121,6 +121,8 @@ mod tests {
     use crate::cluster::connection_pool::ConnectionPoolFactory;
     use crate::cluster::topology::{Node, NodeMap};
     use crate::cluster::TokenMap;
+    use crate::cluster::VersionHolder;
+    use crate::metrics::NoopMetricsSink;
     use crate::transport::MockCdrsTransport;
 
     lazy_static! {
@@ -134,9 +136,10 @@ mod tests {
         let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
         let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
             Default::default(),
-            Version::V4,
+            Arc::new(VersionHolder::new_negotiated(Version::V4)),
             connection_manager,
             keyspace_receiver,
+            Arc::new(NoopMetricsSink),
         ));
 
         let mut nodes = NodeMap::default();
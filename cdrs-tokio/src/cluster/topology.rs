@@ -5,18 +5,27 @@ use uuid::Uuid;
 
 pub mod cluster_metadata;
 mod datacenter_metadata;
+mod function_metadata;
 mod keyspace_metadata;
 mod node;
 mod node_distance;
 mod node_state;
 mod replication_strategy;
+mod table_metadata;
+mod user_type_metadata;
 
 pub use self::datacenter_metadata::DatacenterMetadata;
+pub use self::function_metadata::{AggregateMetadata, FunctionMetadata};
 pub use self::keyspace_metadata::KeyspaceMetadata;
 pub use self::node::Node;
 pub use self::node_distance::NodeDistance;
 pub use self::node_state::NodeState;
 pub use self::replication_strategy::ReplicationStrategy;
+pub use self::table_metadata::{
+    ClusteringOrder, ColumnKind, ColumnMetadata, IndexKind, IndexMetadata, TableMetadata,
+    ViewMetadata,
+};
+pub use self::user_type_metadata::UserTypeMetadata;
 
 /// Map from host id to a node.
 pub type NodeMap<T, CM> = FxHashMap<Uuid, Arc<Node<T, CM>>>;
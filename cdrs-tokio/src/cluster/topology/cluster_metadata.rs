@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 use crate::cluster::topology::keyspace_metadata::KeyspaceMetadata;
 use crate::cluster::topology::node::Node;
-use crate::cluster::topology::{DatacenterMetadata, NodeMap};
+use crate::cluster::topology::{DatacenterMetadata, NodeMap, TableMetadata, UserTypeMetadata};
 use crate::cluster::{ConnectionManager, TokenMap};
 use crate::transport::CdrsTransport;
 
@@ -87,7 +87,12 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ClusterMetadata<T, CM> {
     /// Creates a new metadata with a node replaced/added. The node must have a host id.
     #[must_use]
     pub fn clone_with_node(&self, node: Node<T, CM>) -> Self {
-        let node = Arc::new(node);
+        self.clone_with_shared_node(Arc::new(node))
+    }
+
+    // same as clone_with_node(), but keeps given node instance along with its connections
+    #[must_use]
+    pub(crate) fn clone_with_shared_node(&self, node: Arc<Node<T, CM>>) -> Self {
         let token_map = self.token_map.clone_with_node(node.clone());
 
         let mut nodes = self.nodes.clone();
@@ -148,6 +153,22 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ClusterMetadata<T, CM> {
         &self.datacenters
     }
 
+    /// Returns known table, if present.
+    #[inline]
+    pub fn table(&self, keyspace: &str, table: &str) -> Option<&TableMetadata> {
+        self.keyspaces
+            .get(keyspace)
+            .and_then(|keyspace| keyspace.tables.get(table))
+    }
+
+    /// Returns known user defined type, if present.
+    #[inline]
+    pub fn user_type(&self, keyspace: &str, name: &str) -> Option<&UserTypeMetadata> {
+        self.keyspaces
+            .get(keyspace)
+            .and_then(|keyspace| keyspace.user_types.get(name))
+    }
+
     /// Returns known datacenter, if present.
     #[inline]
     pub fn datacenter(&self, name: &str) -> Option<&DatacenterMetadata> {
@@ -258,6 +279,8 @@ mod tests {
     use crate::cluster::connection_pool::ConnectionPoolFactory;
     use crate::cluster::topology::cluster_metadata::build_datacenter_info;
     use crate::cluster::topology::Node;
+    use crate::cluster::VersionHolder;
+    use crate::metrics::NoopMetricsSink;
     use crate::transport::MockCdrsTransport;
 
     #[test]
@@ -266,9 +289,10 @@ mod tests {
         let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
         let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
             Default::default(),
-            Version::V4,
+            Arc::new(VersionHolder::new_negotiated(Version::V4)),
             connection_manager,
             keyspace_receiver,
+            Arc::new(NoopMetricsSink),
         ));
 
         let mut nodes = FxHashMap::default();
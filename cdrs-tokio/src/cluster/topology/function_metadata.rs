@@ -0,0 +1,28 @@
+use cassandra_protocol::frame::frame_result::ColTypeOption;
+
+/// User defined function metadata. Functions can be overloaded, so a name alone does not identify
+/// a function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionMetadata {
+    pub name: String,
+    pub argument_names: Vec<String>,
+    pub argument_types: Vec<ColTypeOption>,
+    pub return_type: ColTypeOption,
+    pub language: String,
+    pub body: String,
+    pub called_on_null_input: bool,
+}
+
+/// User defined aggregate metadata. Aggregates can be overloaded, so a name alone does not
+/// identify an aggregate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregateMetadata {
+    pub name: String,
+    pub argument_types: Vec<ColTypeOption>,
+    pub return_type: ColTypeOption,
+    pub state_func: String,
+    pub state_type: ColTypeOption,
+    pub final_func: Option<String>,
+    /// Initial state value, as a CQL literal.
+    pub initcond: Option<String>,
+}
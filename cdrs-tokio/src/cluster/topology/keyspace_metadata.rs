@@ -1,9 +1,34 @@
-use derive_more::Constructor;
+use fxhash::FxHashMap;
 
-use crate::cluster::topology::ReplicationStrategy;
+use crate::cluster::topology::{
+    AggregateMetadata, FunctionMetadata, ReplicationStrategy, TableMetadata, UserTypeMetadata,
+    ViewMetadata,
+};
 
 /// Keyspace metadata.
-#[derive(Clone, Debug, Constructor)]
+#[derive(Clone, Debug)]
 pub struct KeyspaceMetadata {
     pub replication_strategy: ReplicationStrategy,
+    /// Tables by name.
+    pub tables: FxHashMap<String, TableMetadata>,
+    /// Materialized views by name.
+    pub views: FxHashMap<String, ViewMetadata>,
+    /// User defined types by name.
+    pub user_types: FxHashMap<String, UserTypeMetadata>,
+    pub functions: Vec<FunctionMetadata>,
+    pub aggregates: Vec<AggregateMetadata>,
+}
+
+impl KeyspaceMetadata {
+    /// Creates keyspace metadata without any schema elements.
+    pub fn new(replication_strategy: ReplicationStrategy) -> Self {
+        KeyspaceMetadata {
+            replication_strategy,
+            tables: Default::default(),
+            views: Default::default(),
+            user_types: Default::default(),
+            functions: vec![],
+            aggregates: vec![],
+        }
+    }
 }
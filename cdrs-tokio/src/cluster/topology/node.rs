@@ -1,11 +1,13 @@
 use atomic::Atomic;
 use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::frame_supported::BodyResSupported;
 use cassandra_protocol::frame::Frame;
 use cassandra_protocol::token::Murmur3Token;
 use std::fmt::{Debug, Formatter};
 use std::net::SocketAddr;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::OnceCell;
 use tracing::*;
@@ -191,6 +193,22 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         &self.rack
     }
 
+    /// Returns the CQL version reported by the node. Available after a connection to the node has
+    /// been established.
+    #[inline]
+    pub fn cql_version(&self) -> Option<&str> {
+        self.supported_options()
+            .and_then(|supported| supported.cql_version())
+    }
+
+    /// Returns the product type reported by the node, if any. Available after a connection to the
+    /// node has been established.
+    #[inline]
+    pub fn product_type(&self) -> Option<&str> {
+        self.supported_options()
+            .and_then(|supported| supported.product_type())
+    }
+
     /// Returns a connection to given node.
     #[inline]
     pub async fn persistent_connection(&self) -> Result<Arc<T>> {
@@ -220,6 +238,16 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             .await
     }
 
+    /// Closes the connection pool to this node, if established, after requests in flight complete
+    /// or given drain timeout elapses. No new pools can be created afterwards.
+    pub(crate) async fn close(&self, drain_timeout: Duration) {
+        self.connection_pool_factory.close();
+
+        if let Some(pool) = self.connection_pool.get() {
+            pool.close(drain_timeout).await;
+        }
+    }
+
     /// Returns node distance in relation to the driver, if available.
     #[inline]
     pub fn distance(&self) -> Option<NodeDistance> {
@@ -244,6 +272,13 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         self.distance.is_none() || self.state.load(Ordering::Relaxed) != NodeState::Up
     }
 
+    #[inline]
+    fn supported_options(&self) -> Option<&BodyResSupported> {
+        self.connection_pool
+            .get()
+            .and_then(|pool| pool.supported_options())
+    }
+
     #[inline]
     pub(crate) fn clone_with_node_info(&self, node_info: NodeInfo) -> Self {
         Node {
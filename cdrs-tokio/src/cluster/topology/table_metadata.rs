@@ -0,0 +1,72 @@
+use cassandra_protocol::frame::frame_result::ColTypeOption;
+use fxhash::FxHashMap;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Kind of a column in a table or a materialized view.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColumnKind {
+    PartitionKey,
+    Clustering,
+    Regular,
+    Static,
+}
+
+/// Order of rows within a partition, for a single clustering column.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ClusteringOrder {
+    Ascending,
+    Descending,
+}
+
+/// Column metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnMetadata {
+    pub kind: ColumnKind,
+    pub col_type: ColTypeOption,
+}
+
+/// Kind of a secondary index.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IndexKind {
+    Keys,
+    Custom,
+    Composites,
+}
+
+/// Secondary index metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexMetadata {
+    pub kind: IndexKind,
+    /// Index options, including the indexed `target`.
+    pub options: HashMap<String, String>,
+}
+
+/// Table metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableMetadata {
+    pub id: Option<Uuid>,
+    /// All columns of the table by name.
+    pub columns: FxHashMap<String, ColumnMetadata>,
+    /// Partition key column names, in order.
+    pub partition_key: Vec<String>,
+    /// Clustering column names with their ordering, in order.
+    pub clustering_key: Vec<(String, ClusteringOrder)>,
+    /// Secondary indexes by name.
+    pub indexes: FxHashMap<String, IndexMetadata>,
+}
+
+/// Materialized view metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViewMetadata {
+    pub id: Option<Uuid>,
+    pub base_table_name: String,
+    pub include_all_columns: bool,
+    pub where_clause: String,
+    /// All columns of the view by name.
+    pub columns: FxHashMap<String, ColumnMetadata>,
+    /// Partition key column names, in order.
+    pub partition_key: Vec<String>,
+    /// Clustering column names with their ordering, in order.
+    pub clustering_key: Vec<(String, ClusteringOrder)>,
+}
@@ -0,0 +1,8 @@
+use cassandra_protocol::frame::frame_result::ColTypeOption;
+
+/// User defined type metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserTypeMetadata {
+    /// Field names with their types, in order.
+    pub fields: Vec<(String, ColTypeOption)>,
+}
@@ -0,0 +1,84 @@
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use cassandra_protocol::frame::Version;
+
+const NEGOTIATED_FLAG: u8 = 0x80;
+
+/// Holds protocol version used by a session. Starts with the highest version to try, which can get
+/// lowered during negotiation with the first node. Once negotiated, the version stays fixed.
+#[derive(Debug)]
+pub struct VersionHolder {
+    // protocol version with an additional flag bit, so both can be updated atomically
+    state: AtomicU8,
+}
+
+impl VersionHolder {
+    /// Creates a new holder, which will try to negotiate given version or lower.
+    pub fn new(version: Version) -> Self {
+        VersionHolder {
+            state: AtomicU8::new(version.into()),
+        }
+    }
+
+    /// Creates a new holder with already negotiated version.
+    pub fn new_negotiated(version: Version) -> Self {
+        VersionHolder {
+            state: AtomicU8::new(u8::from(version) | NEGOTIATED_FLAG),
+        }
+    }
+
+    /// Returns current version - either the negotiated one or the one to try.
+    #[inline]
+    pub fn version(&self) -> Version {
+        Self::decode_version(self.state.load(Ordering::Relaxed))
+    }
+
+    #[inline]
+    pub fn is_negotiated(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & NEGOTIATED_FLAG != 0
+    }
+
+    /// Marks given version as negotiated, unless another one has been negotiated already. Returns
+    /// the resulting negotiated version.
+    pub fn negotiate(&self, version: Version) -> Version {
+        let result = self
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |state| {
+                if state & NEGOTIATED_FLAG != 0 {
+                    None
+                } else {
+                    Some(u8::from(version) | NEGOTIATED_FLAG)
+                }
+            });
+
+        match result {
+            Ok(_) => version,
+            Err(state) => Self::decode_version(state),
+        }
+    }
+
+    #[inline]
+    fn decode_version(state: u8) -> Version {
+        Version::try_from(state & !NEGOTIATED_FLAG)
+            .expect("CDRS BUG: invalid protocol version in holder")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_negotiate_once() {
+        let holder = VersionHolder::new(Version::V5);
+        assert!(!holder.is_negotiated());
+        assert_eq!(holder.version(), Version::V5);
+
+        assert_eq!(holder.negotiate(Version::V4), Version::V4);
+        assert!(holder.is_negotiated());
+
+        assert_eq!(holder.negotiate(Version::V5), Version::V4);
+        assert_eq!(holder.version(), Version::V4);
+    }
+}
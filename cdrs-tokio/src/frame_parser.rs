@@ -1,3 +1,5 @@
+use bytes::Bytes;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::Cursor;
 use tokio::io::AsyncReadExt;
@@ -5,13 +7,15 @@ use tokio::io::AsyncReadExt;
 use cassandra_protocol::compression::Compression;
 use cassandra_protocol::error;
 use cassandra_protocol::frame::frame_response::ResponseBody;
+use cassandra_protocol::frame::segment::{Segment, SegmentFrameDecoder};
 use cassandra_protocol::frame::{Direction, Flags, Frame, Opcode, Version, LENGTH_LEN, STREAM_LEN};
 use cassandra_protocol::types::data_serialization_types::decode_timeuuid;
 use cassandra_protocol::types::{
-    from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes, UUID_LEN,
+    from_cursor_bytes_map, from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes,
+    UUID_LEN,
 };
 
-async fn parse_raw_frame<T: AsyncReadExt + Unpin>(
+pub(crate) async fn parse_raw_frame<T: AsyncReadExt + Unpin>(
     cursor: &mut T,
     compressor: Compression,
 ) -> error::Result<Frame> {
@@ -45,9 +49,7 @@ async fn parse_raw_frame<T: AsyncReadExt + Unpin>(
         Compression::None.decode(body_bytes)?
     };
 
-    let body_len = full_body.len();
-
-    // Use cursor to get tracing id, warnings and actual body
+    // Use cursor to get tracing id, warnings, custom payload and actual body
     let mut body_cursor = Cursor::new(full_body.as_slice());
 
     let tracing_id = if flags.contains(Flags::TRACING) {
@@ -65,9 +67,15 @@ async fn parse_raw_frame<T: AsyncReadExt + Unpin>(
         vec![]
     };
 
-    let mut body = Vec::with_capacity(body_len - body_cursor.position() as usize);
+    let custom_payload = if flags.contains(Flags::CUSTOM_PAYLOAD) {
+        from_cursor_bytes_map(&mut body_cursor)?
+    } else {
+        HashMap::new()
+    };
 
-    std::io::Read::read_to_end(&mut body_cursor, &mut body)?;
+    // the body shares the decoded buffer, so parsed rows can reference it without copying
+    let body_start = body_cursor.position() as usize;
+    let body = Bytes::from(full_body).slice(body_start..);
 
     let frame = Frame {
         version,
@@ -78,6 +86,7 @@ async fn parse_raw_frame<T: AsyncReadExt + Unpin>(
         body,
         tracing_id,
         warnings,
+        custom_payload,
     };
 
     Ok(frame)
@@ -91,7 +100,33 @@ pub async fn parse_frame<T: AsyncReadExt + Unpin>(
     convert_frame_into_result(frame)
 }
 
-fn convert_frame_into_result(frame: Frame) -> error::Result<Frame> {
+/// Reads a single protocol v5 segment and returns all frames completed by it. Frames spanning
+/// multiple segments are accumulated in the given decoder. Note: unlike [`parse_frame`], error
+/// frames are returned as-is.
+pub async fn parse_segment_frames<T: AsyncReadExt + Unpin>(
+    cursor: &mut T,
+    compressor: Compression,
+    decoder: &mut SegmentFrameDecoder,
+) -> error::Result<Vec<Frame>> {
+    let header_len = Segment::header_len(compressor);
+
+    let mut data = vec![0; header_len];
+    cursor.read_exact(&mut data).await?;
+
+    let segment_len = Segment::len_from_header(&data, compressor)
+        .map_err(|error| error::Error::General(format!("Invalid segment: {:?}", error)))?;
+
+    data.resize(segment_len, 0);
+    cursor.read_exact(&mut data[header_len..]).await?;
+
+    let segment = Segment::from_buffer(&data, compressor)
+        .map_err(|error| error::Error::General(format!("Invalid segment: {:?}", error)))?
+        .segment;
+
+    decoder.decode(segment)
+}
+
+pub(crate) fn convert_frame_into_result(frame: Frame) -> error::Result<Frame> {
     match frame.opcode {
         Opcode::Error => frame.response_body().and_then(|err| match err {
             ResponseBody::Error(err) => Err(error::Error::Server(err)),
@@ -45,9 +45,13 @@ pub mod frame_parser;
 pub mod load_balancing;
 
 pub mod future;
+pub mod metrics;
+pub mod query_trace;
+pub mod request_interceptor;
 pub mod retry;
 pub mod speculative_execution;
 pub mod statement;
+pub mod timestamp_generator;
 pub mod transport;
 
 pub use cassandra_protocol::authenticators;
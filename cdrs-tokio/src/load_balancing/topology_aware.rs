@@ -311,9 +311,11 @@ mod tests {
         KeyspaceMetadata, Node, NodeDistance, NodeState, ReplicationStrategy,
     };
     use crate::cluster::ClusterMetadata;
+    use crate::cluster::VersionHolder;
     use crate::load_balancing::{
         LoadBalancingStrategy, Request, TopologyAwareLoadBalancingStrategy,
     };
+    use crate::metrics::NoopMetricsSink;
     use crate::transport::MockCdrsTransport;
 
     lazy_static! {
@@ -330,9 +332,10 @@ mod tests {
         let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
         let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
             Default::default(),
-            Version::V4,
+            Arc::new(VersionHolder::new_negotiated(Version::V4)),
             connection_manager,
             keyspace_receiver,
+            Arc::new(NoopMetricsSink),
         ));
 
         let mut nodes = FxHashMap::default();
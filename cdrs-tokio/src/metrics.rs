@@ -0,0 +1,430 @@
+//! Driver metrics.
+//!
+//! The driver reports what it's doing to a [`MetricsSink`] set with
+//! `SessionBuilder::with_metrics_sink()`. By default, metrics are discarded by
+//! [`NoopMetricsSink`]. [`InMemoryMetricsSink`] aggregates them in memory and allows taking
+//! snapshots with `Session::metrics_snapshot()`, e.g. for exporting to a monitoring system.
+
+use cassandra_protocol::error::Error;
+use cassandra_protocol::frame::frame_error::AdditionalErrorInfo;
+use fxhash::FxHashMap;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// Upper bounds of latency histogram buckets, in microseconds.
+pub const LATENCY_BUCKETS_MICROS: [u64; 16] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// Kind of an error returned by a node, for grouping errors in metrics. Server errors are grouped
+/// by their [`AdditionalErrorInfo`] kind. Timeouts are recorded separately with
+/// [`MetricsSink::record_timeout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub enum ErrorKind {
+    Server,
+    Protocol,
+    Authentication,
+    Unavailable,
+    Overloaded,
+    IsBootstrapping,
+    Truncate,
+    WriteTimeout,
+    ReadTimeout,
+    ReadFailure,
+    FunctionFailure,
+    WriteFailure,
+    Syntax,
+    Unauthorized,
+    Invalid,
+    Config,
+    AlreadyExists,
+    Unprepared,
+    Io,
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns a name suitable for a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Server => "server",
+            ErrorKind::Protocol => "protocol",
+            ErrorKind::Authentication => "authentication",
+            ErrorKind::Unavailable => "unavailable",
+            ErrorKind::Overloaded => "overloaded",
+            ErrorKind::IsBootstrapping => "is_bootstrapping",
+            ErrorKind::Truncate => "truncate",
+            ErrorKind::WriteTimeout => "write_timeout",
+            ErrorKind::ReadTimeout => "read_timeout",
+            ErrorKind::ReadFailure => "read_failure",
+            ErrorKind::FunctionFailure => "function_failure",
+            ErrorKind::WriteFailure => "write_failure",
+            ErrorKind::Syntax => "syntax",
+            ErrorKind::Unauthorized => "unauthorized",
+            ErrorKind::Invalid => "invalid",
+            ErrorKind::Config => "config",
+            ErrorKind::AlreadyExists => "already_exists",
+            ErrorKind::Unprepared => "unprepared",
+            ErrorKind::Io => "io",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+impl From<&AdditionalErrorInfo> for ErrorKind {
+    fn from(info: &AdditionalErrorInfo) -> Self {
+        match info {
+            AdditionalErrorInfo::Server => ErrorKind::Server,
+            AdditionalErrorInfo::Protocol => ErrorKind::Protocol,
+            AdditionalErrorInfo::Authentication => ErrorKind::Authentication,
+            AdditionalErrorInfo::Unavailable(_) => ErrorKind::Unavailable,
+            AdditionalErrorInfo::Overloaded => ErrorKind::Overloaded,
+            AdditionalErrorInfo::IsBootstrapping => ErrorKind::IsBootstrapping,
+            AdditionalErrorInfo::Truncate => ErrorKind::Truncate,
+            AdditionalErrorInfo::WriteTimeout(_) => ErrorKind::WriteTimeout,
+            AdditionalErrorInfo::ReadTimeout(_) => ErrorKind::ReadTimeout,
+            AdditionalErrorInfo::ReadFailure(_) => ErrorKind::ReadFailure,
+            AdditionalErrorInfo::FunctionFailure(_) => ErrorKind::FunctionFailure,
+            AdditionalErrorInfo::WriteFailure(_) => ErrorKind::WriteFailure,
+            AdditionalErrorInfo::Syntax => ErrorKind::Syntax,
+            AdditionalErrorInfo::Unauthorized => ErrorKind::Unauthorized,
+            AdditionalErrorInfo::Invalid => ErrorKind::Invalid,
+            AdditionalErrorInfo::Config => ErrorKind::Config,
+            AdditionalErrorInfo::AlreadyExists(_) => ErrorKind::AlreadyExists,
+            AdditionalErrorInfo::Unprepared(_) => ErrorKind::Unprepared,
+        }
+    }
+}
+
+impl From<&Error> for ErrorKind {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Server(error) => (&error.additional_info).into(),
+            Error::Io(_) => ErrorKind::Io,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Receiver of driver metrics. All methods have empty default implementations, so sinks can pick
+/// which metrics they're interested in. Methods are called on hot paths, so they should be cheap
+/// and must not block.
+pub trait MetricsSink {
+    /// Records the latency of a single attempt of sending a request to a node.
+    fn record_node_latency(&self, _node: SocketAddr, _latency: Duration) {}
+
+    /// Records the latency of a whole request, including retries and speculative executions.
+    fn record_request_latency(&self, _latency: Duration) {}
+
+    /// Records a request being retried after failing on given node.
+    fn record_retry(&self, _node: SocketAddr) {}
+
+    /// Records starting an additional speculative execution of a request.
+    fn record_speculative_execution(&self) {}
+
+    /// Records a timeout waiting for a response from given node.
+    fn record_timeout(&self, _node: SocketAddr) {}
+
+    /// Records an error returned by given node.
+    fn record_error(&self, _node: SocketAddr, _kind: ErrorKind) {}
+
+    /// Records opening a new pooled connection to given node.
+    fn record_connection_opened(&self, _node: SocketAddr) {}
+
+    /// Records finding a broken pooled connection to given node.
+    fn record_connection_broken(&self, _node: SocketAddr) {}
+
+    /// Records a stream id being acquired for a request to given node.
+    fn record_stream_acquired(&self, _node: SocketAddr) {}
+
+    /// Records a stream id being released after a request to given node completed or has been
+    /// cancelled.
+    fn record_stream_released(&self, _node: SocketAddr) {}
+
+    /// Records bytes written to a connection to given node.
+    fn record_bytes_sent(&self, _node: SocketAddr, _bytes: usize) {}
+
+    /// Records bytes read from a connection to given node.
+    fn record_bytes_received(&self, _node: SocketAddr, _bytes: usize) {}
+
+    /// Returns a snapshot of recorded metrics, if the sink keeps them.
+    fn snapshot(&self) -> Option<MetricsSnapshot> {
+        None
+    }
+}
+
+/// Discards all metrics.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetricsSink;
+
+impl MetricsSink for NoopMetricsSink {}
+
+/// Snapshot of a latency histogram.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// Cumulative bucket counts - number of observations less than or equal to the upper bound.
+    pub buckets: Vec<(Duration, u64)>,
+    /// Total number of observations.
+    pub count: u64,
+    /// Sum of all observations.
+    pub sum: Duration,
+}
+
+/// Snapshot of metrics of a single node.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeMetricsSnapshot {
+    pub latency: HistogramSnapshot,
+    pub retries: u64,
+    pub timeouts: u64,
+    pub errors: HashMap<ErrorKind, u64>,
+    pub connections_opened: u64,
+    pub connections_broken: u64,
+    pub in_flight_requests: usize,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Snapshot of all driver metrics.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Overall request latency.
+    pub latency: HistogramSnapshot,
+    pub speculative_executions: u64,
+    pub nodes: HashMap<SocketAddr, NodeMetricsSnapshot>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS_MICROS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn record(&self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        if let Some(index) = LATENCY_BUCKETS_MICROS
+            .iter()
+            .position(|upper_bound| micros <= *upper_bound)
+        {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS_MICROS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(upper_bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (Duration::from_micros(*upper_bound), cumulative)
+            })
+            .collect();
+
+        HistogramSnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct NodeMetrics {
+    latency: Histogram,
+    retries: AtomicU64,
+    timeouts: AtomicU64,
+    errors: Mutex<FxHashMap<ErrorKind, u64>>,
+    connections_opened: AtomicU64,
+    connections_broken: AtomicU64,
+    in_flight_requests: AtomicUsize,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl NodeMetrics {
+    fn snapshot(&self) -> NodeMetricsSnapshot {
+        NodeMetricsSnapshot {
+            latency: self.latency.snapshot(),
+            retries: self.retries.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            errors: self
+                .errors
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(kind, count)| (*kind, *count))
+                .collect(),
+            connections_opened: self.connections_opened.load(Ordering::Relaxed),
+            connections_broken: self.connections_broken.load(Ordering::Relaxed),
+            in_flight_requests: self.in_flight_requests.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Aggregates metrics in memory, per node and overall. Latencies are kept in histograms with
+/// [`LATENCY_BUCKETS_MICROS`] buckets.
+#[derive(Default)]
+pub struct InMemoryMetricsSink {
+    latency: Histogram,
+    speculative_executions: AtomicU64,
+    nodes: RwLock<FxHashMap<SocketAddr, Arc<NodeMetrics>>>,
+}
+
+impl InMemoryMetricsSink {
+    fn node(&self, node: SocketAddr) -> Arc<NodeMetrics> {
+        if let Some(metrics) = self.nodes.read().unwrap().get(&node) {
+            return metrics.clone();
+        }
+
+        self.nodes.write().unwrap().entry(node).or_default().clone()
+    }
+}
+
+impl MetricsSink for InMemoryMetricsSink {
+    fn record_node_latency(&self, node: SocketAddr, latency: Duration) {
+        self.node(node).latency.record(latency);
+    }
+
+    fn record_request_latency(&self, latency: Duration) {
+        self.latency.record(latency);
+    }
+
+    fn record_retry(&self, node: SocketAddr) {
+        self.node(node).retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_speculative_execution(&self) {
+        self.speculative_executions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_timeout(&self, node: SocketAddr) {
+        self.node(node).timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_error(&self, node: SocketAddr, kind: ErrorKind) {
+        *self
+            .node(node)
+            .errors
+            .lock()
+            .unwrap()
+            .entry(kind)
+            .or_default() += 1;
+    }
+
+    fn record_connection_opened(&self, node: SocketAddr) {
+        self.node(node)
+            .connections_opened
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_connection_broken(&self, node: SocketAddr) {
+        self.node(node)
+            .connections_broken
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_stream_acquired(&self, node: SocketAddr) {
+        self.node(node)
+            .in_flight_requests
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_stream_released(&self, node: SocketAddr) {
+        self.node(node)
+            .in_flight_requests
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn record_bytes_sent(&self, node: SocketAddr, bytes: usize) {
+        self.node(node)
+            .bytes_sent
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn record_bytes_received(&self, node: SocketAddr, bytes: usize) {
+        self.node(node)
+            .bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Option<MetricsSnapshot> {
+        Some(MetricsSnapshot {
+            latency: self.latency.snapshot(),
+            speculative_executions: self.speculative_executions.load(Ordering::Relaxed),
+            nodes: self
+                .nodes
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(node, metrics)| (*node, metrics.snapshot()))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn should_bucket_latencies() {
+        let histogram = Histogram::default();
+        histogram.record(Duration::from_micros(50));
+        histogram.record(Duration::from_micros(100));
+        histogram.record(Duration::from_millis(3));
+        histogram.record(Duration::from_secs(60));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 4);
+        assert_eq!(
+            snapshot.sum,
+            Duration::from_secs(60) + Duration::from_micros(3150)
+        );
+        assert_eq!(snapshot.buckets[0], (Duration::from_micros(100), 2));
+        assert_eq!(snapshot.buckets[4], (Duration::from_micros(2_500), 2));
+        assert_eq!(snapshot.buckets[5], (Duration::from_millis(5), 3));
+        assert_eq!(snapshot.buckets.last().unwrap().1, 3);
+    }
+
+    #[test]
+    fn should_aggregate_node_metrics() {
+        let node = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+        let sink = InMemoryMetricsSink::default();
+
+        sink.record_stream_acquired(node);
+        sink.record_stream_acquired(node);
+        sink.record_stream_released(node);
+        sink.record_error(node, ErrorKind::Overloaded);
+        sink.record_error(node, ErrorKind::Overloaded);
+        sink.record_bytes_sent(node, 10);
+        sink.record_request_latency(Duration::from_millis(1));
+        sink.record_speculative_execution();
+
+        let snapshot = sink.snapshot().unwrap();
+        assert_eq!(snapshot.latency.count, 1);
+        assert_eq!(snapshot.speculative_executions, 1);
+
+        let node_snapshot = &snapshot.nodes[&node];
+        assert_eq!(node_snapshot.in_flight_requests, 1);
+        assert_eq!(node_snapshot.errors[&ErrorKind::Overloaded], 2);
+        assert_eq!(node_snapshot.bytes_sent, 10);
+        assert_eq!(node_snapshot.latency.count, 0);
+    }
+
+    #[test]
+    fn should_not_keep_metrics_by_default() {
+        assert_eq!(NoopMetricsSink.snapshot(), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! Server-side query traces.
+//!
+//! Requests sent with tracing enabled (see `StatementParams::tracing`) get traced by the server,
+//! which stores the results in `system_traces` keyspace. The tracing id is returned in the
+//! response frame (`Frame::tracing_id`) and can be used to fetch the trace with
+//! `Session::query_trace()`. Traces are written asynchronously, so they might not be complete right
+//! after receiving the response - the session polls for them, as configured by
+//! [`QueryTraceConfig`].
+
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::error::Result;
+use cassandra_protocol::types::map::Map;
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::{AsRustType, IntoRustByName};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Query selecting a trace session.
+pub(crate) const SELECT_SESSION_QUERY: &str =
+    "SELECT * FROM system_traces.sessions WHERE session_id = ?";
+
+/// Query selecting trace events, in order of occurrence.
+pub(crate) const SELECT_EVENTS_QUERY: &str =
+    "SELECT * FROM system_traces.events WHERE session_id = ?";
+
+/// Configuration of fetching query traces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueryTraceConfig {
+    /// Maximum number of attempts at fetching a complete trace.
+    pub attempts: usize,
+    /// Delay between consecutive attempts.
+    pub delay: Duration,
+    /// Consistency for reading trace tables.
+    pub consistency: Consistency,
+}
+
+impl Default for QueryTraceConfig {
+    fn default() -> Self {
+        QueryTraceConfig {
+            attempts: 5,
+            delay: Duration::from_millis(3),
+            consistency: Consistency::One,
+        }
+    }
+}
+
+/// Trace of a single request, as recorded by the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryTrace {
+    pub session_id: Uuid,
+    /// Node which coordinated the request.
+    pub coordinator: IpAddr,
+    /// Address of the client, if reported by the server.
+    pub client: Option<IpAddr>,
+    /// Kind of the request, e.g. "Execute CQL3 query".
+    pub request: Option<String>,
+    pub command: Option<String>,
+    /// Request parameters, e.g. the query string or consistency.
+    pub parameters: HashMap<String, String>,
+    pub started_at: Option<SystemTime>,
+    /// Time spent by the coordinator on the request.
+    pub duration: Duration,
+    /// Trace events, in order of occurrence.
+    pub events: Vec<TraceEvent>,
+}
+
+/// Single event in a query trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub event_id: Uuid,
+    pub activity: String,
+    /// Node on which the event happened.
+    pub source: IpAddr,
+    /// Time elapsed on the source node since it started handling the request.
+    pub source_elapsed: Duration,
+    pub thread: Option<String>,
+}
+
+/// Checks if given trace session is complete, i.e. the coordinator finished handling the request.
+pub(crate) fn is_trace_complete(session: &Row) -> Result<bool> {
+    let duration: Option<i32> = session.get_by_name("duration")?;
+    Ok(duration.is_some())
+}
+
+/// Builds a complete trace from given session row and event rows.
+pub(crate) fn build_query_trace(session: &Row, events: &[Row]) -> Result<QueryTrace> {
+    let duration: i32 = session.get_r_by_name("duration")?;
+
+    let parameters: Option<Map> = get_optional(session, "parameters")?;
+    let parameters = parameters
+        .map(|parameters| parameters.as_r_type())
+        .transpose()?
+        .unwrap_or_default();
+
+    let started_at: Option<i64> = get_optional(session, "started_at")?;
+
+    Ok(QueryTrace {
+        session_id: session.get_r_by_name("session_id")?,
+        coordinator: session.get_r_by_name("coordinator")?,
+        client: get_optional(session, "client")?,
+        request: get_optional(session, "request")?,
+        command: get_optional(session, "command")?,
+        parameters,
+        started_at: started_at
+            .map(|started_at| UNIX_EPOCH + Duration::from_millis(started_at as u64)),
+        duration: Duration::from_micros(duration as u64),
+        events: events
+            .iter()
+            .map(build_trace_event)
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn build_trace_event(row: &Row) -> Result<TraceEvent> {
+    let source_elapsed: Option<i32> = get_optional(row, "source_elapsed")?;
+
+    Ok(TraceEvent {
+        event_id: row.get_r_by_name("event_id")?,
+        activity: row.get_r_by_name("activity")?,
+        source: row.get_r_by_name("source")?,
+        source_elapsed: Duration::from_micros(source_elapsed.unwrap_or_default() as u64),
+        thread: get_optional(row, "thread")?,
+    })
+}
+
+// optional columns might not exist in older server versions
+fn get_optional<R>(row: &Row, name: &str) -> Result<Option<R>>
+where
+    Row: IntoRustByName<R>,
+{
+    if row.contains_column(name) {
+        row.get_by_name(name)
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::frame_result::ColType;
+    use cassandra_protocol::types::rows::Row;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, UNIX_EPOCH};
+    use uuid::Uuid;
+
+    use super::{build_query_trace, is_trace_complete, TraceEvent};
+    use crate::cluster::schema_builder::{test_rows, TestValue};
+
+    const SESSION_COLUMNS: &[&str] = &[
+        "session_id",
+        "client",
+        "command",
+        "coordinator",
+        "duration",
+        "parameters",
+        "request",
+        "started_at",
+    ];
+
+    const EVENT_COLUMNS: &[&str] = &[
+        "session_id",
+        "event_id",
+        "activity",
+        "source",
+        "source_elapsed",
+        "thread",
+    ];
+
+    fn session_row(session_id: Uuid, duration: TestValue) -> Row {
+        Row::from_frame_body(test_rows(
+            SESSION_COLUMNS,
+            &[vec![
+                TestValue::Uuid(session_id),
+                TestValue::Inet(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))),
+                TestValue::Text("QUERY"),
+                TestValue::Inet(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                duration,
+                TestValue::TextMap(vec![("consistency_level", "ONE")]),
+                TestValue::Text("Execute CQL3 query"),
+                TestValue::Timestamp(1000),
+            ]],
+        ))
+        .remove(0)
+    }
+
+    #[test]
+    fn should_build_complete_trace() {
+        let session_id = Uuid::new_v4();
+        let event_id = Uuid::new_v4();
+
+        let session = session_row(session_id, TestValue::Int(150));
+        let events = Row::from_frame_body(test_rows(
+            EVENT_COLUMNS,
+            &[vec![
+                TestValue::Uuid(session_id),
+                TestValue::Uuid(event_id),
+                TestValue::Text("Parsing query"),
+                TestValue::Inet(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                TestValue::Int(20),
+                TestValue::Text("Native-Transport-Requests-1"),
+            ]],
+        ));
+
+        assert!(is_trace_complete(&session).unwrap());
+
+        let trace = build_query_trace(&session, &events).unwrap();
+        assert_eq!(trace.session_id, session_id);
+        assert_eq!(trace.coordinator, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(trace.client, Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))));
+        assert_eq!(trace.request.as_deref(), Some("Execute CQL3 query"));
+        assert_eq!(trace.command.as_deref(), Some("QUERY"));
+        assert_eq!(trace.parameters["consistency_level"], "ONE");
+        assert_eq!(trace.started_at, Some(UNIX_EPOCH + Duration::from_secs(1)));
+        assert_eq!(trace.duration, Duration::from_micros(150));
+        assert_eq!(
+            trace.events,
+            vec![TraceEvent {
+                event_id,
+                activity: "Parsing query".into(),
+                source: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                source_elapsed: Duration::from_micros(20),
+                thread: Some("Native-Transport-Requests-1".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_detect_incomplete_trace() {
+        let session = session_row(Uuid::new_v4(), TestValue::Null(ColType::Int));
+
+        assert!(!is_trace_complete(&session).unwrap());
+        assert!(build_query_trace(&session, &[]).is_err());
+    }
+
+    #[test]
+    fn should_handle_missing_event_columns() {
+        let session_id = Uuid::new_v4();
+        let session = session_row(session_id, TestValue::Int(150));
+
+        // optional columns can be missing
+        let events = Row::from_frame_body(test_rows(
+            &["session_id", "event_id", "activity", "source"],
+            &[vec![
+                TestValue::Uuid(session_id),
+                TestValue::Uuid(Uuid::new_v4()),
+                TestValue::Text("Parsing query"),
+                TestValue::Inet(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ]],
+        ));
+
+        let trace = build_query_trace(&session, &events).unwrap();
+        assert_eq!(trace.events[0].source_elapsed, Duration::ZERO);
+        assert_eq!(trace.events[0].thread, None);
+
+        // required ones cannot
+        let events = Row::from_frame_body(test_rows(
+            &["session_id", "event_id", "source"],
+            &[vec![
+                TestValue::Uuid(session_id),
+                TestValue::Uuid(Uuid::new_v4()),
+                TestValue::Inet(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ]],
+        ));
+
+        assert!(build_query_trace(&session, &events).is_err());
+    }
+}
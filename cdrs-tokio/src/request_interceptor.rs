@@ -0,0 +1,331 @@
+//! Request interceptors.
+//!
+//! [`RequestInterceptor`]s registered with `SessionBuilder::with_request_interceptor()` form a
+//! chain wrapping every request sent by a session. Each interceptor sees the outgoing frame along
+//! with statement parameters before the request is sent, and its result afterwards. This allows
+//! implementing cross-cutting behavior, e.g. audit logging, query rewriting, tagging requests with
+//! a custom payload or rejecting certain requests.
+//!
+//! Requests sent internally by the driver are not intercepted - these include re-preparing
+//! statements reported as unprepared, preparing cached statements on nodes which come up and
+//! control connection queries.
+
+use cassandra_protocol::error::Result;
+use cassandra_protocol::frame::Frame;
+use std::borrow::Cow;
+use std::future::Future;
+use std::mem;
+use std::sync::Arc;
+
+use crate::statement::StatementParams;
+
+/// Interceptor of requests sent by a session. Requests without explicit statement parameters, e.g.
+/// PREPARE, are intercepted with default parameters.
+pub trait RequestInterceptor {
+    /// Called before sending a request, in order of registration. The frame and parameters can be
+    /// modified before passing them further. After the chain, the request is encoded again from
+    /// final parameters, so changing e.g. query values or consistency changes the sent request,
+    /// along with its routing, retries, speculative execution, timeout and custom payload. Flags
+    /// and custom payload set directly on the frame are kept. If an interceptor replaces the frame
+    /// body, it is sent as is instead. Returning a response or an error short-circuits the chain -
+    /// the request is not sent and subsequent interceptors are skipped.
+    fn before_request(
+        &self,
+        _frame: &mut Frame,
+        _parameters: &mut StatementParams,
+    ) -> Result<Option<Frame>> {
+        Ok(None)
+    }
+
+    /// Called with the result of a request, in reverse order of registration. Only interceptors
+    /// which have seen the request in [`before_request`](RequestInterceptor::before_request) are
+    /// called, including the one which short-circuited it. The result can be modified.
+    fn after_response(
+        &self,
+        _request: &Frame,
+        _parameters: &StatementParams,
+        _result: &mut Result<Frame>,
+    ) {
+    }
+}
+
+/// Passes a request built with `build` through the interceptor chain, using `send` to actually
+/// send it with the resulting parameters. Parameters are only cloned if there are interceptors
+/// which could modify them, in which case the request is built again from final parameters. The
+/// custom payload from parameters is added to the frame after the chain.
+pub(crate) async fn intercept<'p, B, F, Fut>(
+    interceptors: &[Arc<dyn RequestInterceptor + Send + Sync>],
+    parameters: &'p mut Cow<'_, StatementParams>,
+    mut build: B,
+    send: F,
+) -> Result<Frame>
+where
+    B: FnMut(&StatementParams) -> Frame,
+    F: FnOnce(Frame, &'p StatementParams) -> Fut,
+    Fut: Future<Output = Result<Frame>>,
+{
+    let mut frame = build(parameters);
+
+    if interceptors.is_empty() {
+        add_custom_payload(&mut frame, parameters);
+        return send(frame, parameters).await;
+    }
+
+    let encoded_flags = frame.flags;
+    let encoded_body = frame.body.clone();
+
+    let mut called = 0;
+    let mut response = None;
+
+    for interceptor in interceptors {
+        called += 1;
+
+        response = interceptor
+            .before_request(&mut frame, parameters.to_mut())
+            .transpose();
+
+        if response.is_some() {
+            break;
+        }
+    }
+
+    let parameters: &'p StatementParams = parameters;
+
+    // parameters might have changed, so the request needs to reflect them, unless its body has
+    // been replaced
+    if response.is_none() && frame.body == encoded_body {
+        let mut request = build(parameters);
+        request.flags |= frame.flags - encoded_flags;
+        request.custom_payload = mem::take(&mut frame.custom_payload);
+        frame = request;
+    }
+
+    add_custom_payload(&mut frame, parameters);
+
+    let mut result = match response {
+        Some(response) => response,
+        None => send(frame.clone(), parameters).await,
+    };
+
+    for interceptor in interceptors[..called].iter().rev() {
+        interceptor.after_response(&frame, parameters, &mut result);
+    }
+
+    result
+}
+
+fn add_custom_payload(frame: &mut Frame, parameters: &StatementParams) {
+    frame.custom_payload.extend(
+        parameters
+            .custom_payload
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone())),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::{Flags, Opcode, Version};
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct RecordingInterceptor {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+        reject: bool,
+    }
+
+    impl RequestInterceptor for RecordingInterceptor {
+        fn before_request(
+            &self,
+            frame: &mut Frame,
+            _parameters: &mut StatementParams,
+        ) -> Result<Option<Frame>> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("before {}", self.name));
+
+            frame.flags.insert(Flags::TRACING);
+
+            if self.reject {
+                Err(Error::General("rejected".into()))
+            } else {
+                Ok(None)
+            }
+        }
+
+        fn after_response(
+            &self,
+            _request: &Frame,
+            _parameters: &StatementParams,
+            result: &mut Result<Frame>,
+        ) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("after {} {}", self.name, result.is_ok()));
+        }
+    }
+
+    fn interceptor(
+        name: &'static str,
+        calls: &Arc<Mutex<Vec<String>>>,
+        reject: bool,
+    ) -> Arc<dyn RequestInterceptor + Send + Sync> {
+        Arc::new(RecordingInterceptor {
+            name,
+            calls: calls.clone(),
+            reject,
+        })
+    }
+
+    fn request() -> Frame {
+        Frame::new_req_options(Version::V4)
+    }
+
+    #[tokio::test]
+    async fn should_call_interceptors_around_request() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let interceptors = vec![
+            interceptor("a", &calls, false),
+            interceptor("b", &calls, false),
+        ];
+
+        let result = intercept(
+            &interceptors,
+            &mut Default::default(),
+            |_| request(),
+            |frame, _| async move {
+                assert!(frame.flags.contains(Flags::TRACING));
+                Ok(frame)
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap().opcode, Opcode::Options);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["before a", "before b", "after b true", "after a true"]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_short_circuit_request() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let interceptors = vec![
+            interceptor("a", &calls, false),
+            interceptor("b", &calls, true),
+            interceptor("c", &calls, false),
+        ];
+
+        let result = intercept(
+            &interceptors,
+            &mut Default::default(),
+            |_| request(),
+            |_, _| async { panic!("request should not be sent") },
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["before a", "before b", "after b false", "after a false"]
+        );
+    }
+
+    struct TaggingInterceptor;
+
+    impl RequestInterceptor for TaggingInterceptor {
+        fn before_request(
+            &self,
+            _frame: &mut Frame,
+            parameters: &mut StatementParams,
+        ) -> Result<Option<Frame>> {
+            parameters
+                .custom_payload
+                .insert("tag".into(), b"value".to_vec());
+            parameters.is_idempotent = true;
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_with_modified_parameters() {
+        let interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>> =
+            vec![Arc::new(TaggingInterceptor)];
+        let parameters = StatementParams::default();
+
+        let result = intercept(
+            &interceptors,
+            &mut Cow::Borrowed(&parameters),
+            |_| request(),
+            |frame, parameters| {
+                assert!(parameters.is_idempotent);
+                assert_eq!(frame.custom_payload["tag"], b"value");
+                async move { Ok(frame) }
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap().custom_payload["tag"], b"value");
+        // original parameters are left intact
+        assert!(parameters.custom_payload.is_empty());
+    }
+
+    struct RewritingInterceptor;
+
+    impl RequestInterceptor for RewritingInterceptor {
+        fn before_request(
+            &self,
+            frame: &mut Frame,
+            _parameters: &mut StatementParams,
+        ) -> Result<Option<Frame>> {
+            frame.body = vec![1, 2, 3].into();
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_rebuild_request_from_final_parameters() {
+        let interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>> =
+            vec![Arc::new(TaggingInterceptor)];
+        let parameters = StatementParams::default();
+        let builds = Mutex::new(vec![]);
+
+        intercept(
+            &interceptors,
+            &mut Cow::Borrowed(&parameters),
+            |parameters| {
+                builds.lock().unwrap().push(parameters.is_idempotent);
+                request()
+            },
+            |frame, _| async move { Ok(frame) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(*builds.lock().unwrap(), vec![false, true]);
+    }
+
+    #[tokio::test]
+    async fn should_send_replaced_body() {
+        let interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>> =
+            vec![Arc::new(RewritingInterceptor), Arc::new(TaggingInterceptor)];
+
+        let result = intercept(
+            &interceptors,
+            &mut Default::default(),
+            |_| request(),
+            |frame, parameters| {
+                assert!(parameters.is_idempotent);
+                async move { Ok(frame) }
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap().body.as_ref(), &[1, 2, 3]);
+    }
+}
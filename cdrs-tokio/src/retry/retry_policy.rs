@@ -72,6 +72,7 @@ impl RetrySession for DefaultRetrySession {
         match query_info.error {
             Error::Io(_)
             | Error::General(_)
+            | Error::Timeout(_)
             | Error::Server(ErrorBody {
                 additional_info: AdditionalErrorInfo::Overloaded,
                 ..
@@ -2,7 +2,9 @@ use crate::retry::RetryPolicy;
 use cassandra_protocol::query::QueryParams;
 use cassandra_protocol::token::Murmur3Token;
 use cassandra_protocol::types::value::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::speculative_execution::SpeculativeExecutionPolicy;
 
@@ -14,8 +16,9 @@ pub struct StatementParams {
     /// Is the query idempotent.
     pub is_idempotent: bool,
     /// Query keyspace. If not using a global one, setting it explicitly might help the load
-    /// balancer use more appropriate nodes. Note: prepared statements with keyspace information
-    /// take precedence over this field.
+    /// balancer use more appropriate nodes. With protocol V5+, it is also sent along with queries
+    /// and batches, so they are executed in this keyspace regardless of the current global one.
+    /// Note: prepared statements with keyspace information take precedence over this field.
     pub keyspace: Option<String>,
     /// The token to use for token-aware routing. A load balancer may use this information to
     /// determine which nodes to contact. Takes precedence over `routing_key`.
@@ -32,4 +35,11 @@ pub struct StatementParams {
     pub speculative_execution_policy: Option<Arc<dyn SpeculativeExecutionPolicy + Send + Sync>>,
     /// Custom statement retry policy.
     pub retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync>>,
+    /// Time to wait for a response from a single node, after which [`Error::Timeout`] is returned
+    /// to the retry policy. Overrides the session default, if any.
+    ///
+    /// [`Error::Timeout`]: cassandra_protocol::error::Error::Timeout
+    pub timeout: Option<Duration>,
+    /// Custom payload sent along with the request, e.g. for custom query handlers on the server.
+    pub custom_payload: HashMap<String, Vec<u8>>,
 }
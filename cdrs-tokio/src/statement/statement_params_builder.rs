@@ -3,7 +3,9 @@ use cassandra_protocol::query::{QueryFlags, QueryParams, QueryValues};
 use cassandra_protocol::token::Murmur3Token;
 use cassandra_protocol::types::value::Value;
 use cassandra_protocol::types::CBytes;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::retry::RetryPolicy;
 use crate::speculative_execution::SpeculativeExecutionPolicy;
@@ -19,6 +21,7 @@ pub struct StatementParamsBuilder {
     paging_state: Option<CBytes>,
     serial_consistency: Option<Consistency>,
     timestamp: Option<i64>,
+    now_in_seconds: Option<i32>,
     is_idempotent: bool,
     keyspace: Option<String>,
     token: Option<Murmur3Token>,
@@ -27,6 +30,8 @@ pub struct StatementParamsBuilder {
     warnings: bool,
     speculative_execution_policy: Option<Arc<dyn SpeculativeExecutionPolicy + Send + Sync>>,
     retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync>>,
+    timeout: Option<Duration>,
+    custom_payload: HashMap<String, Vec<u8>>,
 }
 
 impl StatementParamsBuilder {
@@ -103,6 +108,13 @@ impl StatementParamsBuilder {
         self
     }
 
+    /// Sets new "now" in seconds, used by the server instead of the current time (protocol V5+).
+    #[must_use]
+    pub fn with_now_in_seconds(mut self, now_in_seconds: i32) -> Self {
+        self.now_in_seconds = Some(now_in_seconds);
+        self
+    }
+
     /// Sets new keyspace.
     #[must_use]
     pub fn with_keyspace(mut self, keyspace: String) -> Self {
@@ -148,6 +160,20 @@ impl StatementParamsBuilder {
         self
     }
 
+    /// Sets request timeout, overriding the session default.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets custom payload sent along with the request.
+    #[must_use]
+    pub fn with_custom_payload(mut self, custom_payload: HashMap<String, Vec<u8>>) -> Self {
+        self.custom_payload = custom_payload;
+        self
+    }
+
     pub fn build(self) -> StatementParams {
         StatementParams {
             query_params: QueryParams {
@@ -158,6 +184,8 @@ impl StatementParamsBuilder {
                 paging_state: self.paging_state,
                 serial_consistency: self.serial_consistency,
                 timestamp: self.timestamp,
+                keyspace: None,
+                now_in_seconds: self.now_in_seconds,
             },
             is_idempotent: self.is_idempotent,
             keyspace: self.keyspace,
@@ -167,6 +195,8 @@ impl StatementParamsBuilder {
             warnings: self.warnings,
             speculative_execution_policy: self.speculative_execution_policy,
             retry_policy: self.retry_policy,
+            timeout: self.timeout,
+            custom_payload: self.custom_payload,
         }
     }
 }
@@ -0,0 +1,220 @@
+//! Client-side generation of request timestamps.
+//!
+//! Cassandra resolves conflicting writes by comparing their timestamps. Without a client-side
+//! timestamp, each write gets one assigned by its coordinator, so clock differences between nodes
+//! can result in later updates being silently lost. Generating timestamps in the driver makes the
+//! order of writes issued by a single session consistent.
+//!
+//! Generated timestamps are applied to QUERY, EXECUTE and BATCH requests which don't have an
+//! explicit one.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::*;
+
+/// Default drift after which generators start logging warnings.
+pub const DEFAULT_WARNING_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Default minimum interval between consecutive drift warnings.
+pub const DEFAULT_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Generates timestamps for requests which don't have an explicit one.
+pub trait TimestampGenerator {
+    /// Returns the next timestamp in microseconds since UNIX epoch. `None` means the server should
+    /// assign the timestamp.
+    fn next_timestamp(&self) -> Option<i64>;
+}
+
+/// Leaves assigning timestamps to the server.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ServerSideTimestampGenerator;
+
+impl TimestampGenerator for ServerSideTimestampGenerator {
+    #[inline]
+    fn next_timestamp(&self) -> Option<i64> {
+        None
+    }
+}
+
+/// Generates strictly increasing timestamps based on the system clock, guarded by a lock. If the
+/// clock doesn't advance between calls (or goes backwards), the previous timestamp gets incremented
+/// by one microsecond, which makes generated timestamps drift ahead of the clock. A warning is
+/// logged when the drift exceeds a threshold.
+pub struct MonotonicTimestampGenerator {
+    last: Mutex<i64>,
+    drift_warning: DriftWarning,
+}
+
+impl MonotonicTimestampGenerator {
+    pub fn new(warning_threshold: Duration, warning_interval: Duration) -> Self {
+        MonotonicTimestampGenerator {
+            last: Mutex::new(0),
+            drift_warning: DriftWarning::new(warning_threshold, warning_interval),
+        }
+    }
+}
+
+impl Default for MonotonicTimestampGenerator {
+    fn default() -> Self {
+        MonotonicTimestampGenerator::new(DEFAULT_WARNING_THRESHOLD, DEFAULT_WARNING_INTERVAL)
+    }
+}
+
+impl TimestampGenerator for MonotonicTimestampGenerator {
+    fn next_timestamp(&self) -> Option<i64> {
+        let clock = current_micros();
+
+        let mut last = self.last.lock().unwrap();
+        *last = next_monotonic(*last, clock);
+
+        self.drift_warning.check(clock, *last);
+        Some(*last)
+    }
+}
+
+/// Lock-free version of [`MonotonicTimestampGenerator`], with the same guarantees. Performs
+/// better when the session is used from many threads at once.
+pub struct AtomicMonotonicTimestampGenerator {
+    last: AtomicI64,
+    drift_warning: DriftWarning,
+}
+
+impl AtomicMonotonicTimestampGenerator {
+    pub fn new(warning_threshold: Duration, warning_interval: Duration) -> Self {
+        AtomicMonotonicTimestampGenerator {
+            last: AtomicI64::new(0),
+            drift_warning: DriftWarning::new(warning_threshold, warning_interval),
+        }
+    }
+}
+
+impl Default for AtomicMonotonicTimestampGenerator {
+    fn default() -> Self {
+        AtomicMonotonicTimestampGenerator::new(DEFAULT_WARNING_THRESHOLD, DEFAULT_WARNING_INTERVAL)
+    }
+}
+
+impl TimestampGenerator for AtomicMonotonicTimestampGenerator {
+    fn next_timestamp(&self) -> Option<i64> {
+        let clock = current_micros();
+
+        let mut last = self.last.load(Ordering::Relaxed);
+        loop {
+            let next = next_monotonic(last, clock);
+            match self
+                .last
+                .compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => {
+                    self.drift_warning.check(clock, next);
+                    return Some(next);
+                }
+                Err(current) => last = current,
+            }
+        }
+    }
+}
+
+struct DriftWarning {
+    threshold: i64,
+    interval: i64,
+    // clock value of the last warning, to avoid flooding the logs
+    last_warning: AtomicI64,
+}
+
+impl DriftWarning {
+    fn new(threshold: Duration, interval: Duration) -> Self {
+        DriftWarning {
+            threshold: threshold.as_micros() as i64,
+            interval: interval.as_micros() as i64,
+            last_warning: AtomicI64::new(i64::MIN),
+        }
+    }
+
+    /// Checks if given timestamp drifted too far from the clock and logs a warning if needed.
+    /// Returns if a warning has been logged.
+    fn check(&self, clock: i64, timestamp: i64) -> bool {
+        let drift = timestamp - clock;
+        if drift <= self.threshold {
+            return false;
+        }
+
+        let last_warning = self.last_warning.load(Ordering::Relaxed);
+        if clock.saturating_sub(last_warning) < self.interval
+            || self
+                .last_warning
+                .compare_exchange(last_warning, clock, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return false;
+        }
+
+        warn!(
+            drift_micros = drift,
+            "Clock skew detected: generated timestamps are ahead of the system clock. This \
+            happens when the clock goes backwards, or when generating more than one timestamp \
+            per microsecond."
+        );
+
+        true
+    }
+}
+
+#[inline]
+fn next_monotonic(last: i64, clock: i64) -> i64 {
+    if clock > last {
+        clock
+    } else {
+        last + 1
+    }
+}
+
+fn current_micros() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_micros() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_increasing(generator: &dyn TimestampGenerator) {
+        let mut last = generator.next_timestamp().unwrap();
+        for _ in 0..1000 {
+            let next = generator.next_timestamp().unwrap();
+            assert!(next > last);
+            last = next;
+        }
+    }
+
+    #[test]
+    fn should_generate_increasing_timestamps() {
+        assert_increasing(&MonotonicTimestampGenerator::default());
+        assert_increasing(&AtomicMonotonicTimestampGenerator::default());
+    }
+
+    #[test]
+    fn should_increment_when_clock_goes_backwards() {
+        assert_eq!(next_monotonic(10, 20), 20);
+        assert_eq!(next_monotonic(20, 20), 21);
+        assert_eq!(next_monotonic(20, 10), 21);
+    }
+
+    #[test]
+    fn should_rate_limit_drift_warnings() {
+        let drift_warning = DriftWarning::new(Duration::from_micros(5), Duration::from_micros(100));
+
+        assert!(!drift_warning.check(100, 105));
+        assert!(drift_warning.check(100, 106));
+        assert!(!drift_warning.check(150, 200));
+        assert!(drift_warning.check(200, 300));
+    }
+
+    #[test]
+    fn should_leave_timestamps_to_server() {
+        assert_eq!(ServerSideTimestampGenerator.next_timestamp(), None);
+    }
+}
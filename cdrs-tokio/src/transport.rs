@@ -10,24 +10,32 @@
 //! * [`TransportRustls`] is a transport which is used to establish SSL encrypted connection
 //!with Apache Cassandra server. **Note:** this option is available if and only if CDRS is imported
 //!with `rust-tls` feature.
+use atomic::Atomic;
 use cassandra_protocol::compression::Compression;
 use cassandra_protocol::frame::frame_result::ResultKind;
-use cassandra_protocol::frame::{Frame, StreamId};
+use cassandra_protocol::frame::frame_supported::BodyResSupported;
+use cassandra_protocol::frame::segment::{Segment, SegmentFrameDecoder, MAX_PAYLOAD_LEN};
+use cassandra_protocol::frame::{Frame, StreamId, Version};
 use cassandra_protocol::frame::{FromBytes, Opcode, EVENT_STREAM_ID};
 use cassandra_protocol::types::INT_LEN;
 use derive_more::Constructor;
-use futures::FutureExt;
+use futures::{future, FutureExt};
 use fxhash::FxHashMap;
 use std::io;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, AtomicI16, Ordering};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::{
-    split, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, ReadHalf, WriteHalf,
+    split, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader,
+    BufWriter, ReadBuf, ReadHalf, WriteHalf,
 };
 use tokio::net::TcpStream;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, Semaphore};
 use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
 #[cfg(feature = "rust-tls")]
 use tokio_rustls::TlsConnector as RustlsConnector;
 use tracing::*;
@@ -36,12 +44,14 @@ use tracing::*;
 use mockall::*;
 
 use crate::cluster::KeyspaceHolder;
-use crate::frame_parser::parse_frame;
+use crate::frame_parser::{convert_frame_into_result, parse_raw_frame, parse_segment_frames};
 use crate::future::BoxFuture;
+use crate::metrics::MetricsSink;
 use crate::Error;
 use crate::Result;
 
-const INITIAL_STREAM_ID: i16 = 1;
+/// Number of stream ids available for requests on a single connection.
+pub const MAX_STREAM_IDS: usize = 32768;
 
 /// General CDRS transport trait.
 pub trait CdrsTransport: Send + Sync {
@@ -53,6 +63,29 @@ pub trait CdrsTransport: Send + Sync {
 
     /// Returns associated node address
     fn address(&self) -> SocketAddr;
+
+    /// Returns options reported by the server in response to OPTIONS request sent when
+    /// establishing the connection, if available.
+    fn supported_options(&self) -> Option<&BodyResSupported> {
+        None
+    }
+
+    /// Checks if the connection reached its limit of requests in flight. New requests will wait
+    /// for a free slot.
+    fn is_saturated(&self) -> bool {
+        false
+    }
+
+    /// Stops accepting new requests and closes the connection once requests in flight complete,
+    /// or given drain timeout elapses, whichever comes first. Requests still in flight after the
+    /// timeout fail.
+    fn close(&self, _drain_timeout: Duration) -> BoxFuture<'_, ()> {
+        future::ready(()).boxed()
+    }
+
+    /// Reports a request which timed out waiting for a response. Connections with too many
+    /// consecutive timeouts are considered broken.
+    fn report_timeout(&self) {}
 }
 
 #[cfg(test)]
@@ -69,12 +102,29 @@ mock! {
     }
 }
 
+/// Function generating responses to frames sent to mock transports.
+#[cfg(test)]
+pub(crate) type MockResponder = Arc<dyn Fn(&Frame) -> Result<Frame> + Send + Sync>;
+
+/// Creates a mock transport, which responds to frames using given function.
+#[cfg(test)]
+pub(crate) fn mock_transport(addr: SocketAddr, respond: MockResponder) -> MockCdrsTransport {
+    let mut transport = MockCdrsTransport::new();
+    transport
+        .expect_write_frame()
+        .returning(move |frame| future::ready(respond(frame)).boxed());
+    transport.expect_is_broken().return_const(false);
+    transport.expect_address().return_const(addr);
+    transport
+}
+
 /// Default Tcp transport.
 pub struct TransportTcp {
     inner: AsyncTransport,
 }
 
 impl TransportTcp {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         addr: SocketAddr,
         keyspace_holder: Arc<KeyspaceHolder>,
@@ -83,6 +133,9 @@ impl TransportTcp {
         compression: Compression,
         buffer_size: usize,
         tcp_nodelay: bool,
+        max_in_flight_requests: usize,
+        heartbeat: Option<HeartbeatConfig>,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
     ) -> io::Result<TransportTcp> {
         TcpStream::connect(addr).await.and_then(move |socket| {
             socket.set_nodelay(tcp_nodelay)?;
@@ -93,6 +146,9 @@ impl TransportTcp {
                     addr,
                     compression,
                     buffer_size,
+                    max_in_flight_requests,
+                    heartbeat,
+                    metrics,
                     read_half,
                     write_half,
                     event_handler,
@@ -104,6 +160,21 @@ impl TransportTcp {
     }
 }
 
+impl TransportTcp {
+    /// Changes compression used for frames - should be called before sending STARTUP, after
+    /// negotiating compression with the server.
+    #[inline]
+    pub fn set_compression(&self, compression: Compression) {
+        self.inner.set_compression(compression);
+    }
+
+    /// Sets options reported by the server for this connection.
+    #[inline]
+    pub fn set_supported_options(&mut self, supported_options: BodyResSupported) {
+        self.inner.supported_options = Some(supported_options);
+    }
+}
+
 impl CdrsTransport for TransportTcp {
     //noinspection DuplicatedCode
     #[inline]
@@ -120,6 +191,26 @@ impl CdrsTransport for TransportTcp {
     fn address(&self) -> SocketAddr {
         self.inner.addr()
     }
+
+    #[inline]
+    fn supported_options(&self) -> Option<&BodyResSupported> {
+        self.inner.supported_options.as_ref()
+    }
+
+    #[inline]
+    fn is_saturated(&self) -> bool {
+        self.inner.is_saturated()
+    }
+
+    #[inline]
+    fn close(&self, drain_timeout: Duration) -> BoxFuture<'_, ()> {
+        self.inner.close(drain_timeout).boxed()
+    }
+
+    #[inline]
+    fn report_timeout(&self) {
+        self.inner.report_timeout();
+    }
 }
 
 #[cfg(feature = "rust-tls")]
@@ -140,6 +231,9 @@ impl TransportRustls {
         compression: Compression,
         buffer_size: usize,
         tcp_nodelay: bool,
+        max_in_flight_requests: usize,
+        heartbeat: Option<HeartbeatConfig>,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
     ) -> io::Result<Self> {
         let stream = TcpStream::connect(addr).await?;
         stream.set_nodelay(tcp_nodelay)?;
@@ -153,6 +247,9 @@ impl TransportRustls {
                 addr,
                 compression,
                 buffer_size,
+                max_in_flight_requests,
+                heartbeat,
+                metrics,
                 read_half,
                 write_half,
                 event_handler,
@@ -163,6 +260,22 @@ impl TransportRustls {
     }
 }
 
+#[cfg(feature = "rust-tls")]
+impl TransportRustls {
+    /// Changes compression used for frames - should be called before sending STARTUP, after
+    /// negotiating compression with the server.
+    #[inline]
+    pub fn set_compression(&self, compression: Compression) {
+        self.inner.set_compression(compression);
+    }
+
+    /// Sets options reported by the server for this connection.
+    #[inline]
+    pub fn set_supported_options(&mut self, supported_options: BodyResSupported) {
+        self.inner.supported_options = Some(supported_options);
+    }
+}
+
 #[cfg(feature = "rust-tls")]
 impl CdrsTransport for TransportRustls {
     //noinspection DuplicatedCode
@@ -180,14 +293,41 @@ impl CdrsTransport for TransportRustls {
     fn address(&self) -> SocketAddr {
         self.inner.addr()
     }
+
+    #[inline]
+    fn supported_options(&self) -> Option<&BodyResSupported> {
+        self.inner.supported_options.as_ref()
+    }
+
+    #[inline]
+    fn is_saturated(&self) -> bool {
+        self.inner.is_saturated()
+    }
+
+    #[inline]
+    fn close(&self, drain_timeout: Duration) -> BoxFuture<'_, ()> {
+        self.inner.close(drain_timeout).boxed()
+    }
+
+    #[inline]
+    fn report_timeout(&self) {
+        self.inner.report_timeout();
+    }
 }
 
 struct AsyncTransport {
     addr: SocketAddr,
-    compression: Compression,
+    compression: Arc<Atomic<Compression>>,
+    supported_options: Option<BodyResSupported>,
     write_sender: mpsc::Sender<Request>,
     is_broken: Arc<AtomicBool>,
     processing_handle: JoinHandle<()>,
+    response_handler_map: Arc<ResponseHandlerMap>,
+    in_flight_requests: Semaphore,
+    max_in_flight_requests: u32,
+    metrics: Arc<dyn MetricsSink + Send + Sync>,
+    // number of timeouts since the last received response
+    consecutive_timeouts: AtomicUsize,
 }
 
 impl Drop for AsyncTransport {
@@ -202,6 +342,9 @@ impl AsyncTransport {
         addr: SocketAddr,
         compression: Compression,
         buffer_size: usize,
+        max_in_flight_requests: usize,
+        heartbeat: Option<HeartbeatConfig>,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
         read_half: ReadHalf<T>,
         write_half: WriteHalf<T>,
         event_handler: Option<mpsc::Sender<Frame>>,
@@ -209,31 +352,45 @@ impl AsyncTransport {
         keyspace_holder: Arc<KeyspaceHolder>,
     ) -> Self {
         let (write_sender, write_receiver) = mpsc::channel(buffer_size);
+        let max_in_flight_requests = max_in_flight_requests.clamp(1, MAX_STREAM_IDS);
         let is_broken = Arc::new(AtomicBool::new(false));
+        let compression = Arc::new(Atomic::new(compression));
+        let response_handler_map = Arc::new(ResponseHandlerMap::new());
 
         let processing_handle = tokio::spawn(Self::start_processing(
+            addr,
+            metrics.clone(),
             write_receiver,
+            write_sender.clone(),
+            heartbeat,
             event_handler,
             error_handler,
             read_half,
             write_half,
             keyspace_holder,
             is_broken.clone(),
-            compression,
+            compression.clone(),
+            response_handler_map.clone(),
         ));
 
         AsyncTransport {
             addr,
             compression,
+            supported_options: None,
             write_sender,
             is_broken,
             processing_handle,
+            response_handler_map,
+            in_flight_requests: Semaphore::new(max_in_flight_requests),
+            max_in_flight_requests: max_in_flight_requests as u32,
+            metrics,
+            consecutive_timeouts: AtomicUsize::new(0),
         }
     }
 
     #[inline]
     fn is_broken(&self) -> bool {
-        self.is_broken.load(Ordering::Relaxed)
+        self.is_broken.load(Ordering::Relaxed) || self.response_handler_map.is_defunct()
     }
 
     #[inline]
@@ -241,56 +398,140 @@ impl AsyncTransport {
         self.addr
     }
 
+    #[inline]
+    fn is_saturated(&self) -> bool {
+        self.in_flight_requests.available_permits() == 0
+    }
+
+    #[inline]
+    fn set_compression(&self, compression: Compression) {
+        self.compression.store(compression, Ordering::Relaxed);
+    }
+
+    fn report_timeout(&self) {
+        let timeouts = self.consecutive_timeouts.fetch_add(1, Ordering::Relaxed) + 1;
+        if timeouts == MAX_CONSECUTIVE_TIMEOUTS {
+            warn!(address = %self.addr, timeouts, "Too many consecutive timeouts - marking connection as broken.");
+            self.is_broken.store(true, Ordering::Relaxed);
+        }
+    }
+
     async fn write_frame(&self, frame: &Frame) -> Result<Frame> {
         let (sender, receiver) = oneshot::channel();
 
-        // leave stream id empty for now and generate it later
-
-        // startup message is never compressed
-        let data = if frame.opcode != Opcode::Startup {
-            frame.encode_with(self.compression)?
+        // startup and options messages are never compressed, since they can be sent before
+        // compression is negotiated
+        let data = if frame.opcode != Opcode::Startup && frame.opcode != Opcode::Options {
+            frame.encode_with(self.compression.load(Ordering::Relaxed))?
         } else {
             frame.encode_with(Compression::None)?
         };
 
+        // wait for a free slot if the connection is saturated
+        let _permit = self
+            .in_flight_requests
+            .acquire()
+            .await
+            .map_err(|_| Error::General("Connection closed when writing data!".into()))?;
+
+        let stream = self.response_handler_map.add_handler(sender)?;
+
+        // if this future gets dropped before receiving a response (e.g. due to a timeout), the
+        // stream needs to be released
+        let _pending_response = PendingResponse::new(
+            &self.response_handler_map,
+            stream,
+            self.metrics.as_ref(),
+            self.addr,
+        );
+
         self.write_sender
-            .send(Request::new(data, sender))
+            .send(Request::new(data, stream.id))
             .await
             .map_err(|_| Error::General("Connection closed when writing data!".into()))?;
 
-        receiver
+        let response = receiver
             .await
-            .map_err(|_| Error::General("Connection closed while waiting for response!".into()))?
+            .map_err(|_| Error::General("Connection closed while waiting for response!".into()))?;
+
+        // the node is responding, even if with an error
+        self.consecutive_timeouts.store(0, Ordering::Relaxed);
+        response
+    }
+
+    async fn close(&self, drain_timeout: Duration) {
+        // taking all slots waits for requests in flight to complete, while new requests queue up
+        // behind and fail when the semaphore gets closed
+        match timeout(
+            drain_timeout,
+            self.in_flight_requests
+                .acquire_many(self.max_in_flight_requests),
+        )
+        .await
+        {
+            Ok(Ok(permits)) => permits.forget(),
+            // already closed
+            Ok(Err(_)) => return,
+            Err(_) => {
+                warn!(address = %self.addr, "Timeout waiting for requests in flight - closing connection anyway.");
+            }
+        }
+
+        self.in_flight_requests.close();
+        self.is_broken.store(true, Ordering::Relaxed);
+        self.processing_handle.abort();
+        self.response_handler_map
+            .signal_general_error("Connection closed!");
     }
 
     #[allow(clippy::too_many_arguments)]
     async fn start_processing<T: AsyncRead + AsyncWrite>(
+        addr: SocketAddr,
+        metrics: Arc<dyn MetricsSink + Send + Sync>,
         write_receiver: mpsc::Receiver<Request>,
+        write_sender: mpsc::Sender<Request>,
+        heartbeat: Option<HeartbeatConfig>,
         event_handler: Option<mpsc::Sender<Frame>>,
         error_handler: Option<mpsc::Sender<Error>>,
         read_half: ReadHalf<T>,
         write_half: WriteHalf<T>,
         keyspace_holder: Arc<KeyspaceHolder>,
         is_broken: Arc<AtomicBool>,
-        compression: Compression,
+        compression: Arc<Atomic<Compression>>,
+        response_handler_map: Arc<ResponseHandlerMap>,
     ) {
-        let response_handler_map = ResponseHandlerMap::new();
+        // protocol v5 connections switch to segment-based framing after startup
+        let is_segmented = AtomicBool::new(false);
+        let activity = ConnectionActivity::default();
 
         let writer = Self::start_writing(
             write_receiver,
-            BufWriter::new(write_half),
+            BufWriter::new(MeteredIo::new(write_half, addr, metrics.as_ref())),
             &response_handler_map,
+            &compression,
+            &is_segmented,
         );
 
         let reader = Self::start_reading(
-            BufReader::new(read_half),
+            BufReader::new(MeteredIo::new(read_half, addr, metrics.as_ref())),
             event_handler,
-            compression,
+            &compression,
             keyspace_holder,
             &response_handler_map,
+            &is_segmented,
+            &activity,
+        );
+
+        let heartbeat = Self::start_heartbeat(
+            heartbeat,
+            write_sender,
+            &response_handler_map,
+            &activity,
+            metrics.as_ref(),
+            addr,
         );
 
-        let result = tokio::try_join!(writer, reader);
+        let result = tokio::try_join!(writer, reader, heartbeat);
         if let Err(error) = result {
             error!(%error, "Transport error!");
 
@@ -304,70 +545,207 @@ impl AsyncTransport {
     }
 
     async fn start_reading(
-        mut read_half: impl AsyncRead + Unpin,
+        mut read_half: impl AsyncBufRead + Unpin,
         event_handler: Option<mpsc::Sender<Frame>>,
-        compression: Compression,
+        compression: &Atomic<Compression>,
         keyspace_holder: Arc<KeyspaceHolder>,
         response_handler_map: &ResponseHandlerMap,
+        is_segmented: &AtomicBool,
+        activity: &ConnectionActivity,
     ) -> Result<()> {
+        let mut segment_decoder = SegmentFrameDecoder::new();
+
         loop {
-            let result = parse_frame(&mut read_half, compression).await;
-            match result {
-                Ok(frame) => {
-                    if frame.stream_id >= 0 {
-                        // in case we get a SetKeyspace result, we need to store current keyspace
-                        // checks are done manually for speed
-                        if frame.opcode == Opcode::Result {
-                            let result_kind = ResultKind::from_bytes(&frame.body[..INT_LEN])?;
-                            if result_kind == ResultKind::SetKeyspace {
-                                let response_body = frame.response_body()?;
-                                let set_keyspace =
-                                    response_body.into_set_keyspace().ok_or_else(|| {
-                                        Error::General(
-                                            "SetKeyspace not found with SetKeyspace opcode!".into(),
-                                        )
-                                    })?;
-
-                                keyspace_holder.update_current_keyspace(set_keyspace.body);
-                            }
-                        }
+            // compression can change while waiting for the next frame, e.g. after negotiating it
+            // with the server, so it needs to be checked once the data arrives
+            read_half.fill_buf().await?;
 
-                        // normal response to query
-                        response_handler_map.send_response(frame.stream_id, Ok(frame))?;
-                    } else if frame.stream_id == EVENT_STREAM_ID {
-                        // server event
-                        if let Some(event_handler) = &event_handler {
-                            let _ = event_handler.send(frame).await;
-                        }
-                    }
+            let compression = compression.load(Ordering::Relaxed);
+            if is_segmented.load(Ordering::Relaxed) {
+                let frames =
+                    parse_segment_frames(&mut read_half, compression, &mut segment_decoder).await?;
+
+                for frame in frames {
+                    activity.mark(frame.version);
+
+                    Self::process_frame(
+                        frame,
+                        &event_handler,
+                        &keyspace_holder,
+                        response_handler_map,
+                    )
+                    .await?;
                 }
-                Err(error) => return Err(error),
+            } else {
+                let frame = parse_raw_frame(&mut read_half, compression).await?;
+                activity.mark(frame.version);
+
+                // READY and AUTHENTICATE are the last frames sent without segments - the whole
+                // authentication exchange is already segmented; the flag needs to be set before
+                // passing the response further, since the writer will start sending segments
+                // immediately after
+                if frame.version >= Version::V5
+                    && (frame.opcode == Opcode::Ready || frame.opcode == Opcode::Authenticate)
+                {
+                    is_segmented.store(true, Ordering::Relaxed);
+                }
+
+                Self::process_frame(
+                    frame,
+                    &event_handler,
+                    &keyspace_holder,
+                    response_handler_map,
+                )
+                .await?;
             }
         }
     }
 
+    /// Periodically sends OPTIONS on an idle connection and fails if there's no response in time.
+    async fn start_heartbeat(
+        heartbeat: Option<HeartbeatConfig>,
+        write_sender: mpsc::Sender<Request>,
+        response_handler_map: &ResponseHandlerMap,
+        activity: &ConnectionActivity,
+        metrics: &(dyn MetricsSink + Send + Sync),
+        addr: SocketAddr,
+    ) -> Result<()> {
+        let heartbeat = match heartbeat {
+            Some(heartbeat) => heartbeat,
+            None => return future::pending().await,
+        };
+
+        loop {
+            sleep(heartbeat.interval).await;
+
+            if activity.is_active.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+
+            trace!("Sending heartbeat.");
+
+            let (sender, receiver) = oneshot::channel();
+            let frame = Frame::new_req_options(activity.version.load(Ordering::Relaxed));
+            let stream = response_handler_map.add_handler(sender)?;
+
+            let _pending_response =
+                PendingResponse::new(response_handler_map, stream, metrics, addr);
+
+            write_sender
+                .send(Request::new(
+                    frame.encode_with(Compression::None)?,
+                    stream.id,
+                ))
+                .await
+                .map_err(|_| Error::General("Connection closed when writing data!".into()))?;
+
+            match timeout(heartbeat.timeout, receiver).await {
+                Ok(Ok(Ok(response))) if response.opcode == Opcode::Supported => {}
+                Ok(Ok(Ok(response))) => {
+                    return Err(Error::General(format!(
+                        "Unexpected heartbeat response: {}",
+                        response.opcode
+                    )))
+                }
+                Ok(Ok(Err(error))) => return Err(error),
+                Ok(Err(_)) => {
+                    return Err(Error::General(
+                        "Connection closed while waiting for heartbeat response!".into(),
+                    ))
+                }
+                Err(_) => {
+                    return Err(Error::Timeout(
+                        "Timeout waiting for heartbeat response!".into(),
+                    ))
+                }
+            }
+        }
+    }
+
+    async fn process_frame(
+        frame: Frame,
+        event_handler: &Option<mpsc::Sender<Frame>>,
+        keyspace_holder: &KeyspaceHolder,
+        response_handler_map: &ResponseHandlerMap,
+    ) -> Result<()> {
+        if frame.stream_id >= 0 {
+            // in case we get a SetKeyspace result, we need to store current keyspace
+            // checks are done manually for speed
+            if frame.opcode == Opcode::Result {
+                let result_kind = ResultKind::from_bytes(&frame.body[..INT_LEN])?;
+                if result_kind == ResultKind::SetKeyspace {
+                    let response_body = frame.response_body()?;
+                    let set_keyspace = response_body.into_set_keyspace().ok_or_else(|| {
+                        Error::General("SetKeyspace not found with SetKeyspace opcode!".into())
+                    })?;
+
+                    keyspace_holder.update_current_keyspace(set_keyspace.body);
+                }
+            }
+
+            // normal response to query
+            response_handler_map
+                .send_response(frame.stream_id, convert_frame_into_result(frame))?;
+        } else if frame.stream_id == EVENT_STREAM_ID {
+            // server event
+            if let Some(event_handler) = event_handler {
+                let _ = event_handler.send(frame).await;
+            }
+        }
+
+        Ok(())
+    }
+
     async fn start_writing(
         mut write_receiver: mpsc::Receiver<Request>,
         mut write_half: impl AsyncWrite + Unpin,
         response_handler_map: &ResponseHandlerMap,
+        compression: &Atomic<Compression>,
+        is_segmented: &AtomicBool,
     ) -> Result<()> {
+        let mut segment_payload = vec![];
+
         while let Some(mut request) = write_receiver.recv().await {
             loop {
-                let stream_id = response_handler_map.next_stream_id();
+                let stream_id = request.stream_id;
+
+                // load current settings for each request, since they can change between requests
+                let is_segmented = is_segmented.load(Ordering::Relaxed);
+                let compression = compression.load(Ordering::Relaxed);
 
-                request.set_stream_id(stream_id);
-                response_handler_map.add_handler(stream_id, request.handler);
+                let result = if is_segmented {
+                    Self::write_segment_data(
+                        &mut write_half,
+                        &request.data,
+                        &mut segment_payload,
+                        compression,
+                    )
+                    .await
+                } else {
+                    write_half
+                        .write_all(&request.data)
+                        .await
+                        .map_err(Into::into)
+                };
 
-                if let Err(error) = write_half.write_all(&request.data).await {
-                    response_handler_map.send_response(stream_id, Err(error.into()))?;
+                if let Err(error) = result {
+                    response_handler_map.send_response(stream_id, Err(error))?;
                     return Err(Error::General("Write channel failure!".into()));
                 }
 
                 request = match write_receiver.try_recv() {
                     Ok(request) => request,
                     Err(_) => {
-                        if let Err(error) = write_half.flush().await {
-                            response_handler_map.send_response(stream_id, Err(error.into()))?;
+                        let result = if is_segmented {
+                            Self::flush_segment(&mut write_half, &mut segment_payload, compression)
+                                .await
+                        } else {
+                            Ok(())
+                        };
+
+                        if let Err(error) = result.and(write_half.flush().await.map_err(Into::into))
+                        {
+                            response_handler_map.send_response(stream_id, Err(error))?;
                             return Err(Error::General("Write channel failure!".into()));
                         }
 
@@ -379,38 +757,209 @@ impl AsyncTransport {
 
         Ok(())
     }
+
+    /// Packs given frame data into segments. Small frames are accumulated in a single
+    /// self-contained segment, while large ones get split into multiple segments.
+    async fn write_segment_data(
+        write_half: &mut (impl AsyncWrite + Unpin),
+        data: &[u8],
+        segment_payload: &mut Vec<u8>,
+        compression: Compression,
+    ) -> Result<()> {
+        if segment_payload.len() + data.len() > MAX_PAYLOAD_LEN {
+            Self::flush_segment(write_half, segment_payload, compression).await?;
+        }
+
+        if data.len() > MAX_PAYLOAD_LEN {
+            for segment in Segment::split_frame(data) {
+                write_half
+                    .write_all(&segment.encode_with(compression)?)
+                    .await?;
+            }
+        } else {
+            segment_payload.extend_from_slice(data);
+        }
+
+        Ok(())
+    }
+
+    async fn flush_segment(
+        write_half: &mut (impl AsyncWrite + Unpin),
+        segment_payload: &mut Vec<u8>,
+        compression: Compression,
+    ) -> Result<()> {
+        if segment_payload.is_empty() {
+            return Ok(());
+        }
+
+        let segment = Segment::new(std::mem::take(segment_payload), true);
+        write_half
+            .write_all(&segment.encode_with(compression)?)
+            .await
+            .map_err(Into::into)
+    }
 }
 
 type ResponseHandler = oneshot::Sender<Result<Frame>>;
 
+/// Number of request timeouts in a row, after which the connection is considered broken. A node
+/// which stopped responding never frees stream ids of timed out requests, so the connection needs
+/// to be replaced.
+const MAX_CONSECUTIVE_TIMEOUTS: usize = 16;
+
+/// Number of released streams still waiting for a response, after which the connection is
+/// considered defunct. Such streams keep their ids, so a node which stopped responding would
+/// eventually exhaust all ids without ever failing the connection.
+const MAX_ORPHANED_STREAMS: usize = 256;
+
+const STREAM_ID_WORDS: usize = MAX_STREAM_IDS / 64;
+
+/// Set of stream ids in use by a connection.
+struct StreamIds {
+    used: [u64; STREAM_ID_WORDS],
+    // word to start looking for free ids from
+    hint: usize,
+}
+
+impl Default for StreamIds {
+    fn default() -> Self {
+        StreamIds {
+            used: [0; STREAM_ID_WORDS],
+            hint: 0,
+        }
+    }
+}
+
+impl StreamIds {
+    fn allocate(&mut self) -> Option<StreamId> {
+        for offset in 0..STREAM_ID_WORDS {
+            let index = (self.hint + offset) % STREAM_ID_WORDS;
+            let word = self.used[index];
+            if word != u64::MAX {
+                let bit = (!word).trailing_zeros() as usize;
+                self.used[index] |= 1 << bit;
+                self.hint = index;
+
+                return Some((index * 64 + bit) as StreamId);
+            }
+        }
+
+        None
+    }
+
+    fn free(&mut self, stream_id: StreamId) {
+        let stream_id = stream_id as usize;
+        self.used[stream_id / 64] &= !(1 << (stream_id % 64));
+    }
+}
+
+/// Stream assigned to a single request. Stream ids get reused as soon as a response arrives, so
+/// the generation tells apart consecutive requests using the same id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stream {
+    id: StreamId,
+    generation: u64,
+}
+
+struct StreamHandler {
+    generation: u64,
+    // released streams which still wait for a response are kept without a handler, so a late
+    // response doesn't get treated as unmatched and their ids don't get reused
+    handler: Option<ResponseHandler>,
+}
+
+#[derive(Default)]
+struct StreamState {
+    handlers: FxHashMap<StreamId, StreamHandler>,
+    stream_ids: StreamIds,
+    next_generation: u64,
+    // number of handlers released before receiving a response
+    orphaned: usize,
+}
+
 struct ResponseHandlerMap {
-    stream_handlers: Mutex<FxHashMap<StreamId, ResponseHandler>>,
-    available_stream_id: AtomicI16,
+    state: Mutex<StreamState>,
 }
 
 impl ResponseHandlerMap {
     #[inline]
     pub fn new() -> Self {
         ResponseHandlerMap {
-            stream_handlers: Default::default(),
-            available_stream_id: AtomicI16::new(INITIAL_STREAM_ID),
+            state: Default::default(),
         }
     }
 
-    #[inline]
-    pub fn add_handler(&self, stream_id: StreamId, handler: ResponseHandler) {
-        self.stream_handlers
-            .lock()
-            .unwrap()
-            .insert(stream_id, handler);
+    /// Assigns a free stream id to given handler.
+    pub fn add_handler(&self, handler: ResponseHandler) -> Result<Stream> {
+        let mut state = self.state.lock().unwrap();
+        let id = state
+            .stream_ids
+            .allocate()
+            .ok_or_else(|| Error::General("No free stream ids available!".into()))?;
+
+        let generation = state.next_generation;
+        state.next_generation += 1;
+
+        state.handlers.insert(
+            id,
+            StreamHandler {
+                generation,
+                handler: Some(handler),
+            },
+        );
+
+        Ok(Stream { id, generation })
+    }
+
+    /// Releases the handler for given stream, if it's still waiting for a response. Does nothing
+    /// if the id has already been reused by another stream.
+    pub fn release(&self, stream: Stream) {
+        let mut state = self.state.lock().unwrap();
+        let released = match state.handlers.get_mut(&stream.id) {
+            Some(handler) if handler.generation == stream.generation => {
+                handler.handler.take().is_some()
+            }
+            _ => false,
+        };
+
+        if released {
+            state.orphaned += 1;
+            if state.orphaned == MAX_ORPHANED_STREAMS {
+                warn!(
+                    orphaned = state.orphaned,
+                    "Too many requests without a response - marking connection as defunct."
+                );
+            }
+        }
+    }
+
+    /// Checks if too many released streams still wait for a response, which means the other side
+    /// probably stopped responding.
+    pub fn is_defunct(&self) -> bool {
+        self.state.lock().unwrap().orphaned >= MAX_ORPHANED_STREAMS
     }
 
     pub fn send_response(&self, stream_id: StreamId, response: Result<Frame>) -> Result<()> {
-        match self.stream_handlers.lock().unwrap().remove(&stream_id) {
-            Some(handler) => {
+        let mut state = self.state.lock().unwrap();
+        let handler = state.handlers.remove(&stream_id);
+        match &handler {
+            Some(StreamHandler { handler: None, .. }) => {
+                state.stream_ids.free(stream_id);
+                state.orphaned -= 1;
+            }
+            Some(_) => state.stream_ids.free(stream_id),
+            None => {}
+        }
+
+        drop(state);
+
+        match handler.map(|handler| handler.handler) {
+            Some(Some(handler)) => {
                 let _ = handler.send(response);
                 Ok(())
             }
+            // response to a released stream
+            Some(None) => Ok(()),
             // unmatched stream - probably a bug somewhere
             None => Err(Error::General(format!(
                 "Unmatched stream id: {}",
@@ -420,40 +969,542 @@ impl ResponseHandlerMap {
     }
 
     pub fn signal_general_error(&self, error: &str) {
-        for (_, handler) in self.stream_handlers.lock().unwrap().drain() {
-            let _ = handler.send(Err(Error::General(error.to_string())));
+        let mut state = self.state.lock().unwrap();
+        let StreamState {
+            handlers,
+            stream_ids,
+            orphaned,
+            ..
+        } = &mut *state;
+
+        *orphaned = 0;
+
+        for (stream_id, handler) in handlers.drain() {
+            stream_ids.free(stream_id);
+
+            if let Some(handler) = handler.handler {
+                let _ = handler.send(Err(Error::General(error.to_string())));
+            }
         }
     }
+}
 
-    pub fn next_stream_id(&self) -> StreamId {
-        loop {
-            let stream = self.available_stream_id.fetch_add(1, Ordering::Relaxed);
-            if stream < 0 {
-                match self.available_stream_id.compare_exchange(
-                    stream,
-                    INITIAL_STREAM_ID,
-                    Ordering::Relaxed,
-                    Ordering::Relaxed,
-                ) {
-                    Ok(_) => return INITIAL_STREAM_ID,
-                    Err(_) => continue,
-                }
-            }
+/// Configuration of heartbeats sent on idle connections to detect dead sockets, e.g. half-open
+/// connections dropped by NATs or load balancers. A connection is idle when it doesn't receive
+/// any frames during the heartbeat interval. If the response to a heartbeat doesn't arrive before
+/// the timeout, the connection is marked as broken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Constructor)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig {
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+struct ConnectionActivity {
+    // was there any frame received since the last heartbeat check
+    is_active: AtomicBool,
+    // protocol version of the last received frame, used for heartbeats
+    version: Atomic<Version>,
+}
+
+impl Default for ConnectionActivity {
+    fn default() -> Self {
+        ConnectionActivity {
+            is_active: AtomicBool::new(false),
+            version: Atomic::new(Version::V4),
+        }
+    }
+}
+
+impl ConnectionActivity {
+    #[inline]
+    fn mark(&self, version: Version) {
+        self.is_active.store(true, Ordering::Relaxed);
+        self.version.store(version, Ordering::Relaxed);
+    }
+}
 
-            return stream;
+struct PendingResponse<'a> {
+    response_handler_map: &'a ResponseHandlerMap,
+    stream: Stream,
+    metrics: &'a (dyn MetricsSink + Send + Sync),
+    addr: SocketAddr,
+}
+
+impl<'a> PendingResponse<'a> {
+    fn new(
+        response_handler_map: &'a ResponseHandlerMap,
+        stream: Stream,
+        metrics: &'a (dyn MetricsSink + Send + Sync),
+        addr: SocketAddr,
+    ) -> Self {
+        metrics.record_stream_acquired(addr);
+
+        PendingResponse {
+            response_handler_map,
+            stream,
+            metrics,
+            addr,
         }
     }
 }
 
-#[derive(Constructor)]
+impl Drop for PendingResponse<'_> {
+    fn drop(&mut self) {
+        self.response_handler_map.release(self.stream);
+        self.metrics.record_stream_released(self.addr);
+    }
+}
+
+/// Reports the number of bytes read or written through given IO to metrics.
+struct MeteredIo<'a, T> {
+    io: T,
+    addr: SocketAddr,
+    metrics: &'a (dyn MetricsSink + Send + Sync),
+}
+
+impl<'a, T> MeteredIo<'a, T> {
+    fn new(io: T, addr: SocketAddr, metrics: &'a (dyn MetricsSink + Send + Sync)) -> Self {
+        MeteredIo { io, addr, metrics }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for MeteredIo<'_, T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let previous_len = buf.filled().len();
+        let result = Pin::new(&mut self.io).poll_read(cx, buf);
+
+        let read = buf.filled().len() - previous_len;
+        if read > 0 {
+            self.metrics.record_bytes_received(self.addr, read);
+        }
+
+        result
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for MeteredIo<'_, T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.io).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = result {
+            self.metrics.record_bytes_sent(self.addr, written);
+        }
+
+        result
+    }
+
+    #[inline]
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
 struct Request {
     data: Vec<u8>,
-    handler: ResponseHandler,
+    stream_id: StreamId,
 }
 
 impl Request {
     #[inline]
-    fn set_stream_id(&mut self, stream_d: StreamId) {
-        self.data[2..4].copy_from_slice(&stream_d.to_be_bytes());
+    fn new(mut data: Vec<u8>, stream_id: StreamId) -> Self {
+        data[2..4].copy_from_slice(&stream_id.to_be_bytes());
+        Request { data, stream_id }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AsyncTransport, HeartbeatConfig, ResponseHandlerMap, MAX_CONSECUTIVE_TIMEOUTS,
+        MAX_ORPHANED_STREAMS, MAX_STREAM_IDS,
+    };
+    use crate::cluster::KeyspaceHolder;
+    use crate::frame_parser::parse_segment_frames;
+    use crate::metrics::NoopMetricsSink;
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::frame::segment::{Segment, SegmentFrameDecoder};
+    use cassandra_protocol::frame::{Direction, Flags, Frame, Opcode, Version};
+    use cassandra_protocol::types::CBytes;
+    use std::collections::HashSet;
+    use std::convert::TryInto;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{duplex, split, AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::{mpsc, oneshot, watch};
+    use tokio::time::timeout;
+
+    #[test]
+    fn should_ignore_response_to_released_stream() {
+        let map = ResponseHandlerMap::new();
+        let (sender, mut receiver) = oneshot::channel();
+
+        let stream = map.add_handler(sender).unwrap();
+        map.release(stream);
+
+        assert!(map
+            .send_response(stream.id, Err("late response".into()))
+            .is_ok());
+        assert!(receiver.try_recv().is_err());
+        assert!(map.state.lock().unwrap().handlers.is_empty());
+
+        // the stream is forgotten after the late response
+        assert!(map
+            .send_response(stream.id, Err("unmatched response".into()))
+            .is_err());
+    }
+
+    #[test]
+    fn should_not_reuse_stream_ids_in_use() {
+        let map = ResponseHandlerMap::new();
+
+        let streams: Vec<_> = (0..MAX_STREAM_IDS)
+            .map(|_| map.add_handler(oneshot::channel().0).unwrap())
+            .collect();
+        let stream_ids: Vec<_> = streams.iter().map(|stream| stream.id).collect();
+
+        assert!(stream_ids.iter().all(|stream_id| *stream_id >= 0));
+        assert_eq!(
+            stream_ids.iter().collect::<HashSet<_>>().len(),
+            MAX_STREAM_IDS
+        );
+        assert!(map.add_handler(oneshot::channel().0).is_err());
+
+        map.release(streams[100]);
+        assert!(map.add_handler(oneshot::channel().0).is_err());
+
+        map.send_response(100, Err("late response".into())).unwrap();
+        assert_eq!(map.add_handler(oneshot::channel().0).unwrap().id, 100);
+    }
+
+    #[test]
+    fn should_not_release_reused_stream_id() {
+        let map = ResponseHandlerMap::new();
+
+        let (sender, _receiver) = oneshot::channel();
+        let first = map.add_handler(sender).unwrap();
+        map.send_response(first.id, Err("response".into())).unwrap();
+
+        let (sender, mut receiver) = oneshot::channel();
+        let second = map.add_handler(sender).unwrap();
+        assert_eq!(first.id, second.id);
+
+        // the guard of the first request gets dropped after its id got reused
+        map.release(first);
+
+        map.send_response(second.id, Err("response".into()))
+            .unwrap();
+        assert!(receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn should_become_defunct_with_too_many_orphaned_streams() {
+        let map = ResponseHandlerMap::new();
+
+        let streams: Vec<_> = (0..MAX_ORPHANED_STREAMS)
+            .map(|_| map.add_handler(oneshot::channel().0).unwrap())
+            .collect();
+
+        for stream in &streams[1..] {
+            map.release(*stream);
+        }
+
+        assert!(!map.is_defunct());
+
+        // releasing the same stream twice doesn't count
+        map.release(streams[1]);
+        assert!(!map.is_defunct());
+
+        map.release(streams[0]);
+        assert!(map.is_defunct());
+
+        // a late response means the other side is still alive
+        map.send_response(streams[0].id, Err("late response".into()))
+            .unwrap();
+        assert!(!map.is_defunct());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn should_handle_concurrent_requests() {
+        let (client, server) = duplex(64 * 1024);
+        let (read_half, write_half) = split(client);
+
+        // responds to OPTIONS with an empty SUPPORTED
+        tokio::spawn(async move {
+            let (mut server_read, mut server_write) = split(server);
+            let mut header = [0; 9];
+
+            while server_read.read_exact(&mut header).await.is_ok() {
+                let length = u32::from_be_bytes(header[5..9].try_into().unwrap()) as usize;
+                let mut body = vec![0; length];
+                if server_read.read_exact(&mut body).await.is_err() {
+                    break;
+                }
+
+                let response = [
+                    0x84, 0, header[2], header[3], 0x06, // SUPPORTED
+                    0, 0, 0, 2, // length
+                    0, 0, // empty multimap
+                ];
+                if server_write.write_all(&response).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let transport = Arc::new(AsyncTransport::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042),
+            Compression::None,
+            1024,
+            1024,
+            None,
+            Arc::new(NoopMetricsSink),
+            read_half,
+            write_half,
+            None,
+            None,
+            Arc::new(KeyspaceHolder::new(watch::channel(None).0)),
+        ));
+
+        let requests = (0..64).map(|_| {
+            let transport = transport.clone();
+            tokio::spawn(async move {
+                for _ in 0..100 {
+                    transport
+                        .write_frame(&Frame::new_req_options(Version::V4))
+                        .await
+                        .unwrap();
+                }
+            })
+        });
+
+        timeout(
+            Duration::from_secs(10),
+            futures::future::try_join_all(requests),
+        )
+        .await
+        .expect("requests should not hang")
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_segment_authentication_exchange() {
+        let (client, server) = duplex(1024);
+        let (read_half, write_half) = split(client);
+
+        let server = tokio::spawn(async move {
+            let (mut server_read, mut server_write) = split(server);
+
+            // STARTUP is sent without segments
+            let mut header = [0; 9];
+            server_read.read_exact(&mut header).await.unwrap();
+            assert_eq!(header[4], u8::from(Opcode::Startup));
+
+            let length = u32::from_be_bytes(header[5..9].try_into().unwrap()) as usize;
+            let mut body = vec![0; length];
+            server_read.read_exact(&mut body).await.unwrap();
+
+            let authenticator = b"org.apache.cassandra.auth.PasswordAuthenticator";
+            let mut response = vec![
+                0x85,
+                0,
+                header[2],
+                header[3],
+                u8::from(Opcode::Authenticate),
+            ];
+            response.extend_from_slice(&(authenticator.len() as u32 + 2).to_be_bytes());
+            response.extend_from_slice(&(authenticator.len() as u16).to_be_bytes());
+            response.extend_from_slice(authenticator);
+            server_write.write_all(&response).await.unwrap();
+
+            // the rest of the exchange uses segments
+            let mut decoder = SegmentFrameDecoder::new();
+            let frames = parse_segment_frames(&mut server_read, Compression::None, &mut decoder)
+                .await
+                .unwrap();
+            assert_eq!(frames.len(), 1);
+            assert_eq!(frames[0].opcode, Opcode::AuthResponse);
+
+            let auth_success = Frame::new(
+                Version::V5,
+                Direction::Response,
+                Flags::empty(),
+                Opcode::AuthSuccess,
+                frames[0].stream_id,
+                vec![0xff, 0xff, 0xff, 0xff].into(),
+                None,
+                vec![],
+                Default::default(),
+            );
+            let segment = Segment::new(auth_success.encode_with(Compression::None).unwrap(), true);
+            server_write
+                .write_all(&segment.encode_with(Compression::None).unwrap())
+                .await
+                .unwrap();
+        });
+
+        let transport = AsyncTransport::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042),
+            Compression::None,
+            16,
+            16,
+            None,
+            Arc::new(NoopMetricsSink),
+            read_half,
+            write_half,
+            None,
+            None,
+            Arc::new(KeyspaceHolder::new(watch::channel(None).0)),
+        );
+
+        let response = transport
+            .write_frame(&Frame::new_req_startup(None, Version::V5))
+            .await
+            .unwrap();
+        assert_eq!(response.opcode, Opcode::Authenticate);
+
+        let response = timeout(
+            Duration::from_secs(5),
+            transport.write_frame(&Frame::new_req_auth_response(
+                CBytes::new(b"\0user\0password".to_vec()),
+                Version::V5,
+            )),
+        )
+        .await
+        .expect("authentication should not hang")
+        .unwrap();
+        assert_eq!(response.opcode, Opcode::AuthSuccess);
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_break_connection_without_heartbeat_response() {
+        // the server side never responds
+        let (client, _server) = duplex(1024);
+        let (read_half, write_half) = split(client);
+        let (error_sender, mut error_receiver) = mpsc::channel(1);
+
+        let transport = AsyncTransport::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042),
+            Compression::None,
+            16,
+            16,
+            Some(HeartbeatConfig::new(
+                Duration::from_millis(10),
+                Duration::from_millis(10),
+            )),
+            Arc::new(NoopMetricsSink),
+            read_half,
+            write_half,
+            None,
+            Some(error_sender),
+            Arc::new(KeyspaceHolder::new(watch::channel(None).0)),
+        );
+
+        assert!(!transport.is_broken());
+        assert!(error_receiver.recv().await.is_some());
+        assert!(transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_break_connection_after_consecutive_timeouts() {
+        // the server side never responds
+        let (client, _server) = duplex(1024);
+        let (read_half, write_half) = split(client);
+
+        let transport = AsyncTransport::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042),
+            Compression::None,
+            16,
+            16,
+            None,
+            Arc::new(NoopMetricsSink),
+            read_half,
+            write_half,
+            None,
+            None,
+            Arc::new(KeyspaceHolder::new(watch::channel(None).0)),
+        );
+
+        for _ in 1..MAX_CONSECUTIVE_TIMEOUTS {
+            assert!(timeout(
+                Duration::from_millis(1),
+                transport.write_frame(&Frame::new_req_options(Version::V4))
+            )
+            .await
+            .is_err());
+
+            transport.report_timeout();
+        }
+
+        assert!(!transport.is_broken());
+
+        transport.report_timeout();
+        assert!(transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_fail_requests_in_flight_after_drain_timeout() {
+        // the server side never responds
+        let (client, _server) = duplex(1024);
+        let (read_half, write_half) = split(client);
+
+        let transport = Arc::new(AsyncTransport::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042),
+            Compression::None,
+            16,
+            16,
+            None,
+            Arc::new(NoopMetricsSink),
+            read_half,
+            write_half,
+            None,
+            None,
+            Arc::new(KeyspaceHolder::new(watch::channel(None).0)),
+        ));
+
+        let request = tokio::spawn({
+            let transport = transport.clone();
+            async move {
+                transport
+                    .write_frame(&Frame::new_req_options(Version::V4))
+                    .await
+            }
+        });
+
+        while transport.in_flight_requests.available_permits() == 16 {
+            tokio::task::yield_now().await;
+        }
+
+        transport.close(Duration::from_millis(10)).await;
+
+        assert!(request.await.unwrap().is_err());
+        assert!(transport.is_broken());
+        assert!(transport
+            .write_frame(&Frame::new_req_options(Version::V4))
+            .await
+            .is_err());
     }
 }
@@ -10,6 +10,8 @@ use cdrs_tokio::types::blob::Blob;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::types::decimal::Decimal;
 #[cfg(feature = "e2e-tests")]
+use cdrs_tokio::types::duration::Duration;
+#[cfg(feature = "e2e-tests")]
 use cdrs_tokio::types::map::Map;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::types::value::Bytes;
@@ -331,7 +333,7 @@ async fn uuid() {
     }
 }
 
-// TODO date, time, duration
+// TODO date, time
 #[tokio::test]
 #[cfg(feature = "e2e-tests")]
 async fn time() {
@@ -405,3 +407,36 @@ async fn inet() {
         assert_eq!(my_inet_v6_row, my_inet_v6);
     }
 }
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn duration() {
+    let cql = "CREATE TABLE IF NOT EXISTS cdrs_test.test_duration \
+               (my_id int PRIMARY KEY, my_duration duration)";
+    let session = setup(cql).await.expect("setup");
+
+    let my_duration = Duration::new(1, 2, 3_000_000_000);
+    let values = query_values!(1, my_duration);
+
+    let query = "INSERT INTO cdrs_test.test_duration (my_id, my_duration) VALUES (?, ?)";
+    session
+        .query_with_values(query, values)
+        .await
+        .expect("insert duration error");
+
+    let query = "SELECT * FROM cdrs_test.test_duration";
+    let rows = session
+        .query(query)
+        .await
+        .expect("query duration error")
+        .response_body()
+        .expect("get body with duration error")
+        .into_rows()
+        .expect("converting body with duration into rows error");
+
+    assert_eq!(rows.len(), 1);
+    for row in rows {
+        let my_duration_row: Duration = row.get_r_by_name("my_duration").expect("my_duration");
+        assert_eq!(my_duration_row, my_duration);
+    }
+}
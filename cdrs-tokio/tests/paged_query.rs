@@ -9,6 +9,10 @@ use cdrs_tokio::load_balancing::RoundRobinLoadBalancingStrategy;
 #[cfg(feature = "e2e-tests")]
 use cdrs_tokio::retry::NeverReconnectionPolicy;
 #[cfg(feature = "e2e-tests")]
+use cdrs_tokio::types::IntoRustByName;
+#[cfg(feature = "e2e-tests")]
+use futures::StreamExt;
+#[cfg(feature = "e2e-tests")]
 use std::sync::Arc;
 
 #[tokio::test]
@@ -67,3 +71,55 @@ async fn paged_query() {
 
     assert!(!query_pager.has_more());
 }
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn paged_query_stream() {
+    let cluster_config = NodeTcpConfigBuilder::new()
+        .with_contact_point("127.0.0.1:9042".into())
+        .with_authenticator_provider(Arc::new(NoneAuthenticatorProvider))
+        .build()
+        .await
+        .unwrap();
+    let lb = RoundRobinLoadBalancingStrategy::new();
+    let session = TcpSessionBuilder::new(lb, cluster_config)
+        .with_reconnection_policy(Arc::new(NeverReconnectionPolicy::default()))
+        .build();
+
+    session
+        .query(
+            "CREATE KEYSPACE IF NOT EXISTS test_ks WITH REPLICATION = { \
+                                       'class' : 'SimpleStrategy', 'replication_factor' : 1 };",
+        )
+        .await
+        .expect("Keyspace creation error");
+
+    session
+        .query("create table if not exists test_ks.stream_user (user_id int primary key)")
+        .await
+        .expect("Could not create table");
+
+    for i in 0..=9 {
+        session
+            .query(format!(
+                "insert into test_ks.stream_user(user_id) values ({})",
+                i
+            ))
+            .await
+            .expect("Could not insert");
+    }
+
+    let mut pager = session.paged(3).with_prefetch_depth(2);
+    let mut ids: Vec<i32> = pager
+        .query("SELECT * FROM test_ks.stream_user")
+        .into_stream()
+        .map(|row| {
+            row.and_then(|row| IntoRustByName::<i32>::get_r_by_name(&row, "user_id"))
+                .expect("row")
+        })
+        .collect()
+        .await;
+
+    ids.sort_unstable();
+    assert_eq!(ids, (0..=9).collect::<Vec<_>>());
+}
@@ -57,3 +57,55 @@ async fn query_values_in() {
         "should find at least one element for each criteria"
     );
 }
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn query_prepared() {
+    let cql = "CREATE TABLE IF NOT EXISTS cdrs_test.test_query_prepared \
+             (id text PRIMARY KEY)";
+    let session = setup(cql).await.expect("setup");
+
+    let query_insert = "INSERT INTO cdrs_test.test_query_prepared (id) VALUES (?)";
+
+    let first = session
+        .prepare_cached(query_insert)
+        .await
+        .expect("prepare error");
+    let (second, third) = tokio::join!(
+        session.prepare_cached(query_insert),
+        session.prepare_cached(query_insert)
+    );
+
+    assert!(std::sync::Arc::ptr_eq(
+        &first,
+        &second.expect("prepare error")
+    ));
+    assert!(std::sync::Arc::ptr_eq(
+        &first,
+        &third.expect("prepare error")
+    ));
+
+    for item in ["1", "2"] {
+        session
+            .query_prepared(query_insert, query_values!(item))
+            .await
+            .expect("insert item error");
+    }
+
+    let rows = session
+        .query_prepared(
+            "SELECT * FROM cdrs_test.test_query_prepared WHERE id = ?",
+            query_values!("2"),
+        )
+        .await
+        .expect("select error")
+        .response_body()
+        .expect("get body error")
+        .into_rows()
+        .expect("converting into rows error");
+
+    assert_eq!(rows.len(), 1);
+
+    let id: String = rows[0].get_r_by_name("id").expect("id");
+    assert_eq!(id, "2");
+}
@@ -0,0 +1,46 @@
+mod common;
+
+#[cfg(feature = "e2e-tests")]
+use cdrs_tokio::statement::StatementParamsBuilder;
+#[cfg(feature = "e2e-tests")]
+use common::*;
+#[cfg(feature = "e2e-tests")]
+use std::time::Duration;
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn close_session() {
+    let session = setup_multiple(&[]).await.expect("setup");
+
+    let query = session.query("SELECT * FROM system.local");
+    let close = session.close(Duration::from_secs(5));
+
+    // the query was issued before closing, so it should complete
+    let (result, _) = tokio::join!(query, close);
+    assert!(result.is_ok());
+
+    assert!(session.is_closed());
+    assert!(session.query("SELECT * FROM system.local").await.is_err());
+
+    // closing again does nothing
+    session.close(Duration::from_secs(5)).await;
+}
+
+#[tokio::test]
+#[cfg(feature = "e2e-tests")]
+async fn query_trace() {
+    let session = setup_multiple(&[]).await.expect("setup");
+
+    let mut params = StatementParamsBuilder::new().build();
+    params.tracing = true;
+
+    let frame = session
+        .query_with_params("SELECT * FROM system.local", params)
+        .await
+        .expect("query");
+    let tracing_id = frame.tracing_id().expect("tracing id");
+
+    let trace = session.query_trace(tracing_id).await.expect("trace");
+    assert_eq!(trace.session_id, tracing_id);
+    assert!(!trace.events.is_empty());
+}